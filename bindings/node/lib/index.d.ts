@@ -9,6 +9,8 @@ export declare enum MessageType {
   Unconfirmed = 4,
   /// A value message.
   Value = 5,
+  /// A data-only (indexation) message.
+  Data = 6,
 }
 
 export declare interface TransactionEssence {
@@ -45,6 +47,11 @@ export declare interface Message {
   broadcasted: boolean;
   incoming: boolean;
   value: number;
+  index?: string;
+  data?: number[];
+  idempotencyKey?: string;
+  transferAmount?: number;
+  outputs: Output[];
 }
 
 export declare interface Address {
@@ -81,8 +88,19 @@ export declare class RemainderValueStrategy {
   static accountAddress(address: string): RemainderValueStrategy
 }
 
+/** A number of iotas or a string with an optional unit, e.g. '1.5 Mi' */
+export declare type Amount = number | string
+
+export declare interface TransferOutput {
+  address: string
+  amount: Amount
+}
+
 export declare class SyncedAccount {
-  send(address: string, amount: number, remainderValueStrategy?: RemainderValueStrategy): Promise<Message>
+  send(address: string, amount: Amount, remainderValueStrategy?: RemainderValueStrategy): Promise<Message>
+  sendBatch(outputs: TransferOutput[], remainderValueStrategy?: RemainderValueStrategy): Promise<Message>
+  sendMax(address: string): Promise<Message>
+  sendToContact(name: string, amount: Amount): Promise<Message>
   retry(messageId: string): Promise<Message>
   reattach(messageId: string): Promise<Message>
   promote(messageId: string): Promise<Message>
@@ -100,11 +118,14 @@ export declare interface ClientOptions {
   network?: Network;
   quorumSize?: number;
   quorumThreshold?: number;
+  localPow?: boolean;
+  powWorkerCount?: number;
+  powTimeoutMs?: number;
 }
 
 export declare enum SignerType {
   Stronghold = 1,
-  EnvMnemonic = 2
+  Mnemonic = 2
 }
 
 export declare interface AccountToCreate {
@@ -120,6 +141,29 @@ export declare enum StorageType {
   Sqlite = 2
 }
 
+export declare interface Contact {
+  name: string
+  address: string
+  labels?: string[]
+  note?: string
+  network?: Network
+  createdAt?: string
+  lastUsedAt?: string
+}
+
+export declare interface AllowedRecipient {
+  address: string
+  addedAt?: string
+}
+
+export declare interface SpendingPolicy {
+  maxTransactionAmount?: number
+  dailyLimit?: number
+  weeklyLimit?: number
+  allowedRecipients?: AllowedRecipient[]
+  newRecipientDelay?: number
+}
+
 export declare interface ManagerOptions {
   storagePath?: string
   storageType?: StorageType
@@ -128,13 +172,20 @@ export declare interface ManagerOptions {
 export declare class AccountManager {
   constructor(storagePath?: string)
   setStrongholdPassword(password: string): void
+  setMnemonicPassphrase(passphrase: string): void
   createAccount(account: AccountToCreate): Account
   getAccount(accountId: string | number): Account | undefined
   getAccountByAlias(alias: string): Account | undefined
   getAccounts(): Account[]
   removeAccount(accountId: string | number): void
   syncAccounts(): Promise<SyncedAccount[]>
-  internalTransfer(fromAccount: Account, toAccount: Account, amount: number): Promise<Message>
+  internalTransfer(fromAccount: Account, toAccount: Account, amount: Amount, idempotencyKey?: string): Promise<Message>
+  addContact(contact: Contact): void
+  getContact(name: string): Contact | undefined
+  getContacts(): Contact[]
+  updateContact(name: string, contact: Contact): void
+  removeContact(name: string): void
+  setSpendingPolicy(accountId: string | number, policy: SpendingPolicy, password: string): void
   backup(destination: string): string
   importAccounts(source: string): void
 }
@@ -144,6 +195,11 @@ export declare type Event = 'ErrorThrown' |
   'NewTransaction' |
   'ConfirmationStateChange' |
   'Reattachment' |
-  'Broadcast'
+  'Broadcast' |
+  'MessagePostingStarted' |
+  'MessagePostingFinished'
 
 export declare function addEventListener(event: Event, cb: (err?: any, data?: { [k: string]: any }) => void): void
+
+export declare function parseAmount(amount: string): string
+export declare function formatAmount(amount: Amount, precision?: number, unit?: string): string
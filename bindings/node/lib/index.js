@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 var addon = require('../native')
-const { AccountManager, Account, SyncedAccount, EventListener } = addon
+const { AccountManager, Account, SyncedAccount, EventListener, parseAmount, formatAmount } = addon
 
 function promisify (fn) {
   return function () {
@@ -55,6 +55,9 @@ class RemainderValueStrategy {
 
 Account.prototype.sync = promisify(Account.prototype.sync)
 SyncedAccount.prototype.send = promisify(SyncedAccount.prototype.send)
+SyncedAccount.prototype.sendBatch = promisify(SyncedAccount.prototype.sendBatch)
+SyncedAccount.prototype.sendMax = promisify(SyncedAccount.prototype.sendMax)
+SyncedAccount.prototype.sendToContact = promisify(SyncedAccount.prototype.sendToContact)
 SyncedAccount.prototype.retry = promisify(SyncedAccount.prototype.retry)
 SyncedAccount.prototype.reattach = promisify(SyncedAccount.prototype.reattach)
 SyncedAccount.prototype.promote = promisify(SyncedAccount.prototype.promote)
@@ -64,6 +67,8 @@ AccountManager.prototype.internalTransfer = promisify(AccountManager.prototype.i
 module.exports = {
   AccountManager,
   addEventListener,
+  parseAmount,
+  formatAmount,
   RemainderValueStrategy,
   StorageType: {
     Stronghold: 1,
@@ -71,6 +76,6 @@ module.exports = {
   },
   SignerType: {
     Stronghold: 1,
-    EnvMnemonic: 2
+    Mnemonic: 2
   }
 }
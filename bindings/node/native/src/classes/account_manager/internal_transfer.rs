@@ -11,6 +11,7 @@ pub struct InternalTransferTask {
     pub from_account_id: String,
     pub to_account_id: String,
     pub amount: u64,
+    pub idempotency_key: Option<String>,
 }
 
 impl Task for InternalTransferTask {
@@ -25,9 +26,23 @@ impl Task for InternalTransferTask {
             let from_account = from_account.read().unwrap();
             let to_account = crate::get_account(&self.to_account_id);
             let to_account = to_account.read().unwrap();
-            let res = manager
-                .internal_transfer(from_account.id().into(), to_account.id().into(), self.amount)
-                .await?;
+            let res = match &self.idempotency_key {
+                Some(idempotency_key) => {
+                    manager
+                        .internal_transfer_with_idempotency_key(
+                            from_account.id().into(),
+                            to_account.id().into(),
+                            self.amount,
+                            idempotency_key.clone(),
+                        )
+                        .await?
+                }
+                None => {
+                    manager
+                        .internal_transfer(from_account.id().into(), to_account.id().into(), self.amount)
+                        .await?
+                }
+            };
 
             crate::update_account(&self.from_account_id, res.from_account);
             crate::update_account(&self.to_account_id, res.to_account);
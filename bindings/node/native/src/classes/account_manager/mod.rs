@@ -10,8 +10,10 @@ use std::{
 use iota_wallet::{
     account::AccountIdentifier,
     account_manager::{AccountManager, DEFAULT_STORAGE_PATH},
+    address_book::Contact,
     client::ClientOptions,
-    signing::SignerType,
+    signing::{set_signer, SeedSigner, SignerType},
+    spending_policy::SpendingPolicy,
     storage::{sqlite::SqliteStorageAdapter, stronghold::StrongholdStorageAdapter},
     DateTime, Utc,
 };
@@ -26,7 +28,7 @@ mod sync;
 #[repr(u8)]
 pub enum AccountSignerType {
     Stronghold = 1,
-    EnvMnemonic = 2,
+    Mnemonic = 2,
 }
 
 impl Default for AccountSignerType {
@@ -120,6 +122,12 @@ declare_types! {
             Ok(cx.undefined().upcast())
         }
 
+        method setMnemonicPassphrase(mut cx) {
+            let passphrase = cx.argument::<JsString>(0)?.value();
+            set_signer(SignerType::Mnemonic, SeedSigner::new(passphrase));
+            Ok(cx.undefined().upcast())
+        }
+
         method createAccount(mut cx) {
             let account = {
                 let account_to_create = cx.argument::<JsValue>(0)?;
@@ -133,7 +141,7 @@ declare_types! {
                     .create_account(account_to_create.client_options.clone())
                     .signer_type(match account_to_create.signer_type {
                         AccountSignerType::Stronghold => SignerType::Stronghold,
-                        AccountSignerType::EnvMnemonic => SignerType::EnvMnemonic,
+                        AccountSignerType::Mnemonic => SignerType::Mnemonic,
                     });
                 if let Some(mnemonic) = &account_to_create.mnemonic {
                     builder = builder.mnemonic(mnemonic);
@@ -242,8 +250,15 @@ declare_types! {
         method internalTransfer(mut cx) {
             let from_account = cx.argument::<JsAccount>(0)?;
             let to_account = cx.argument::<JsAccount>(1)?;
-            let amount = cx.argument::<JsNumber>(2)?.value() as u64;
-            let cb = cx.argument::<JsFunction>(3)?;
+            let amount = cx.argument::<JsValue>(2)?;
+            let amount = crate::js_value_to_amount(&mut cx, amount)?;
+            let (idempotency_key, cb) = match cx.argument_opt(4) {
+                Some(arg) => {
+                    let cb = arg.downcast::<JsFunction>().or_throw(&mut cx)?;
+                    (Some(cx.argument::<JsString>(3)?.value()), cb)
+                }
+                None => (None, cx.argument::<JsFunction>(3)?),
+            };
 
             let from_account_id = {
                 let guard = cx.lock();
@@ -263,11 +278,105 @@ declare_types! {
                 from_account_id,
                 to_account_id,
                 amount,
+                idempotency_key,
             };
             task.schedule(cb);
             Ok(cx.undefined().upcast())
         }
 
+        method addContact(mut cx) {
+            let contact = cx.argument::<JsValue>(0)?;
+            let contact: Contact = neon_serde::from_value(&mut cx, contact)?;
+            let result = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.add_contact(contact)
+            };
+            match result {
+                Ok(_) => Ok(cx.undefined().upcast()),
+                Err(e) => cx.throw_error(e.to_string()),
+            }
+        }
+
+        method getContact(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let contact = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.get_contact(&name)
+            };
+            match contact {
+                Ok(contact) => Ok(neon_serde::to_value(&mut cx, &contact)?),
+                Err(_) => Ok(cx.undefined().upcast())
+            }
+        }
+
+        method getContacts(mut cx) {
+            let contacts = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.get_contacts().expect("failed to get contacts")
+            };
+            Ok(neon_serde::to_value(&mut cx, &contacts)?)
+        }
+
+        method updateContact(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let contact = cx.argument::<JsValue>(1)?;
+            let contact: Contact = neon_serde::from_value(&mut cx, contact)?;
+            let result = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.update_contact(&name, contact)
+            };
+            match result {
+                Ok(_) => Ok(cx.undefined().upcast()),
+                Err(e) => cx.throw_error(e.to_string()),
+            }
+        }
+
+        method removeContact(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let result = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.remove_contact(&name)
+            };
+            match result {
+                Ok(_) => Ok(cx.undefined().upcast()),
+                Err(e) => cx.throw_error(e.to_string()),
+            }
+        }
+
+        method setSpendingPolicy(mut cx) {
+            let id = cx.argument::<JsValue>(0)?;
+            let id = js_value_to_account_id(&mut cx, id)?;
+            let policy = cx.argument::<JsValue>(1)?;
+            let policy: SpendingPolicy = neon_serde::from_value(&mut cx, policy)?;
+            let password = cx.argument::<JsString>(2)?.value();
+            let result = {
+                let this = cx.this();
+                let guard = cx.lock();
+                let ref_ = &this.borrow(&guard).0;
+                let manager = ref_.read().unwrap();
+                manager.set_spending_policy(id, policy, password)
+            };
+            match result {
+                Ok(_) => Ok(cx.undefined().upcast()),
+                Err(e) => cx.throw_error(e.to_string()),
+            }
+        }
+
         method backup(mut cx) {
             let backup_path = cx.argument::<JsString>(0)?.value();
             let destination = {
@@ -10,7 +10,8 @@ use std::{
 };
 
 use iota_wallet::event::{
-    on_balance_change, on_broadcast, on_confirmation_state_change, on_error, on_new_transaction, on_reattachment,
+    on_balance_change, on_broadcast, on_confirmation_state_change, on_error, on_message_posting_finished,
+    on_message_posting_started, on_new_transaction, on_reattachment,
 };
 use neon::prelude::*;
 
@@ -21,6 +22,8 @@ pub enum EventType {
     ConfirmationStateChange,
     Reattachment,
     Broadcast,
+    MessagePostingStarted,
+    MessagePostingFinished,
 }
 
 impl TryFrom<&str> for EventType {
@@ -34,6 +37,8 @@ impl TryFrom<&str> for EventType {
             "ConfirmationStateChange" => EventType::ConfirmationStateChange,
             "Reattachment" => EventType::Reattachment,
             "Broadcast" => EventType::Broadcast,
+            "MessagePostingStarted" => EventType::MessagePostingStarted,
+            "MessagePostingFinished" => EventType::MessagePostingFinished,
             _ => return Err(format!("invalid event name {}", value)),
         };
         Ok(event_type)
@@ -60,6 +65,12 @@ fn listen(event_type: EventType, sender: Sender<String>) {
         EventType::Broadcast => on_broadcast(move |event| {
             let _ = sender.send(serde_json::to_string(&event).unwrap());
         }),
+        EventType::MessagePostingStarted => on_message_posting_started(move |event| {
+            let _ = sender.send(serde_json::to_string(&event).unwrap());
+        }),
+        EventType::MessagePostingFinished => on_message_posting_finished(move |event| {
+            let _ = sender.send(serde_json::to_string(&event).unwrap());
+        }),
     }
 }
 
@@ -8,14 +8,42 @@ use std::{
 
 use iota_wallet::{
     account::SyncedAccount,
-    address::parse as parse_address,
-    message::{MessageId, RemainderValueStrategy, Transfer},
+    address::IotaAddress,
+    message::{parse_amount, MessageId, RemainderValueStrategy, Transfer},
 };
 use neon::prelude::*;
+use serde::Deserialize;
 
 mod repost;
 mod send;
 
+/// An amount, either a number of iotas or a string like `1.5 Mi`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchAmount {
+    Iotas(f64),
+    Denominated(String),
+}
+
+#[derive(Deserialize)]
+struct BatchOutput {
+    address: String,
+    amount: BatchAmount,
+}
+
+/// Parses the address, throwing if it doesn't belong to the account network.
+fn parse_address<'a, C: Context<'a>>(cx: &mut C, account_id: &str, address: &str) -> NeonResult<IotaAddress> {
+    let address = {
+        let account = crate::get_account(account_id);
+        let account = account.read().unwrap();
+        account.parse_address(address)
+    };
+    match address {
+        Ok(address) => Ok(address),
+        Err(e) => cx.throw_error(e.to_string()),
+    }
+}
+
 #[derive(Clone)]
 pub struct SyncedAccountWrapper(Arc<RwLock<SyncedAccount>>, String);
 
@@ -30,7 +58,8 @@ declare_types! {
 
         method send(mut cx) {
             let address = cx.argument::<JsString>(0)?.value();
-            let amount = cx.argument::<JsNumber>(1)?.value() as u64;
+            let amount = cx.argument::<JsValue>(1)?;
+            let amount = crate::js_value_to_amount(&mut cx, amount)?;
             let (remainder_value_strategy, cb) = match cx.argument_opt(3) {
                 Some(arg) => {
                     let cb = arg.downcast::<JsFunction>().or_throw(&mut cx)?;
@@ -41,11 +70,96 @@ declare_types! {
                 None => (RemainderValueStrategy::ChangeAddress, cx.argument::<JsFunction>(2)?),
             };
 
-            let transfer = Transfer::new(parse_address(address).expect("invalid address format"), amount)
-                .remainder_value_strategy(remainder_value_strategy);
+            let this = cx.this();
+            let instance = cx.borrow(&this, |r| r.clone());
+            let address = parse_address(&mut cx, &instance.1, &address)?;
+            let transfer = Transfer::new(address, amount).remainder_value_strategy(remainder_value_strategy);
+
+            let task = send::SendTask {
+                synced: instance.0,
+                account_id: instance.1,
+                transfer,
+            };
+            task.schedule(cb);
+            Ok(cx.undefined().upcast())
+        }
+
+        method sendBatch(mut cx) {
+            let outputs = cx.argument::<JsValue>(0)?;
+            let outputs: Vec<BatchOutput> = neon_serde::from_value(&mut cx, outputs)?;
+            let (remainder_value_strategy, cb) = match cx.argument_opt(2) {
+                Some(arg) => {
+                    let cb = arg.downcast::<JsFunction>().or_throw(&mut cx)?;
+                    let remainder_value_strategy = cx.argument::<JsValue>(1)?;
+                    let remainder_value_strategy = neon_serde::from_value(&mut cx, remainder_value_strategy)?;
+                    (remainder_value_strategy, cb)
+                }
+                None => (RemainderValueStrategy::ChangeAddress, cx.argument::<JsFunction>(1)?),
+            };
+
+            let this = cx.this();
+            let instance = cx.borrow(&this, |r| r.clone());
+            let mut parsed_outputs = Vec::new();
+            for output in outputs {
+                let amount = match output.amount {
+                    BatchAmount::Iotas(amount) => crate::number_to_amount(amount),
+                    BatchAmount::Denominated(amount) => parse_amount(&amount).map_err(|e| e.to_string()),
+                };
+                let amount = match amount {
+                    Ok(amount) => amount,
+                    Err(e) => return cx.throw_error(e),
+                };
+                let address = parse_address(&mut cx, &instance.1, &output.address)?;
+                parsed_outputs.push((address, amount));
+            }
+            let outputs = parsed_outputs;
+            let transfer = Transfer::with_outputs(outputs).remainder_value_strategy(remainder_value_strategy);
+
+            let task = send::SendTask {
+                synced: instance.0,
+                account_id: instance.1,
+                transfer,
+            };
+            task.schedule(cb);
+            Ok(cx.undefined().upcast())
+        }
+
+        method sendMax(mut cx) {
+            let address = cx.argument::<JsString>(0)?.value();
+            let cb = cx.argument::<JsFunction>(1)?;
+
+            let this = cx.this();
+            let instance = cx.borrow(&this, |r| r.clone());
+            let address = parse_address(&mut cx, &instance.1, &address)?;
+            let transfer = Transfer::max(address);
+
+            let task = send::SendTask {
+                synced: instance.0,
+                account_id: instance.1,
+                transfer,
+            };
+            task.schedule(cb);
+            Ok(cx.undefined().upcast())
+        }
+
+        method sendToContact(mut cx) {
+            let name = cx.argument::<JsString>(0)?.value();
+            let amount = cx.argument::<JsValue>(1)?;
+            let amount = crate::js_value_to_amount(&mut cx, amount)?;
+            let cb = cx.argument::<JsFunction>(2)?;
 
             let this = cx.this();
             let instance = cx.borrow(&this, |r| r.clone());
+            let transfer = {
+                let account = crate::get_account(&instance.1);
+                let account = account.read().unwrap();
+                account.transfer_to_contact(&name, amount)
+            };
+            let transfer = match transfer {
+                Ok(transfer) => transfer,
+                Err(e) => return cx.throw_error(e.to_string()),
+            };
+
             let task = send::SendTask {
                 synced: instance.0,
                 account_id: instance.1,
@@ -10,7 +10,12 @@ use std::{
 };
 
 use futures::{Future, FutureExt};
-use iota_wallet::{account::Account, address::Address, message::Message, WalletError};
+use iota_wallet::{
+    account::Account,
+    address::Address,
+    message::{format_amount, format_amount_with_unit, parse_amount, Message, ValueUnit},
+    WalletError,
+};
 use neon::prelude::*;
 use once_cell::sync::{Lazy, OnceCell};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
@@ -150,6 +155,65 @@ pub async fn convert_async_panics<T, F: Future<Output = Result<T, WalletError>>>
     }
 }
 
+/// The largest integer a JS number represents exactly.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Converts a JS number to an amount of iotas, rejecting values that aren't exact non-negative integers.
+pub(crate) fn number_to_amount(amount: f64) -> Result<u64, String> {
+    if amount.is_finite() && amount >= 0.0 && amount.fract() == 0.0 && amount <= MAX_SAFE_INTEGER {
+        Ok(amount as u64)
+    } else {
+        Err(format!(
+            "invalid amount {}: expected an integer between 0 and {}, larger amounts must be passed as strings",
+            amount, MAX_SAFE_INTEGER
+        ))
+    }
+}
+
+/// Reads an amount argument, either a number of iotas or a string like `1.5 Mi`.
+pub(crate) fn js_value_to_amount<'a, C: Context<'a>>(cx: &mut C, value: Handle<JsValue>) -> NeonResult<u64> {
+    let amount = match value.downcast::<JsString>() {
+        Ok(js_string) => parse_amount(&js_string.value()).map_err(|e| e.to_string()),
+        Err(_) => {
+            let amount: JsNumber = *value.downcast_or_throw(cx)?;
+            number_to_amount(amount.value())
+        }
+    };
+    match amount {
+        Ok(amount) => Ok(amount),
+        Err(e) => cx.throw_error(e),
+    }
+}
+
+/// Parses an amount, returning the number of iotas as a string since it can exceed the JS number precision.
+fn js_parse_amount(mut cx: FunctionContext) -> JsResult<JsString> {
+    let amount = cx.argument::<JsString>(0)?.value();
+    match parse_amount(&amount) {
+        Ok(amount) => Ok(cx.string(amount.to_string())),
+        Err(e) => cx.throw_error(e.to_string()),
+    }
+}
+
+fn js_format_amount(mut cx: FunctionContext) -> JsResult<JsString> {
+    let amount = cx.argument::<JsValue>(0)?;
+    let amount = js_value_to_amount(&mut cx, amount)?;
+    let precision = match cx.argument_opt(1) {
+        Some(arg) => arg.downcast::<JsNumber>().or_throw(&mut cx)?.value() as usize,
+        None => 2,
+    };
+    let formatted = match cx.argument_opt(2) {
+        Some(arg) => {
+            let unit = arg.downcast::<JsString>().or_throw(&mut cx)?.value();
+            match unit.parse::<ValueUnit>() {
+                Ok(unit) => format_amount_with_unit(amount, unit, precision),
+                Err(e) => return cx.throw_error(e.to_string()),
+            }
+        }
+        None => format_amount(amount, precision),
+    };
+    Ok(cx.string(formatted))
+}
+
 pub(crate) fn block_on<C: futures::Future>(cb: C) -> C::Output {
     static INSTANCE: OnceCell<Mutex<Runtime>> = OnceCell::new();
     let runtime = INSTANCE.get_or_init(|| Mutex::new(Runtime::new().unwrap()));
@@ -162,5 +226,7 @@ register_module!(mut m, {
     m.export_class::<JsAccount>("Account")?;
     m.export_class::<JsSyncedAccount>("SyncedAccount")?;
     m.export_class::<JsEventListener>("EventListener")?;
+    m.export_function("parseAmount", js_parse_amount)?;
+    m.export_function("formatAmount", js_format_amount)?;
     Ok(())
 });
@@ -2,15 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    address::{Address, IotaAddress},
+    address::{Address, AddressCache, IotaAddress, OutputId},
     client::ClientOptions,
-    message::{Message, MessageType},
-    signing::{with_signer, SignerType},
+    message::{Message, MessageType, Transfer, TransferOutput, Value},
+    outbox::{OutboxEntry, OutboxState, TransferChain},
+    payment_request::{PaymentRequest, PaymentRequestBuilder},
+    schedule::ScheduledTransfer,
+    signing::{with_signer, Mnemonic, SignerType},
+    spending_policy::SpendingPolicy,
+    validation::KnownOutput,
 };
 
 use chrono::prelude::{DateTime, Utc};
 use getset::{Getters, Setters};
-use iota::message::prelude::MessageId;
+use iota::message::prelude::{Indexation, MessageId, Payload, Transaction};
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
@@ -22,8 +27,11 @@ use std::{
 };
 
 mod sync;
-pub(crate) use sync::{repost_message, RepostAction};
-pub use sync::{AccountSynchronizer, SyncedAccount, TransferMetadata};
+pub(crate) use sync::{movable_outputs, reconcile_outbox, repost_message, RepostAction};
+pub use sync::{
+    AccountSynchronizer, ConsolidationOptions, InputSelectionStrategy, InputSelector, SyncedAccount, TransferMetadata,
+    TransferPreview, MAX_INPUTS_COUNT, MAX_OUTPUTS_COUNT,
+};
 
 type AddressesLock = Arc<Mutex<Vec<IotaAddress>>>;
 type AccountAddressesLock = Arc<Mutex<HashMap<AccountIdentifier, AddressesLock>>>;
@@ -69,7 +77,7 @@ impl From<u64> for AccountIdentifier {
 
 /// Account initialiser.
 pub struct AccountInitialiser<'a> {
-    mnemonic: Option<String>,
+    mnemonic: Option<Mnemonic>,
     alias: Option<String>,
     created_at: Option<DateTime<Utc>>,
     messages: Vec<Message>,
@@ -108,7 +116,7 @@ impl<'a> AccountInitialiser<'a> {
     /// Defines the account BIP-39 mnemonic.
     /// When importing an account from stronghold, the mnemonic won't be required.
     pub fn mnemonic(mut self, mnemonic: impl AsRef<str>) -> Self {
-        self.mnemonic = Some(mnemonic.as_ref().to_string());
+        self.mnemonic = Some(Mnemonic::new(mnemonic.as_ref().to_string()));
         self
     }
 
@@ -163,16 +171,38 @@ impl<'a> AccountInitialiser<'a> {
             }
         }
 
+        let bech32_hrp = crate::address::network_bech32_hrp(self.client_options.network().as_ref());
+        let mut addresses = self.addresses;
+        for address in addresses.iter_mut() {
+            address.set_bech32_hrp(bech32_hrp.to_string());
+        }
+        let mut messages = self.messages;
+        for message in messages.iter_mut() {
+            message.set_bech32_hrp(bech32_hrp)?;
+        }
+
         let mut account = Account {
             id: "".to_string(),
             signer_type: signer_type.clone(),
             index: accounts.len(),
             alias,
             created_at,
-            messages: self.messages,
-            addresses: self.addresses,
+            messages,
+            addresses,
             client_options: self.client_options,
+            node_bech32_hrp: None,
+            network_id: None,
             storage_path: self.storage_path.clone(),
+            address_cache: Default::default(),
+            input_selection_strategy: Default::default(),
+            frozen_outputs: vec![],
+            frozen_addresses: vec![],
+            outbox: vec![],
+            transfer_chains: vec![],
+            scheduled_transfers: vec![],
+            spending_policy: Default::default(),
+            next_change_index: 0,
+            migrated_from: None,
             has_pending_changes: false,
         };
 
@@ -206,6 +236,7 @@ pub struct Account {
     #[getset(set = "pub(crate)")]
     id: String,
     /// The account's signer type.
+    #[getset(set = "pub(crate)")]
     signer_type: SignerType,
     /// The account index
     index: usize,
@@ -225,8 +256,47 @@ pub struct Account {
     /// The client options.
     #[serde(rename = "clientOptions")]
     client_options: ClientOptions,
+    /// The bech32 human-readable part reported by the node on the last sync.
+    #[serde(rename = "nodeBech32Hrp", default)]
+    node_bech32_hrp: Option<String>,
+    /// The id of the network the account messages were built for.
+    #[serde(rename = "networkId", default)]
+    #[getset(set = "pub(crate)")]
+    network_id: Option<u64>,
     #[getset(set = "pub(crate)", get = "pub(crate)")]
     storage_path: PathBuf,
+    /// The cache of the derived addresses.
+    #[serde(rename = "addressCache", default)]
+    #[getset(set = "pub(crate)", get = "pub(crate)")]
+    address_cache: AddressCache,
+    /// The input selection strategy used by transfers that don't define one.
+    #[serde(rename = "inputSelectionStrategy", default)]
+    input_selection_strategy: InputSelectionStrategy,
+    /// The outputs that the automatic input selection never spends.
+    #[serde(rename = "frozenOutputs", default)]
+    frozen_outputs: Vec<OutputId>,
+    /// The addresses that the automatic input selection never spends from.
+    #[serde(rename = "frozenAddresses", default, with = "crate::serde::iota_address_vec_serde")]
+    frozen_addresses: Vec<IotaAddress>,
+    /// The outgoing transfers, persisted to recover them if the process stops mid-transfer.
+    #[serde(default)]
+    outbox: Vec<OutboxEntry>,
+    /// The outcomes of the transfer chains sent with an idempotency key.
+    #[serde(rename = "transferChains", default)]
+    transfer_chains: Vec<TransferChain>,
+    /// The transfers executed by the account manager's polling system according to their schedule.
+    #[serde(rename = "scheduledTransfers", default)]
+    scheduled_transfers: Vec<ScheduledTransfer>,
+    /// The spending controls enforced on the account's outgoing transfers.
+    #[serde(rename = "spendingPolicy", default)]
+    spending_policy: SpendingPolicy,
+    /// The next index of the change chain. It only moves forward, so change addresses are never reused.
+    #[serde(rename = "nextChangeIndex", default)]
+    next_change_index: usize,
+    /// The identifier of the account record replaced by a signer migration that wasn't completed yet.
+    #[serde(rename = "migratedFrom", default)]
+    #[getset(set = "pub(crate)")]
+    migrated_from: Option<String>,
     #[doc(hidden)]
     #[serde(skip)]
     has_pending_changes: bool,
@@ -283,11 +353,262 @@ impl Account {
     }
 
     /// Updates the account's client options.
+    /// The bech32 human-readable part reported by the previous node is discarded until the next sync.
+    /// The new nodes must belong to the account's network unless the options switch the network explicitly;
+    /// that's validated by the next sync or transfer.
     pub fn set_client_options(&mut self, options: ClientOptions) {
+        if options != self.client_options {
+            if options.network() != self.client_options.network() {
+                self.network_id = None;
+            }
+            self.has_pending_changes = true;
+            self.client_options = options;
+            self.set_node_bech32_hrp(None);
+        }
+    }
+
+    /// Gets the network id of the account's nodes, used to build messages.
+    /// Fails if the nodes belong to a network other than the one the account was used on.
+    pub(crate) async fn resolve_network_id(&mut self) -> crate::Result<u64> {
+        let network_id = crate::client::get_network_id(&self.client_options).await?;
+        match self.network_id {
+            Some(account_network_id) if account_network_id != network_id => {
+                Err(crate::WalletError::NetworkMismatch(format!(
+                    "the account nodes belong to the network {}, but the account was used on the network {}",
+                    network_id, account_network_id
+                )))
+            }
+            Some(_) => Ok(network_id),
+            None => {
+                self.network_id = Some(network_id);
+                self.has_pending_changes = true;
+                Ok(network_id)
+            }
+        }
+    }
+
+    /// Gets the bech32 human-readable part of the account addresses.
+    /// The one reported by the node is used when available, otherwise it's derived from the client options network.
+    pub fn bech32_hrp(&self) -> String {
+        match &self.node_bech32_hrp {
+            Some(bech32_hrp) => bech32_hrp.clone(),
+            None => crate::address::network_bech32_hrp(self.client_options.network().as_ref()).to_string(),
+        }
+    }
+
+    /// Sets the bech32 human-readable part reported by the node and re-encodes the account addresses and messages.
+    pub(crate) fn set_node_bech32_hrp(&mut self, bech32_hrp: Option<String>) {
+        if bech32_hrp != self.node_bech32_hrp {
+            self.has_pending_changes = true;
+            self.node_bech32_hrp = bech32_hrp;
+        }
+        let bech32_hrp = self.bech32_hrp();
+        for address in self.addresses.iter_mut() {
+            address.set_bech32_hrp(bech32_hrp.clone());
+        }
+        for message in self.messages.iter_mut() {
+            // the HRP is validated when it's set
+            let _ = message.set_bech32_hrp(&bech32_hrp);
+        }
+    }
+
+    /// Records a change chain index as used, moving the change chain past it.
+    pub(crate) fn record_change_index(&mut self, index: usize) {
+        if index >= self.next_change_index {
+            self.next_change_index = index + 1;
+            self.has_pending_changes = true;
+        }
+    }
+
+    /// Replaces the spending policy.
+    /// The recipients already on the allowlist keep the time they were added.
+    pub(crate) fn set_spending_policy(&mut self, mut policy: SpendingPolicy) {
+        policy.inherit_allowlist(&self.spending_policy, Utc::now());
+        self.spending_policy = policy;
+        self.has_pending_changes = true;
+    }
+
+    /// Gets the amount sent by the account since the given time, ignoring reattachments.
+    ///
+    /// Transfers still in progress are counted from their outbox entry until their message is stored,
+    /// so concurrent transfers can't exceed the spending limits together.
+    pub fn spent_since(&self, since: DateTime<Utc>) -> u64 {
+        let mut counted: Vec<&Message> = vec![];
+        for message in &self.messages {
+            if *message.incoming() || message.timestamp() < &since {
+                continue;
+            }
+            if counted.iter().any(|m| m.payload() == message.payload()) {
+                continue;
+            }
+            counted.push(message);
+        }
+        let spent = counted.iter().fold(0u64, |acc, message| {
+            acc.saturating_add(message.transfer_amount.unwrap_or(*message.value()))
+        });
+
+        self.outbox
+            .iter()
+            .filter(|entry| !entry.is_settled() && entry.created_at() >= &since)
+            .filter(|entry| match entry.message_id() {
+                Some(message_id) => !self.messages.iter().any(|message| message.id() == message_id),
+                None => true,
+            })
+            .fold(spent, |acc, entry| acc.saturating_add(*entry.amount()))
+    }
+
+    /// Sums the outputs sent to addresses outside of the account, the value a transfer spends.
+    /// Consolidations and other transfers between the account's own addresses don't spend anything.
+    pub(crate) fn external_amount(&self, outputs: &[TransferOutput]) -> u64 {
+        outputs
+            .iter()
+            .filter(|output| !self.addresses.iter().any(|a| a.address() == output.address()))
+            .fold(0u64, |acc, output| acc.saturating_add(*output.amount()))
+    }
+
+    /// Checks the transfer outputs against the spending policy.
+    /// Outputs to the account's own addresses aren't limited.
+    pub(crate) fn check_spending_policy(&self, outputs: &[TransferOutput]) -> crate::Result<()> {
+        let recipients: Vec<(IotaAddress, u64)> = outputs
+            .iter()
+            .filter(|output| !self.addresses.iter().any(|a| a.address() == output.address()))
+            .map(|output| (output.address().clone(), *output.amount()))
+            .collect();
+        self.spending_policy.check(
+            &recipients,
+            |since| self.spent_since(since),
+            &self.bech32_hrp(),
+            Utc::now(),
+        )?;
+        Ok(())
+    }
+
+    /// Validates a signed transaction against the account outputs before it's posted,
+    /// e.g. a transaction signed by an external signer.
+    pub fn validate_transaction(&self, transaction: &Transaction) -> crate::Result<()> {
+        let known_outputs: Vec<KnownOutput> = self
+            .addresses
+            .iter()
+            .flat_map(|address| {
+                address.outputs().iter().map(move |output| {
+                    KnownOutput::new(
+                        output.id(),
+                        address.address().clone(),
+                        *output.amount(),
+                        *output.is_spent() || output.pending_on_message_id().is_some(),
+                    )
+                })
+            })
+            .collect();
+        crate::validation::validate_transaction(transaction, &known_outputs)
+    }
+
+    /// Parses a bech32 address, rejecting addresses that don't belong to the account network.
+    pub fn parse_address(&self, address: &str) -> crate::Result<IotaAddress> {
+        crate::address::parse_with_hrp(address, &self.bech32_hrp())
+    }
+
+    /// Updates the input selection strategy used by transfers that don't define one.
+    /// The account strategy is persisted, so custom selectors must be set on the transfers instead.
+    pub fn set_input_selection_strategy(&mut self, strategy: InputSelectionStrategy) -> crate::Result<()> {
+        if let InputSelectionStrategy::Custom(_) = strategy {
+            return Err(anyhow::anyhow!("custom input selectors can't be set on the account").into());
+        }
         if !self.has_pending_changes {
-            self.has_pending_changes = options != self.client_options;
+            self.has_pending_changes = strategy != self.input_selection_strategy;
         }
-        self.client_options = options;
+        self.input_selection_strategy = strategy;
+        Ok(())
+    }
+
+    /// Freezes the output so the automatic input selection never spends it.
+    pub fn freeze_output(&mut self, output_id: OutputId) {
+        if !self.frozen_outputs.contains(&output_id) {
+            self.frozen_outputs.push(output_id);
+            self.has_pending_changes = true;
+        }
+    }
+
+    /// Unfreezes the output.
+    pub fn unfreeze_output(&mut self, output_id: &OutputId) {
+        if let Some(index) = self.frozen_outputs.iter().position(|id| id == output_id) {
+            self.frozen_outputs.remove(index);
+            self.has_pending_changes = true;
+        }
+    }
+
+    /// Freezes the address so the automatic input selection never spends from it.
+    pub fn freeze_address(&mut self, address: IotaAddress) {
+        if !self.frozen_addresses.contains(&address) {
+            self.frozen_addresses.push(address);
+            self.has_pending_changes = true;
+        }
+    }
+
+    /// Unfreezes the address.
+    pub fn unfreeze_address(&mut self, address: &IotaAddress) {
+        if let Some(index) = self.frozen_addresses.iter().position(|a| a == address) {
+            self.frozen_addresses.remove(index);
+            self.has_pending_changes = true;
+        }
+    }
+
+    /// Builds a transfer to the address book contact with the given name.
+    /// Fails if the contact belongs to a network other than the account's.
+    pub fn transfer_to_contact(&self, name: &str, amount: u64) -> crate::Result<Transfer> {
+        let address_book = crate::address_book::load(&self.storage_path)?;
+        let contact = address_book.get(name)?;
+        if let (Some(contact_network), Some(network)) = (contact.network(), self.client_options.network()) {
+            if contact_network != network {
+                return Err(crate::WalletError::InvalidContact(format!(
+                    "contact `{}` belongs to the {:?} network",
+                    contact.name(),
+                    contact_network
+                )));
+            }
+        }
+        Ok(Transfer::new(contact.address().clone(), amount))
+    }
+
+    /// Creates a payment request to the account's latest address.
+    pub fn create_payment_request(&self, amount: Value, memo: Option<String>) -> crate::Result<PaymentRequest> {
+        let address = self
+            .latest_address()
+            .ok_or_else(|| anyhow::anyhow!("account address list empty"))?;
+        let mut request = PaymentRequestBuilder::new(address.address().clone())
+            .bech32_hrp(self.bech32_hrp())
+            .amount(amount);
+        if let Some(memo) = memo {
+            request = request.message(memo);
+        }
+        request.build()
+    }
+
+    /// Schedules a transfer, executed by the account manager's polling system.
+    /// Returns the scheduled transfer identifier.
+    /// Scheduled transfers are persisted, so they can't use custom input selectors.
+    pub fn schedule_transfer(&mut self, mut scheduled_transfer: ScheduledTransfer) -> crate::Result<String> {
+        if let Some(InputSelectionStrategy::Custom(_)) = scheduled_transfer.transfer().input_selection_strategy {
+            return Err(anyhow::anyhow!("custom input selectors can't be set on scheduled transfers").into());
+        }
+        scheduled_transfer.transfer_mut().use_bech32_hrp(&self.bech32_hrp())?;
+        scheduled_transfer.init(Utc::now())?;
+        let id = scheduled_transfer.id().clone();
+        self.scheduled_transfers.push(scheduled_transfer);
+        self.has_pending_changes = true;
+        Ok(id)
+    }
+
+    /// Cancels a scheduled transfer.
+    pub fn cancel_scheduled_transfer(&mut self, id: &str) {
+        if let Some(index) = self.scheduled_transfers.iter().position(|s| s.id() == id) {
+            self.scheduled_transfers.remove(index);
+            self.has_pending_changes = true;
+        }
+    }
+
+    pub(crate) fn scheduled_transfers_mut(&mut self) -> &mut Vec<ScheduledTransfer> {
+        &mut self.scheduled_transfers
     }
 
     /// Saves the pending changes on the account.
@@ -357,6 +678,7 @@ impl Account {
                     MessageType::Failed => !message.broadcasted(),
                     MessageType::Unconfirmed => !message.confirmed(),
                     MessageType::Value => *message.value() > 0,
+                    MessageType::Data => matches!(message.payload(), Payload::Indexation(_)),
                 }
             } else {
                 true
@@ -385,7 +707,7 @@ impl Account {
 
     /// Gets a new unused address and links it to this account.
     pub fn generate_address(&mut self) -> crate::Result<Address> {
-        let address = crate::address::get_new_address(&self)?;
+        let address = crate::address::get_new_address(self)?;
         self.addresses.push(address.clone());
 
         let id: AccountIdentifier = self.id.clone().into();
@@ -398,6 +720,59 @@ impl Account {
         Ok(address)
     }
 
+    /// Posts a data-only (indexation) message with the given index through the account's client
+    /// and stores it on the account's message history.
+    pub async fn send_data(&mut self, index: String, data: Vec<u8>) -> crate::Result<Message> {
+        let indexation = Indexation::new(index, &data).map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+        let network_id = self.resolve_network_id().await?;
+
+        let client = crate::client::get_client(&self.client_options);
+        let client = client.read().unwrap();
+        let (parent1, parent2) = client.get_tips().await?;
+        let iota_message = iota::Message::builder()
+            .with_parent1(parent1)
+            .with_parent2(parent2)
+            .with_payload(Payload::Indexation(Box::new(indexation)))
+            .with_network_id(network_id)
+            .finish()
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let message_id =
+            crate::client::with_message_posting(&self.id, &self.client_options, client.post_message(&iota_message))
+                .await?;
+        // drop the client ref so it doesn't lock the monitor system
+        std::mem::drop(client);
+
+        let message = Message::from_iota_message(message_id, &self.addresses, &iota_message, &self.bech32_hrp())?;
+        self.messages.push(message.clone());
+
+        let id: AccountIdentifier = self.id.clone().into();
+        crate::storage::with_adapter(&self.storage_path, |storage| {
+            storage.set(id, serde_json::to_string(self)?)
+        })?;
+
+        // ignore errors because we fallback to the polling system
+        let _ = crate::monitor::monitor_confirmation_state_change(&self, &message_id);
+        Ok(message)
+    }
+
+    /// Fetches the messages with the given index from the node.
+    pub async fn find_messages_by_index(&self, index: &str) -> crate::Result<Vec<Message>> {
+        let client = crate::client::get_client(&self.client_options);
+        let client = client.read().unwrap();
+        let message_ids = client.get_message().index(index).await?;
+        let mut messages = vec![];
+        for message_id in message_ids {
+            let message = client.get_message().data(&message_id).await?;
+            messages.push(Message::from_iota_message(
+                message_id,
+                &self.addresses,
+                &message,
+                &self.bech32_hrp(),
+            )?);
+        }
+        Ok(messages)
+    }
+
     #[doc(hidden)]
     pub fn append_messages(&mut self, messages: Vec<Message>) {
         self.messages.extend(messages.iter().cloned());
@@ -432,6 +807,10 @@ impl Account {
             });
     }
 
+    pub(crate) fn address_cache_mut(&mut self) -> &mut AddressCache {
+        &mut self.address_cache
+    }
+
     #[doc(hidden)]
     pub fn addresses_mut(&mut self) -> &mut Vec<Address> {
         &mut self.addresses
@@ -458,7 +837,42 @@ impl Account {
         updated
     }
 
+    pub(crate) fn on_message_confirmed(&mut self, message_id: &MessageId) {
+        for entry in self.outbox.iter_mut() {
+            if entry.message_id().as_ref() == Some(message_id) {
+                entry.set_state(OutboxState::Confirmed);
+            }
+        }
+    }
+
+    pub(crate) fn add_outbox_entry(&mut self, entry: OutboxEntry) {
+        self.outbox.push(entry);
+        crate::outbox::prune(&mut self.outbox);
+    }
+
+    pub(crate) fn update_outbox_entry<F: FnOnce(&mut OutboxEntry)>(&mut self, entry_id: &str, cb: F) {
+        if let Some(entry) = self.outbox.iter_mut().find(|entry| entry.id() == entry_id) {
+            cb(entry);
+        }
+    }
+
+    /// Stores the transfer chain, replacing the previous chain with the same idempotency key.
+    pub(crate) fn set_transfer_chain(&mut self, chain: TransferChain) {
+        self.transfer_chains
+            .retain(|c| c.idempotency_key() != chain.idempotency_key());
+        self.transfer_chains.push(chain);
+    }
+
+    pub(crate) fn remove_transfer_chain(&mut self, idempotency_key: &str) {
+        self.transfer_chains.retain(|c| c.idempotency_key() != idempotency_key);
+    }
+
     pub(crate) fn on_reattachment(&mut self, old_message_id: &MessageId, new_message_id: &MessageId) -> bool {
+        for entry in self.outbox.iter_mut() {
+            if entry.message_id().as_ref() == Some(old_message_id) {
+                entry.set_message_id(*new_message_id);
+            }
+        }
         let mut updated = false;
         for address in self.addresses.iter_mut() {
             for output in address.outputs_mut().iter_mut() {
@@ -506,8 +920,29 @@ pub struct InitialisedAccount<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::client::ClientOptionsBuilder;
+    use super::{InputSelectionStrategy, InputSelector};
+    use crate::{
+        address::{Address, IotaAddress, OutputId, MAINNET_BECH32_HRP},
+        address_book::ContactBuilder,
+        client::{ClientOptionsBuilder, Network},
+        message::{Message, MessageType, Transfer},
+        outbox::{OutboxEntry, OutboxState},
+        schedule::{Schedule, ScheduledTransfer},
+        test_utils::{account_with_funds, iota_message, transaction_payload},
+    };
+    use chrono::Utc;
+    use iota::message::prelude::{Ed25519Address, Indexation, MessageId, Payload, TransactionId};
     use rusty_fork::rusty_fork_test;
+    use std::sync::Arc;
+
+    /// Spends the first available address.
+    struct FirstAvailable;
+
+    impl InputSelector for FirstAvailable {
+        fn select(&self, _target: u64, available_addresses: &mut [Address]) -> crate::Result<Vec<Address>> {
+            Ok(available_addresses.iter().take(1).cloned().collect())
+        }
+    }
 
     rusty_fork_test! {
         #[test]
@@ -539,4 +974,169 @@ mod tests {
             );
         }
     }
+
+    rusty_fork_test! {
+        #[test]
+        fn freeze_outputs_and_addresses() {
+            let manager = crate::test_utils::get_account_manager();
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let output_id = OutputId::new(TransactionId::new([1; 32]), 0);
+            let address = IotaAddress::Ed25519(Ed25519Address::new([2; 32]));
+
+            let account_id = {
+                let mut account = manager
+                    .create_account(client_options)
+                    .initialise()
+                    .expect("failed to add account");
+
+                account.freeze_output(output_id.clone());
+                account.freeze_address(address.clone());
+                account.id().into()
+            };
+
+            let mut account_in_storage = manager
+                .get_account(account_id)
+                .expect("failed to get account from storage");
+            assert_eq!(account_in_storage.frozen_outputs(), &vec![output_id.clone()]);
+            assert_eq!(account_in_storage.frozen_addresses(), &vec![address.clone()]);
+
+            account_in_storage.unfreeze_output(&output_id);
+            account_in_storage.unfreeze_address(&address);
+            assert!(account_in_storage.frozen_outputs().is_empty());
+            assert!(account_in_storage.frozen_addresses().is_empty());
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn list_data_messages() {
+            let manager = crate::test_utils::get_account_manager();
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mut account = manager
+                .create_account(client_options)
+                .initialise()
+                .expect("failed to add account");
+
+            let indexation = Indexation::new("NEWS".to_string(), b"hello").unwrap();
+            let data_message = Message::from_iota_message(
+                MessageId::new([1; 32]),
+                &[],
+                &iota_message(Payload::Indexation(Box::new(indexation))),
+                MAINNET_BECH32_HRP,
+            )
+            .unwrap();
+            let value_message = Message::from_iota_message(
+                MessageId::new([2; 32]),
+                &[],
+                &iota_message(transaction_payload(None)),
+                MAINNET_BECH32_HRP,
+            )
+            .unwrap();
+            account.append_messages(vec![data_message.clone(), value_message]);
+
+            let data_messages = account.list_messages(0, 0, Some(MessageType::Data));
+            assert_eq!(data_messages.len(), 1);
+            assert_eq!(data_messages[0].id(), data_message.id());
+            assert_eq!(data_messages[0].data_as_utf8(), Some("hello".to_string()));
+            assert_eq!(account.list_messages(0, 0, None).len(), 2);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn spent_since_counts_transfers_in_progress() {
+            let mut account = account_with_funds(vec![]);
+            let since = Utc::now() - chrono::Duration::days(1);
+
+            let entry = OutboxEntry::new(vec![], vec![], 2_000_000, None);
+            let entry_id = entry.id().clone();
+            account.add_outbox_entry(entry);
+            assert_eq!(account.spent_since(since), 2_000_000);
+
+            // a posted transfer is counted from its message once it's stored
+            let message = Message::from_iota_message(
+                MessageId::new([1; 32]),
+                &[],
+                &iota_message(transaction_payload(None)),
+                MAINNET_BECH32_HRP,
+            )
+            .unwrap();
+            account.update_outbox_entry(&entry_id, |entry| entry.set_posted(*message.id()));
+            assert_eq!(account.spent_since(since), 2_000_000);
+            account.messages.push(message);
+            let mut confirmed_account = account.clone();
+            confirmed_account.update_outbox_entry(&entry_id, |entry| entry.set_state(OutboxState::Confirmed));
+            assert_eq!(account.spent_since(since), confirmed_account.spent_since(since));
+
+            account.update_outbox_entry(&entry_id, |entry| entry.set_state(OutboxState::Failed));
+            account.messages.clear();
+            assert_eq!(account.spent_since(since), 0);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn transfer_to_contact_network() {
+            let manager = crate::test_utils::get_account_manager();
+
+            let client_options = ClientOptionsBuilder::network(Network::Devnet).build();
+            let account = manager
+                .create_account(client_options)
+                .initialise()
+                .expect("failed to add account");
+
+            let mainnet_address = IotaAddress::Ed25519(Ed25519Address::new([1; 32]));
+            let devnet_address = IotaAddress::Ed25519(Ed25519Address::new([2; 32]));
+            let unknown_network_address = IotaAddress::Ed25519(Ed25519Address::new([3; 32]));
+            manager
+                .add_contact(ContactBuilder::new("Mainnet", mainnet_address).network(Network::Mainnet).build())
+                .unwrap();
+            manager
+                .add_contact(ContactBuilder::new("Devnet", devnet_address.clone()).network(Network::Devnet).build())
+                .unwrap();
+            manager
+                .add_contact(ContactBuilder::new("Unknown", unknown_network_address.clone()).build())
+                .unwrap();
+
+            assert!(matches!(
+                account.transfer_to_contact("Mainnet", 10),
+                Err(crate::WalletError::InvalidContact(_))
+            ));
+            let transfer = account.transfer_to_contact("devnet", 10).unwrap();
+            assert_eq!(transfer.outputs()[0].address(), &devnet_address);
+            let transfer = account.transfer_to_contact("Unknown", 10).unwrap();
+            assert_eq!(transfer.outputs()[0].address(), &unknown_network_address);
+            assert!(matches!(
+                account.transfer_to_contact("Carol", 10),
+                Err(crate::WalletError::ContactNotFound(_))
+            ));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn schedule_transfer_with_custom_input_selector() {
+            let manager = crate::test_utils::get_account_manager();
+            let address = IotaAddress::Ed25519(Ed25519Address::new([1; 32]));
+            let mut account = account_with_funds(vec![]);
+
+            let transfer = Transfer::new(address.clone(), 10)
+                .input_selection_strategy(InputSelectionStrategy::Custom(Arc::new(FirstAvailable)));
+            let schedule = Schedule::Cron("0 9 1 * *".parse().unwrap());
+            assert!(account.schedule_transfer(ScheduledTransfer::new(transfer, schedule.clone())).is_err());
+            assert!(account.scheduled_transfers().is_empty());
+
+            let transfer = Transfer::new(address, 10).input_selection_strategy(InputSelectionStrategy::LargestFirst);
+            let id = account.schedule_transfer(ScheduledTransfer::new(transfer, schedule)).unwrap();
+            account.save().unwrap();
+            let stored_account = manager.get_account(account.id().into()).unwrap();
+            assert_eq!(stored_account.scheduled_transfers()[0].id(), &id);
+        }
+    }
 }
@@ -3,52 +3,144 @@
 
 use crate::address::Address;
 use rand::{thread_rng, Rng};
-use std::convert::TryInto;
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc};
 
-pub fn select_input(target: u64, available_utxos: &mut [Address]) -> crate::Result<Vec<Address>> {
-    if target
-        > available_utxos
-            .iter()
-            .fold(0, |acc, address| acc + address.available_balance())
-    {
-        return Err(crate::WalletError::InsufficientFunds);
+/// Upper bound of the branch and bound search iterations.
+const MAX_BRANCH_AND_BOUND_TRIES: u64 = 100_000;
+
+/// Input selection interface.
+/// Selects the addresses whose available balance is spent to fulfill a transfer of `target` value.
+pub trait InputSelector {
+    /// Selects the input addresses from the available addresses.
+    /// The available balance of the selected addresses must be greater than or equal to the target.
+    fn select(&self, target: u64, available_addresses: &mut [Address]) -> crate::Result<Vec<Address>>;
+}
+
+/// The input selection strategies.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum InputSelectionStrategy {
+    /// Searches for an exact match to avoid a remainder output, with a fallback to a single random draw.
+    BranchAndBound,
+    /// Spends the addresses with the largest balance first, minimizing the number of inputs.
+    LargestFirst,
+    /// Spends the addresses with the smallest balance first, consolidating the account's funds.
+    SmallestFirst,
+    /// Spends the oldest addresses (lowest key index) first.
+    OldestFirst,
+    /// Never mixes addresses: spends the single address with the smallest balance that covers the target.
+    Privacy,
+    /// A custom input selector.
+    /// It can't be serialized, so it can only be set on transfers that aren't scheduled.
+    #[serde(skip)]
+    Custom(Arc<dyn InputSelector + Send + Sync>),
+}
+
+impl fmt::Debug for InputSelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BranchAndBound => write!(f, "BranchAndBound"),
+            Self::LargestFirst => write!(f, "LargestFirst"),
+            Self::SmallestFirst => write!(f, "SmallestFirst"),
+            Self::OldestFirst => write!(f, "OldestFirst"),
+            Self::Privacy => write!(f, "Privacy"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl PartialEq for InputSelectionStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // custom selectors are only equal to themselves
+            (Self::Custom(a), Self::Custom(b)) => Arc::as_ptr(a) as *const u8 == Arc::as_ptr(b) as *const u8,
+            (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+        }
+    }
+}
+
+impl Default for InputSelectionStrategy {
+    fn default() -> Self {
+        Self::BranchAndBound
+    }
+}
+
+impl InputSelector for InputSelectionStrategy {
+    fn select(&self, target: u64, available_addresses: &mut [Address]) -> crate::Result<Vec<Address>> {
+        if target
+            > available_addresses
+                .iter()
+                .fold(0, |acc, address| acc + address.available_balance())
+        {
+            return Err(crate::WalletError::InsufficientFunds);
+        }
+
+        match self {
+            Self::BranchAndBound => Ok(select_branch_and_bound(target, available_addresses)),
+            Self::LargestFirst => {
+                available_addresses.sort_by(|a, b| b.available_balance().cmp(&a.available_balance()));
+                Ok(select_in_order(target, available_addresses))
+            }
+            Self::SmallestFirst => {
+                available_addresses.sort_by(|a, b| a.available_balance().cmp(&b.available_balance()));
+                Ok(select_in_order(target, available_addresses))
+            }
+            Self::OldestFirst => {
+                available_addresses.sort_by(|a, b| {
+                    a.key_index()
+                        .cmp(b.key_index())
+                        .then_with(|| a.internal().cmp(b.internal()))
+                });
+                Ok(select_in_order(target, available_addresses))
+            }
+            Self::Privacy => available_addresses
+                .iter()
+                .filter(|address| address.available_balance() >= target)
+                .min_by_key(|address| address.available_balance())
+                .map(|address| vec![address.clone()])
+                .ok_or(crate::WalletError::NoSingleInputAddress),
+            Self::Custom(selector) => selector.select(target, available_addresses),
+        }
     }
+}
 
+/// Selects the input addresses with the default strategy.
+#[cfg(test)]
+fn select_input(target: u64, available_utxos: &mut [Address]) -> crate::Result<Vec<Address>> {
+    InputSelectionStrategy::default().select(target, available_utxos)
+}
+
+fn select_branch_and_bound(target: u64, available_utxos: &mut [Address]) -> Vec<Address> {
     available_utxos.sort_by(|a, b| b.available_balance().cmp(&a.available_balance()));
     let mut selected_coins = Vec::new();
-    let result = branch_and_bound(
-        target,
-        available_utxos,
-        0,
-        &mut selected_coins,
-        0,
-        2i64.pow(available_utxos.len().try_into().unwrap()),
-    );
+    let mut tries = MAX_BRANCH_AND_BOUND_TRIES;
+    let result = branch_and_bound(target, available_utxos, 0, &mut selected_coins, 0, &mut tries);
 
     if result {
-        Ok(selected_coins)
+        selected_coins
     } else {
         // If no match, Single Random Draw
         single_random_draw(target, available_utxos)
     }
 }
 
-fn single_random_draw(target: u64, available_utxos: &mut [Address]) -> crate::Result<Vec<Address>> {
-    thread_rng().shuffle(available_utxos);
+/// Takes the addresses in the given order until the target is reached.
+fn select_in_order(target: u64, available_utxos: &[Address]) -> Vec<Address> {
     let mut sum = 0;
+    available_utxos
+        .iter()
+        .take_while(|address| {
+            let old_sum = sum;
+            sum += address.available_balance();
+            old_sum < target
+        })
+        .cloned()
+        .collect()
+}
 
-    let selected_coins_iter = available_utxos.iter_mut().take_while(|address| {
-        let value = address.available_balance();
-        let old_sum = sum;
-        sum += value;
-        old_sum < target
-    });
-    let mut selected_coins = vec![];
-    for coin in selected_coins_iter {
-        selected_coins.push(coin.clone());
-    }
-
-    Ok(selected_coins)
+fn single_random_draw(target: u64, available_utxos: &mut [Address]) -> Vec<Address> {
+    thread_rng().shuffle(available_utxos);
+    select_in_order(target, available_utxos)
 }
 
 fn branch_and_bound(
@@ -57,7 +149,7 @@ fn branch_and_bound(
     depth: usize,
     current_selection: &mut Vec<Address>,
     effective_value: u64,
-    mut tries: i64,
+    tries: &mut u64,
 ) -> bool {
     if effective_value > target {
         return false;
@@ -67,11 +159,11 @@ fn branch_and_bound(
         return true;
     }
 
-    if tries <= 0 || depth >= available_utxos.len() {
+    if *tries == 0 || depth >= available_utxos.len() {
         return false;
     }
 
-    tries -= 1;
+    *tries -= 1;
 
     // Exploring omission and inclusion branch
     let current_utxo_value = available_utxos[depth].available_balance();
@@ -100,12 +192,12 @@ fn branch_and_bound(
     )
 }
 
-// TODO use quickcheck or proptest
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::address::{Address, AddressBuilder, IotaAddress};
     use iota::message::prelude::Ed25519Address;
+    use proptest::{collection::vec, prelude::*};
     use rand::{Rng, SeedableRng, StdRng};
 
     fn generate_random_utxos(rng: &mut StdRng, utxos_number: usize) -> Vec<Address> {
@@ -187,4 +279,140 @@ mod tests {
             }
         }
     }
+
+    fn strategies() -> Vec<InputSelectionStrategy> {
+        vec![
+            InputSelectionStrategy::BranchAndBound,
+            InputSelectionStrategy::LargestFirst,
+            InputSelectionStrategy::SmallestFirst,
+            InputSelectionStrategy::OldestFirst,
+            InputSelectionStrategy::Privacy,
+        ]
+    }
+
+    fn build_addresses(balances: &[u64]) -> Vec<Address> {
+        balances
+            .iter()
+            .enumerate()
+            .map(|(index, balance)| {
+                AddressBuilder::new()
+                    .address(IotaAddress::Ed25519(Ed25519Address::new([index as u8; 32])))
+                    .balance(*balance)
+                    .key_index(index)
+                    .outputs(vec![])
+                    .build()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn sum(addresses: &[Address]) -> u64 {
+        addresses
+            .iter()
+            .fold(0, |acc, address| acc + address.available_balance())
+    }
+
+    /// Spends the newest addresses first.
+    struct NewestFirst;
+
+    impl InputSelector for NewestFirst {
+        fn select(&self, target: u64, available_addresses: &mut [Address]) -> crate::Result<Vec<Address>> {
+            available_addresses.sort_by(|a, b| b.key_index().cmp(a.key_index()));
+            Ok(select_in_order(target, available_addresses))
+        }
+    }
+
+    #[test]
+    fn custom_selector() {
+        let strategy = InputSelectionStrategy::Custom(Arc::new(NewestFirst));
+        let available = build_addresses(&[500, 500, 500]);
+
+        let selected = strategy.select(800, &mut available.clone()).unwrap();
+        let indexes: Vec<usize> = selected.iter().map(|a| *a.key_index()).collect();
+        assert_eq!(indexes, vec![2, 1]);
+        assert!(matches!(
+            strategy.select(1600, &mut available.clone()),
+            Err(crate::WalletError::InsufficientFunds)
+        ));
+
+        assert_eq!(strategy, strategy.clone());
+        assert_ne!(strategy, InputSelectionStrategy::Custom(Arc::new(NewestFirst)));
+        assert_ne!(strategy, InputSelectionStrategy::LargestFirst);
+        assert_eq!(
+            InputSelectionStrategy::LargestFirst,
+            InputSelectionStrategy::LargestFirst
+        );
+        // custom selectors can't be persisted
+        assert!(serde_json::to_string(&strategy).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn selection_covers_target(balances in vec(1u64..2000, 1..80), target_ratio in 0.0f64..1.0) {
+            let available = build_addresses(&balances);
+            let target = ((sum(&available) as f64 * target_ratio) as u64).max(1);
+            for strategy in strategies() {
+                match strategy.select(target, &mut available.clone()) {
+                    Ok(selected) => {
+                        prop_assert!(sum(&selected) >= target);
+                        let mut indexes: Vec<usize> = selected.iter().map(|a| *a.key_index()).collect();
+                        indexes.sort_unstable();
+                        indexes.dedup();
+                        prop_assert_eq!(indexes.len(), selected.len());
+                    }
+                    Err(crate::WalletError::NoSingleInputAddress) => {
+                        prop_assert_eq!(strategy.clone(), InputSelectionStrategy::Privacy);
+                        prop_assert!(available.iter().all(|a| a.available_balance() < target));
+                    }
+                    Err(e) => prop_assert!(false, "unexpected error {:?}", e),
+                }
+            }
+        }
+
+        #[test]
+        fn insufficient_funds_for_every_strategy(balances in vec(0u64..2000, 0..80)) {
+            let available = build_addresses(&balances);
+            let target = sum(&available) + 1;
+            for strategy in strategies() {
+                let response = strategy.select(target, &mut available.clone());
+                prop_assert!(matches!(response, Err(crate::WalletError::InsufficientFunds)));
+            }
+        }
+
+        #[test]
+        fn largest_first_uses_fewest_inputs(balances in vec(1u64..2000, 1..80), target_ratio in 0.0f64..1.0) {
+            let available = build_addresses(&balances);
+            let target = ((sum(&available) as f64 * target_ratio) as u64).max(1);
+            let largest_first = InputSelectionStrategy::LargestFirst.select(target, &mut available.clone()).unwrap();
+            for strategy in strategies() {
+                if let Ok(selected) = strategy.select(target, &mut available.clone()) {
+                    prop_assert!(largest_first.len() <= selected.len());
+                }
+            }
+        }
+
+        #[test]
+        fn oldest_first_selects_lowest_indexes(balances in vec(1u64..2000, 1..80), target_ratio in 0.0f64..1.0) {
+            let available = build_addresses(&balances);
+            let target = ((sum(&available) as f64 * target_ratio) as u64).max(1);
+            let selected = InputSelectionStrategy::OldestFirst.select(target, &mut available.clone()).unwrap();
+            let indexes: Vec<usize> = selected.iter().map(|a| *a.key_index()).collect();
+            prop_assert_eq!(indexes, (0..selected.len()).collect::<Vec<usize>>());
+        }
+
+        #[test]
+        fn privacy_never_mixes_addresses(balances in vec(1u64..2000, 1..80), target in 1u64..2000) {
+            let available = build_addresses(&balances);
+            if let Ok(selected) = InputSelectionStrategy::Privacy.select(target, &mut available.clone()) {
+                prop_assert_eq!(selected.len(), 1);
+            }
+        }
+
+        #[test]
+        fn branch_and_bound_doesnt_overflow(balances in vec(1u64..10, 63..100)) {
+            let mut available = build_addresses(&balances);
+            let target = sum(&available) / 2;
+            prop_assert!(select_input(target, &mut available).is_ok());
+        }
+    }
 }
@@ -3,31 +3,47 @@
 
 use crate::{
     account::{get_account_addresses_lock, Account, AccountIdentifier},
-    address::{Address, AddressBuilder, AddressOutput, IotaAddress},
+    address::{Address, AddressBuilder, AddressOutput, IotaAddress, OutputId},
     client::get_client,
-    message::{Message, RemainderValueStrategy, Transfer},
+    message::{InputLimitStrategy, Message, RemainderValueStrategy, Transfer, TransferOutput},
+    outbox::{OutboxEntry, OutboxState, TransferChain, TransferChainState},
+    validation::{TransactionValidationError, DUST_THRESHOLD},
 };
 
 use getset::Getters;
-use iota::message::prelude::{
-    Input, Message as IotaMessage, MessageId, Payload, SignatureLockedSingleOutput, Transaction, TransactionEssence,
-    UTXOInput,
+use iota::{
+    client::Client,
+    message::prelude::{
+        Input, Message as IotaMessage, MessageId, Payload, SignatureLockedSingleOutput, Transaction,
+        TransactionEssence, UTXOInput,
+    },
 };
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use slip10::BIP32Path;
 
 use std::{
+    collections::{HashSet, VecDeque},
     convert::TryInto,
     num::NonZeroU64,
     path::PathBuf,
-    sync::{mpsc::channel, Arc, Mutex, MutexGuard},
+    sync::{mpsc::channel, Arc, Mutex},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 mod input_selection;
+pub use input_selection::{InputSelectionStrategy, InputSelector};
 
 const OUTPUT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a transfer waits for its consolidation messages to be confirmed.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(300);
+/// The interval between the account syncs while waiting for a confirmation.
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The protocol's maximum number of inputs per transaction.
+pub const MAX_INPUTS_COUNT: usize = 127;
+/// The protocol's maximum number of outputs per transaction.
+pub const MAX_OUTPUTS_COUNT: usize = 127;
 
 /// Syncs addresses with the tangle.
 /// The method ensures that the wallet local state has all used addresses plus an unused address.
@@ -47,7 +63,7 @@ const OUTPUT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
 /// and the messages associated with the addresses.
 async fn sync_addresses(
     storage_path: &PathBuf,
-    account: &'_ Account,
+    account: &'_ mut Account,
     address_index: usize,
     gap_limit: usize,
 ) -> crate::Result<(Vec<Address>, Vec<(MessageId, IotaMessage)>)> {
@@ -59,12 +75,10 @@ async fn sync_addresses(
     let mut generated_addresses = vec![];
     let mut found_messages = vec![];
     loop {
-        let mut generated_iota_addresses = vec![]; // collection of (address_index, internal, address) pairs
-        for i in address_index..(address_index + gap_limit) {
-            // generate both `public` and `internal (change)` addresses
-            generated_iota_addresses.push((i, false, crate::address::get_iota_address(&account, i, false)?));
-            generated_iota_addresses.push((i, true, crate::address::get_iota_address(&account, i, true)?));
-        }
+        // generate both `public` and `internal (change)` addresses
+        // collection of (address_index, internal, address) pairs
+        let generated_iota_addresses =
+            crate::address::get_iota_addresses(account, address_index..(address_index + gap_limit))?;
 
         let mut curr_generated_addresses = vec![];
         let mut curr_found_messages = vec![];
@@ -104,6 +118,7 @@ async fn sync_addresses(
 
             let address = AddressBuilder::new()
                 .address(iota_address.clone())
+                .bech32_hrp(account.bech32_hrp())
                 .key_index(*iota_address_index)
                 .balance(balance)
                 .outputs(curr_found_outputs)
@@ -187,6 +202,7 @@ async fn update_account_messages<'a>(
         let confirmed = metadata.ledger_inclusion_state.as_deref() == Some("included");
         if confirmed {
             message.set_confirmed(true);
+            account.on_message_confirmed(message.id());
         } else {
             account.on_message_unconfirmed(message.id());
         }
@@ -197,13 +213,35 @@ async fn update_account_messages<'a>(
     Ok(())
 }
 
+/// Fetches the bech32 human-readable part from the node info.
+/// Returns `None` if the node can't be reached or reports an invalid HRP.
+async fn fetch_node_bech32_hrp(account: &Account) -> Option<String> {
+    let client = get_client(account.client_options());
+    let client = client.read().unwrap();
+    let info = client.get_info().await.ok()?;
+    if crate::address::is_valid_bech32_hrp(&info.bech32_hrp) {
+        Some(info.bech32_hrp)
+    } else {
+        None
+    }
+}
+
 async fn perform_sync(
     mut account: &mut Account,
     storage_path: &PathBuf,
     address_index: usize,
     gap_limit: usize,
 ) -> crate::Result<bool> {
-    let (found_addresses, found_messages) = sync_addresses(&storage_path, &account, address_index, gap_limit).await?;
+    // the addresses found on this sync are encoded with the node's HRP;
+    // the previously known HRP is kept if the node info isn't available
+    if let Some(bech32_hrp) = fetch_node_bech32_hrp(&account).await {
+        account.set_node_bech32_hrp(Some(bech32_hrp));
+    }
+    // detects nodes of another network before their data is merged into the account
+    account.resolve_network_id().await?;
+
+    let (found_addresses, found_messages) =
+        sync_addresses(&storage_path, &mut account, address_index, gap_limit).await?;
 
     let mut new_messages = vec![];
     for (found_message_id, found_message) in found_messages {
@@ -255,8 +293,8 @@ async fn perform_sync(
 
     let parsed_messages = new_messages
         .iter()
-        .map(|(id, message)| Message::from_iota_message(*id, account.addresses(), &message).unwrap())
-        .collect();
+        .map(|(id, message)| Message::from_iota_message(*id, account.addresses(), &message, &account.bech32_hrp()))
+        .collect::<crate::Result<Vec<Message>>>()?;
     account.append_messages(parsed_messages);
 
     Ok(is_empty)
@@ -316,8 +354,11 @@ impl<'a> AccountSynchronizer<'a> {
         let return_value =
             match perform_sync(&mut account_, &self.storage_path, self.address_index, self.gap_limit).await {
                 Ok(is_empty) => {
+                    self.account.set_node_bech32_hrp(account_.node_bech32_hrp().clone());
+                    self.account.set_network_id(*account_.network_id());
                     self.account.set_addresses(account_.addresses().to_vec());
                     self.account.set_messages(account_.messages().to_vec());
+                    self.account.set_address_cache(account_.address_cache().clone());
                     if !self.skip_persistance {
                         crate::storage::with_adapter(&self.storage_path, |storage| {
                             storage.set(self.account.id().into(), serde_json::to_string(&self.account)?)
@@ -369,11 +410,629 @@ pub struct SyncedAccount {
     storage_path: PathBuf,
 }
 
+/// Output consolidation options.
+#[derive(Debug, Clone, Default)]
+pub struct ConsolidationOptions {
+    target_address: Option<IotaAddress>,
+    min_outputs: usize,
+}
+
+impl ConsolidationOptions {
+    /// Initialises the default consolidation options:
+    /// the outputs are merged into the account's latest address.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the address receiving the consolidated outputs. It must belong to the account.
+    pub fn target_address(mut self, address: IotaAddress) -> Self {
+        self.target_address = Some(address);
+        self
+    }
+
+    /// Skips the consolidation when the account has less than `min_outputs` outputs to merge.
+    pub fn min_outputs(mut self, min_outputs: usize) -> Self {
+        self.min_outputs = min_outputs;
+        self
+    }
+}
+
+/// Transfer preview, computed without locking addresses, signing or posting anything.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct TransferPreview {
+    /// The outputs that would be spent, with their addresses.
+    inputs: Vec<(IotaAddress, AddressOutput)>,
+    /// The recipient outputs, merged by address.
+    outputs: Vec<TransferOutput>,
+    /// The remainder value.
+    remainder_value: u64,
+    /// The address receiving the remainder value, resolved from the transfer's `RemainderValueStrategy`.
+    remainder_address: Option<IotaAddress>,
+    /// The addresses that would be generated by the transfer.
+    generated_addresses: Vec<Address>,
+}
+
+/// Gets the outputs spent by the account's transfers in progress.
+/// A signed message may reach the node even if posting it failed, so its inputs stay reserved until the outbox
+/// reconciliation settles the transfer.
+fn reserved_outputs(account: &Account) -> Vec<&OutputId> {
+    account
+        .outbox()
+        .iter()
+        .filter(|entry| entry.is_in_progress())
+        .flat_map(|entry| entry.inputs())
+        .collect()
+}
+
+/// Gets the addresses holding the explicitly selected outputs, keeping only those outputs on each address.
+fn select_explicit_inputs(
+    locked_addresses: &[IotaAddress],
+    threshold: u64,
+    account: &Account,
+    recipients: &[IotaAddress],
+    inputs: &[OutputId],
+) -> crate::Result<Vec<Address>> {
+    let reserved_outputs = reserved_outputs(account);
+    let mut addresses = vec![];
+    for input in inputs {
+        let address = account
+            .addresses()
+            .iter()
+            .find(|a| a.available_outputs().iter().any(|output| &output.id() == input))
+            .ok_or_else(|| crate::WalletError::InputNotAvailable(input.clone()))?;
+        if locked_addresses.contains(address.address()) || reserved_outputs.contains(&input) {
+            return Err(crate::WalletError::InputNotAvailable(input.clone()));
+        }
+        if recipients.contains(address.address()) {
+            return Err(anyhow::anyhow!("can't spend outputs of a recipient address").into());
+        }
+        if !addresses.contains(address) {
+            let mut address = address.clone();
+            address.retain_available_outputs(|output| inputs.contains(&output.id()));
+            addresses.push(address);
+        }
+    }
+    if addresses.iter().fold(0, |acc, a| acc + a.available_balance()) < threshold {
+        return Err(crate::WalletError::InsufficientFunds);
+    }
+    Ok(addresses)
+}
+
+/// Removes the addresses from the account's list of addresses used by transfers in progress.
+/// Releases the input addresses of a transfer once it's sent or if it fails.
+/// If it fails before the message is posted, the outbox entry is marked as failed so the inputs can be spent again.
+struct TransferGuard {
+    storage_path: PathBuf,
+    account_id: AccountIdentifier,
+    outbox_entry_id: String,
+    input_addresses: Vec<Address>,
+    posted: bool,
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        if !self.posted {
+            if let Ok(mut account) = crate::storage::get_account(&self.storage_path, self.account_id.clone()) {
+                account.update_outbox_entry(&self.outbox_entry_id, |entry| entry.set_state(OutboxState::Failed));
+                let _ = account.save();
+            }
+        }
+        release_addresses(&self.account_id, &self.input_addresses);
+    }
+}
+
+fn release_addresses(account_id: &AccountIdentifier, addresses: &[Address]) {
+    let account_addresses_locker = get_account_addresses_lock(account_id.clone());
+    let mut locked_addresses = account_addresses_locker.lock().unwrap();
+    for address in addresses {
+        if let Some(index) = locked_addresses.iter().position(|a| a == address.address()) {
+            locked_addresses.remove(index);
+        }
+    }
+}
+
+/// The idempotency keys of the transfers being sent by this process, with their account identifiers.
+static SENDING_IDEMPOTENCY_KEYS: OnceCell<Mutex<HashSet<(String, String)>>> = OnceCell::new();
+
+/// Marks a transfer with an idempotency key as being sent until it's dropped,
+/// so its retries don't mistake the transactions already sent by a transfer chain for its outcome.
+struct IdempotencyKeyGuard {
+    account_id: String,
+    idempotency_key: String,
+}
+
+impl IdempotencyKeyGuard {
+    fn acquire(account_id: &str, idempotency_key: &str) -> crate::Result<Self> {
+        let mut keys = SENDING_IDEMPOTENCY_KEYS.get_or_init(Default::default).lock().unwrap();
+        if !keys.insert((account_id.to_string(), idempotency_key.to_string())) {
+            return Err(crate::WalletError::TransferInProgress(idempotency_key.to_string()));
+        }
+        Ok(Self {
+            account_id: account_id.to_string(),
+            idempotency_key: idempotency_key.to_string(),
+        })
+    }
+}
+
+impl Drop for IdempotencyKeyGuard {
+    fn drop(&mut self) {
+        let mut keys = SENDING_IDEMPOTENCY_KEYS.get_or_init(Default::default).lock().unwrap();
+        keys.remove(&(self.account_id.clone(), self.idempotency_key.clone()));
+    }
+}
+
+/// Gets the result of the account's transfer with the given idempotency key, if it was already sent.
+///
+/// A transfer chain returns its stored outcome: the messages of a failed or interrupted chain are returned as a
+/// `PartialTransfer` error instead of a success, and nothing is sent again.
+fn find_idempotent_transfer(account: &Account, idempotency_key: &str) -> crate::Result<Option<TransferMetadata>> {
+    let messages: Vec<Message> = account
+        .messages()
+        .iter()
+        .filter(|message| message.idempotency_key.as_deref() == Some(idempotency_key))
+        .cloned()
+        .collect();
+    let in_progress = account
+        .outbox()
+        .iter()
+        .any(|entry| entry.idempotency_key().as_deref() == Some(idempotency_key) && entry.is_in_progress());
+    let chain = account
+        .transfer_chains()
+        .iter()
+        .find(|chain| chain.idempotency_key() == idempotency_key);
+
+    let amount = match chain {
+        Some(chain) => match chain.state() {
+            TransferChainState::Completed => *chain.amount(),
+            TransferChainState::Failed => {
+                return Err(crate::WalletError::PartialTransfer {
+                    messages,
+                    error: chain.error().clone().unwrap_or_default(),
+                })
+            }
+            // the process stopped while sending the chain; if nothing was sent, the transfer is sent again
+            TransferChainState::InProgress if !messages.is_empty() && !in_progress => {
+                return Err(crate::WalletError::PartialTransfer {
+                    messages,
+                    error: "the transfer was interrupted before sending all its transactions".to_string(),
+                });
+            }
+            TransferChainState::InProgress if !messages.is_empty() => {
+                return Err(crate::WalletError::TransferInProgress(idempotency_key.to_string()));
+            }
+            TransferChainState::InProgress => 0,
+        },
+        None => messages
+            .iter()
+            .fold(0, |acc, message| acc + message.transfer_amount.unwrap_or_default()),
+    };
+    if let Some(message) = messages.last() {
+        return Ok(Some(TransferMetadata {
+            message: message.clone(),
+            amount,
+            messages,
+            account: account.clone(),
+        }));
+    }
+    if in_progress {
+        return Err(crate::WalletError::TransferInProgress(idempotency_key.to_string()));
+    }
+    Ok(None)
+}
+
+/// A transfer whose transaction is signed and validated but not posted yet.
+/// Its outbox entry is stored and its input addresses are locked until the transfer guard is dropped.
+struct PreparedTransfer {
+    account: Account,
+    transaction: Transaction,
+    outbox_entry_id: String,
+    transfer_guard: TransferGuard,
+    input_addresses: Vec<Address>,
+    recipients: Vec<IotaAddress>,
+    remainder_value_deposit_address: Option<IotaAddress>,
+    addresses_to_watch: Vec<IotaAddress>,
+    value: u64,
+    /// The value sent to addresses outside of the account.
+    spent_value: u64,
+    idempotency_key: Option<String>,
+}
+
+enum PrepareResult {
+    Prepared(Box<PreparedTransfer>),
+    /// No transaction is needed: the transfer was already sent or its inputs must be split first.
+    Done(SendResult),
+}
+
+enum SendResult {
+    Sent(TransferMetadata),
+    /// The selected inputs exceed `MAX_INPUTS_COUNT`; nothing was sent.
+    InputLimitExceeded(Vec<Address>),
+}
+
+/// Wraps the error of a transfer chain step, keeping the messages already posted by the chain.
+fn partial_transfer_error(messages: Vec<Message>, error: crate::WalletError) -> crate::WalletError {
+    if messages.is_empty() {
+        error
+    } else {
+        crate::WalletError::PartialTransfer {
+            messages,
+            error: error.to_string(),
+        }
+    }
+}
+
+fn count_outputs(addresses: &[Address]) -> usize {
+    addresses
+        .iter()
+        .fold(0, |acc, address| acc + address.available_outputs().len())
+}
+
+/// Sums the outputs amount, failing if it overflows.
+fn sum_outputs(outputs: &[&AddressOutput]) -> crate::Result<u64> {
+    outputs
+        .iter()
+        .try_fold(0u64, |acc, output| acc.checked_add(*output.amount()))
+        .ok_or_else(|| anyhow::anyhow!("the outputs sum overflows").into())
+}
+
+/// Gets the amount, up to `available`, that a step of a split transfer sends to the pending outputs.
+/// Neither the step's share of an output, the share left for the next steps nor the value the step holds back as
+/// remainder may be below `DUST_THRESHOLD`, so the step sends less than `available` if needed.
+fn split_step_amount(pending_outputs: &VecDeque<TransferOutput>, available: u64) -> crate::Result<u64> {
+    // the pending outputs boundaries: a step ending on one of them doesn't split an output
+    let mut boundaries = vec![0u64];
+    for output in pending_outputs {
+        let last = *boundaries.last().unwrap();
+        boundaries.push(last.saturating_add(output.amount));
+    }
+    let fits = |amount: u64| {
+        boundaries.windows(2).any(|window| {
+            amount == window[1]
+                || (amount >= window[0].saturating_add(DUST_THRESHOLD)
+                    && amount.saturating_add(DUST_THRESHOLD) <= window[1])
+        })
+    };
+    if fits(available) {
+        return Ok(available);
+    }
+
+    // otherwise the step holds back at least `DUST_THRESHOLD` as remainder
+    let max_amount = available.saturating_sub(DUST_THRESHOLD);
+    let amount = boundaries
+        .windows(2)
+        .filter_map(|window| {
+            if window[1] <= max_amount {
+                Some(window[1])
+            } else {
+                let amount = std::cmp::min(max_amount, window[1].saturating_sub(DUST_THRESHOLD));
+                if amount >= window[0].saturating_add(DUST_THRESHOLD) {
+                    Some(amount)
+                } else {
+                    None
+                }
+            }
+        })
+        .filter(|amount| *amount > 0)
+        .max();
+    amount.ok_or_else(|| {
+        // the dust share left by sending everything
+        let dust = boundaries
+            .windows(2)
+            .find(|window| available < window[1])
+            .map(|window| std::cmp::min(available - window[0], window[1] - available))
+            .unwrap_or(available);
+        crate::WalletError::DustRemainder(dust)
+    })
+}
+
+/// Splits a transfer into transfers with at most `MAX_INPUTS_COUNT` explicit inputs each.
+/// The largest outputs are spent first to minimize the number of transactions.
+///
+/// Every step is planned before anything is sent, so its outputs and remainder are either empty or at least
+/// `DUST_THRESHOLD`; the transfer fails with `DustRemainder` if the outputs can't be split that way.
+fn split_transfer(transfer_obj: &Transfer, input_addresses: &[Address]) -> crate::Result<Vec<Transfer>> {
+    let mut utxos: Vec<&AddressOutput> = input_addresses.iter().flat_map(|a| a.available_outputs()).collect();
+    utxos.sort_by(|a, b| b.amount().cmp(a.amount()));
+    let total = sum_outputs(&utxos)?;
+    let mut utxos: VecDeque<&AddressOutput> = utxos.into_iter().collect();
+
+    let mut pending_outputs = transfer_obj
+        .merged_outputs()
+        .into_iter()
+        .collect::<VecDeque<TransferOutput>>();
+    if transfer_obj.send_max {
+        if let Some(output) = pending_outputs.front_mut() {
+            output.amount = total;
+        }
+    }
+    let amount = pending_outputs
+        .iter()
+        .try_fold(0u64, |acc, output| acc.checked_add(output.amount))
+        .ok_or_else(|| crate::WalletError::AmountOverflow(transfer_obj.amount().to_string()))?;
+    if amount > total {
+        return Err(crate::WalletError::InsufficientFunds);
+    }
+
+    let mut transfers = vec![];
+    while !pending_outputs.is_empty() {
+        let pending_sum = pending_outputs.iter().fold(0, |acc, output| acc + output.amount);
+        // only the inputs needed by the step are spent
+        let mut chunk = vec![];
+        let mut available = 0;
+        while chunk.len() < MAX_INPUTS_COUNT && available < pending_sum {
+            match utxos.pop_front() {
+                Some(utxo) => {
+                    available += *utxo.amount();
+                    chunk.push(utxo);
+                }
+                None => break,
+            }
+        }
+
+        let mut step_amount = if available >= pending_sum {
+            // the last step: an additional input raises a dust remainder
+            while available > pending_sum && available - pending_sum < DUST_THRESHOLD && chunk.len() < MAX_INPUTS_COUNT
+            {
+                match utxos.pop_front() {
+                    Some(utxo) => {
+                        available += *utxo.amount();
+                        chunk.push(utxo);
+                    }
+                    None => break,
+                }
+            }
+            let remainder = available - pending_sum;
+            if remainder > 0 && remainder < DUST_THRESHOLD {
+                return Err(crate::WalletError::DustRemainder(remainder));
+            }
+            pending_sum
+        } else if utxos.is_empty() {
+            // the remainders held back by the previous steps left too little for the last one
+            return Err(crate::WalletError::DustRemainder(total - amount));
+        } else {
+            split_step_amount(&pending_outputs, available)?
+        };
+
+        let mut outputs = vec![];
+        while step_amount > 0 {
+            let pending_output = match pending_outputs.front_mut() {
+                Some(output) => output,
+                None => break,
+            };
+            let amount = std::cmp::min(pending_output.amount, step_amount);
+            outputs.push(TransferOutput {
+                amount,
+                ..pending_output.clone()
+            });
+            step_amount -= amount;
+            pending_output.amount -= amount;
+            if pending_output.amount == 0 {
+                pending_outputs.pop_front();
+            }
+        }
+
+        let mut transfer = transfer_obj.clone();
+        transfer.outputs = outputs;
+        transfer.inputs = chunk.iter().map(|output| output.id()).collect();
+        transfer.input_limit_strategy = InputLimitStrategy::Fail;
+        transfer.send_max = false;
+        // only the first transaction carries the indexation payload
+        if !transfers.is_empty() {
+            transfer.index = None;
+            transfer.data = None;
+        }
+        transfers.push(transfer);
+    }
+    Ok(transfers)
+}
+
+/// Builds the transfers consolidating the selected outputs into the account's latest address,
+/// until the remaining outputs fit in a single transaction.
+fn consolidation_transfers(account: &Account, input_addresses: &[Address]) -> crate::Result<Vec<Transfer>> {
+    let target_address = account
+        .latest_address()
+        .ok_or_else(|| anyhow::anyhow!("account address list empty"))?
+        .address()
+        .clone();
+    let utxos: Vec<&AddressOutput> = input_addresses
+        .iter()
+        .filter(|a| a.address() != &target_address)
+        .flat_map(|a| a.available_outputs())
+        .collect();
+
+    let mut remaining_count = count_outputs(input_addresses);
+    let mut transfers = vec![];
+    for chunk in utxos.chunks(MAX_INPUTS_COUNT) {
+        if remaining_count <= MAX_INPUTS_COUNT {
+            break;
+        }
+        transfers.push(chunk_transfer(&target_address, chunk));
+        // the chunk is replaced by a single output
+        remaining_count -= chunk.len() - 1;
+    }
+    Ok(transfers)
+}
+
+/// Gets the account addresses that the automatic input selection can spend from,
+/// keeping only the outputs that aren't frozen.
+fn spendable_addresses(
+    locked_addresses: &[IotaAddress],
+    account: &Account,
+    recipients: &[IotaAddress],
+    transfer_obj: &Transfer,
+) -> Vec<Address> {
+    let reserved_outputs = reserved_outputs(account);
+    account
+        .addresses()
+        .iter()
+        .filter(|a| {
+            !recipients.contains(a.address())
+                && !locked_addresses.contains(a.address())
+                && !account.frozen_addresses().contains(a.address())
+                && (transfer_obj.source_addresses.is_empty() || transfer_obj.source_addresses.contains(a.address()))
+        })
+        .cloned()
+        .map(|mut a| {
+            a.retain_available_outputs(|output| {
+                !account.frozen_outputs().contains(&output.id()) && !reserved_outputs.contains(&&output.id())
+            });
+            a
+        })
+        .filter(|a| a.available_balance() > 0)
+        .collect()
+}
+
+/// Sets the amount of a send-max transfer to the value of the outputs it can spend.
+fn resolve_send_max_amount(
+    transfer_obj: &mut Transfer,
+    locked_addresses: &[IotaAddress],
+    account: &Account,
+) -> crate::Result<()> {
+    if !transfer_obj.send_max {
+        return Ok(());
+    }
+    let recipients: Vec<IotaAddress> = transfer_obj.outputs.iter().map(|o| o.address().clone()).collect();
+    let amount = if transfer_obj.inputs.is_empty() {
+        spendable_addresses(locked_addresses, account, &recipients, transfer_obj)
+            .iter()
+            .fold(0, |acc, a| acc + a.available_balance())
+    } else {
+        select_explicit_inputs(locked_addresses, 0, account, &recipients, &transfer_obj.inputs)?
+            .iter()
+            .fold(0, |acc, a| acc + a.available_balance())
+    };
+    if amount == 0 {
+        return Err(crate::WalletError::InsufficientFunds);
+    }
+    transfer_obj.outputs[0].amount = amount;
+    Ok(())
+}
+
+/// Gets the available outputs that consolidations and sweeps move to the target address:
+/// the outputs of the other addresses, skipping the frozen addresses and outputs.
+pub(crate) fn movable_outputs<'a>(account: &'a Account, target_address: &IotaAddress) -> Vec<&'a AddressOutput> {
+    account
+        .addresses()
+        .iter()
+        .filter(|a| a.address() != target_address && !account.frozen_addresses().contains(a.address()))
+        .flat_map(|a| a.available_outputs())
+        .filter(|output| !account.frozen_outputs().contains(&output.id()))
+        .collect()
+}
+
+/// Builds a transfer moving every output of the chunk to the target address.
+fn chunk_transfer(target_address: &IotaAddress, chunk: &[&AddressOutput]) -> Transfer {
+    let amount = chunk.iter().fold(0, |acc, output| acc + *output.amount());
+    Transfer::new(target_address.clone(), amount)
+        .inputs(chunk.iter().map(|output| output.id()).collect())
+        .input_limit_strategy(InputLimitStrategy::Fail)
+}
+
+fn validate_transfer(transfer_obj: &Transfer) -> crate::Result<()> {
+    if transfer_obj.outputs().is_empty() {
+        return Err(anyhow::anyhow!("the transfer must have at least one output").into());
+    }
+    if transfer_obj.send_max {
+        if transfer_obj.outputs().len() > 1 {
+            return Err(anyhow::anyhow!("send-max transfers must have a single output").into());
+        }
+        // the amount is resolved from the available outputs
+        return Ok(());
+    }
+    if transfer_obj.merged_outputs().len() > MAX_OUTPUTS_COUNT {
+        return Err(crate::WalletError::TooManyOutputs {
+            count: transfer_obj.merged_outputs().len(),
+            max: MAX_OUTPUTS_COUNT,
+        });
+    }
+    if transfer_obj.outputs().iter().any(|output| *output.amount() == 0) {
+        return Err(crate::WalletError::ZeroAmount);
+    }
+    if transfer_obj.checked_amount().is_none() {
+        let amounts: Vec<String> = transfer_obj
+            .outputs()
+            .iter()
+            .map(|output| output.amount().to_string())
+            .collect();
+        return Err(crate::WalletError::AmountOverflow(amounts.join(" + ")));
+    }
+    if let Some((index, output)) = transfer_obj
+        .merged_outputs()
+        .iter()
+        .enumerate()
+        .find(|(_, output)| *output.amount() < DUST_THRESHOLD)
+    {
+        return Err(TransactionValidationError::DustOutput {
+            index,
+            amount: *output.amount(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn validate_remainder_value_strategy(account: &Account, strategy: &RemainderValueStrategy) -> crate::Result<()> {
+    if let RemainderValueStrategy::AccountAddress(remainder_target_address) = strategy {
+        if !account
+            .addresses()
+            .iter()
+            .any(|addr| addr.address() == remainder_target_address)
+        {
+            return Err(crate::WalletError::InvalidRemainderValueAddress);
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the address receiving the remainder value.
+/// Returns the target address and the change address that must be generated for it, if any.
+fn resolve_remainder_target(
+    account: &mut Account,
+    strategy: &RemainderValueStrategy,
+    remainder_address: &Address,
+) -> crate::Result<(IotaAddress, Option<Address>)> {
+    let target = match strategy {
+        // use one of the account's addresses to send the remainder value
+        RemainderValueStrategy::AccountAddress(target_address) => (target_address.clone(), None),
+        // generate a new change address to send the remainder value
+        RemainderValueStrategy::ChangeAddress => {
+            let change_address = crate::address::get_new_change_address(account)?;
+            (change_address.address().clone(), Some(change_address))
+        }
+        // keep the remainder value on the address
+        RemainderValueStrategy::ReuseAddress => (remainder_address.address().clone(), None),
+    };
+    Ok(target)
+}
+
+/// Resolves the remainder target of a transfer being sent.
+/// A generated change address is added to the account and its index recorded, so the next transfer moves forward on
+/// the change chain. Returns the target address and the generated change address, if any.
+fn reserve_remainder_target(
+    account: &mut Account,
+    strategy: &RemainderValueStrategy,
+    remainder_address: &Address,
+) -> crate::Result<(IotaAddress, Option<IotaAddress>)> {
+    let (target_address, change_address) = resolve_remainder_target(account, strategy, remainder_address)?;
+    let change_address = change_address.map(|change_address| {
+        account.record_change_index(*change_address.key_index());
+        let address = change_address.address().clone();
+        account.append_addresses(vec![change_address]);
+        address
+    });
+    Ok((target_address, change_address))
+}
+
 /// Transfer response metadata.
 #[derive(Debug)]
 pub struct TransferMetadata {
-    /// The transfer message.
+    /// The transfer message. If the transfer was split or needed consolidation, this is the last message sent.
     pub message: Message,
+    /// Every message sent by the transfer, including the consolidation and split transactions.
+    pub messages: Vec<Message>,
+    /// The value sent to the transfer recipients. On send-max transfers, it's the resolved balance.
+    pub amount: u64,
     /// The transfer source account with new message and addresses attached.
     pub account: Account,
 }
@@ -385,33 +1044,58 @@ impl SyncedAccount {
     /// # Arguments
     ///
     /// * `threshold` Amount user wants to spend.
-    /// * `address` Recipient address.
+    /// * `recipients` Recipient addresses.
+    /// * `transfer_obj` The transfer, defining the explicit inputs, source addresses and input selection strategy.
     ///
     /// # Return value
     ///
     /// Returns a (addresses, address) tuple representing the selected input addresses and the remainder address if
-    /// needed.
+    /// needed. The selected addresses only hold the outputs that must be spent.
+    ///
+    /// If the selected inputs leave a remainder below the dust threshold, the inputs are selected again to cover
+    /// a remainder output of at least `DUST_THRESHOLD`; the transfer fails with `DustRemainder` if that's not possible.
     fn select_inputs<'a>(
         &self,
-        locked_addresses: &'a mut MutexGuard<'_, Vec<IotaAddress>>,
+        locked_addresses: &'a [IotaAddress],
         threshold: u64,
-        account: &'a mut Account,
-        address: &'a IotaAddress,
+        account: &'a Account,
+        recipients: &'a [IotaAddress],
+        transfer_obj: &Transfer,
     ) -> crate::Result<(Vec<Address>, Option<Address>)> {
-        let mut available_addresses: Vec<Address> = account
-            .addresses()
-            .iter()
-            .cloned()
-            .filter(|a| a.address() != address && a.available_balance() > 0 && !locked_addresses.contains(a.address()))
-            .collect();
-        let addresses = input_selection::select_input(threshold, &mut available_addresses)?;
+        let select = |target: u64| {
+            if transfer_obj.inputs.is_empty() {
+                let mut available_addresses = spendable_addresses(locked_addresses, account, recipients, transfer_obj);
+                let strategy = transfer_obj
+                    .input_selection_strategy
+                    .as_ref()
+                    .unwrap_or_else(|| account.input_selection_strategy());
+                strategy.select(target, &mut available_addresses)
+            } else {
+                select_explicit_inputs(locked_addresses, target, account, recipients, &transfer_obj.inputs)
+            }
+        };
 
-        locked_addresses.extend(
-            addresses
-                .iter()
-                .map(|a| a.address().clone())
-                .collect::<Vec<IotaAddress>>(),
-        );
+        let mut addresses = select(threshold)?;
+        let remainder_value = addresses
+            .iter()
+            .fold(0, |acc, a| acc + a.available_balance())
+            .saturating_sub(threshold);
+        if remainder_value > 0 && remainder_value < DUST_THRESHOLD {
+            // explicit inputs can't be changed, so the dust remainder can't be avoided
+            if !transfer_obj.inputs.is_empty() {
+                return Err(crate::WalletError::DustRemainder(remainder_value));
+            }
+            let target = threshold
+                .checked_add(DUST_THRESHOLD)
+                .ok_or_else(|| crate::WalletError::DustRemainder(remainder_value))?;
+            addresses = match select(target) {
+                Ok(addresses) => addresses,
+                Err(crate::WalletError::InsufficientFunds) | Err(crate::WalletError::NoSingleInputAddress) => {
+                    return Err(crate::WalletError::DustRemainder(remainder_value))
+                }
+                Err(e) => return Err(e),
+            };
+        }
 
         let remainder = if addresses.iter().fold(0, |acc, a| acc + a.available_balance()) > threshold {
             addresses.last().cloned()
@@ -422,12 +1106,223 @@ impl SyncedAccount {
         Ok((addresses, remainder))
     }
 
+    /// Previews a transfer without locking addresses, signing or posting anything.
+    ///
+    /// The preview is computed from the stored account state, so it's only accurate if the account is synced
+    /// and no other transfer is in progress.
+    pub fn preview_transfer(&self, transfer_obj: &Transfer) -> crate::Result<TransferPreview> {
+        validate_transfer(transfer_obj)?;
+
+        let account_id: AccountIdentifier = self.account_id.clone().into();
+        let mut account = crate::storage::get_account(&self.storage_path, account_id.clone())?;
+        // skip the addresses used by transfers in progress, without holding the lock
+        let locked_addresses = get_account_addresses_lock(account_id).lock().unwrap().clone();
+        let mut transfer_obj = transfer_obj.clone();
+        transfer_obj.use_bech32_hrp(&account.bech32_hrp())?;
+        resolve_send_max_amount(&mut transfer_obj, &locked_addresses, &account)?;
+
+        let value = transfer_obj.amount();
+        if value > account.total_balance() {
+            return Err(crate::WalletError::InsufficientFunds);
+        }
+        validate_remainder_value_strategy(&account, &transfer_obj.remainder_value_strategy)?;
+
+        let outputs = transfer_obj.merged_outputs();
+        let recipients: Vec<IotaAddress> = outputs.iter().map(|o| o.address().clone()).collect();
+        let (input_addresses, remainder_address) =
+            self.select_inputs(&locked_addresses, value, &account, &recipients, &transfer_obj)?;
+
+        let mut inputs = vec![];
+        for input_address in &input_addresses {
+            for output in input_address.available_outputs() {
+                inputs.push((input_address.address().clone(), output.clone()));
+            }
+        }
+        let input_sum = inputs.iter().fold(0, |acc, (_, output)| acc + *output.amount());
+        let remainder_value = input_sum
+            .checked_sub(value)
+            .ok_or(crate::WalletError::InsufficientFunds)?;
+
+        let mut generated_addresses = vec![];
+        let mut remainder_address_target = None;
+        if remainder_value > 0 {
+            let remainder_address =
+                remainder_address.ok_or_else(|| anyhow::anyhow!("remainder address not defined"))?;
+            let (target_address, change_address) =
+                resolve_remainder_target(&mut account, &transfer_obj.remainder_value_strategy, &remainder_address)?;
+            generated_addresses.extend(change_address);
+            remainder_address_target = Some(target_address);
+        }
+
+        let latest_address = account.latest_address().unwrap().address();
+        if recipients.contains(latest_address) || remainder_address_target.as_ref() == Some(latest_address) {
+            generated_addresses.push(crate::address::get_new_address(&mut account)?);
+        }
+
+        Ok(TransferPreview {
+            inputs,
+            outputs,
+            remainder_value,
+            remainder_address: remainder_address_target,
+            generated_addresses,
+        })
+    }
+
     /// Send messages.
+    ///
+    /// If the selected inputs exceed `MAX_INPUTS_COUNT`, the transfer's `InputLimitStrategy` defines whether the
+    /// account outputs are consolidated first, the transfer is split into multiple transactions or it fails.
+    /// Send-max transfers are always split, since the consolidated outputs can't be spent until confirmed.
+    ///
+    /// If the transfer has an idempotency key already used by the account, the original result is returned.
     pub async fn transfer(&self, transfer_obj: Transfer) -> crate::Result<TransferMetadata> {
-        // validate the transfer
-        if transfer_obj.amount == 0 {
-            return Err(crate::WalletError::ZeroAmount);
+        // a retry of a transfer still being sent by this process doesn't look at its partial outcome
+        let _idempotency_key_guard = match &transfer_obj.idempotency_key {
+            Some(idempotency_key) => Some(IdempotencyKeyGuard::acquire(&self.account_id, idempotency_key)?),
+            None => None,
+        };
+        let input_addresses = match self.send_transfer(transfer_obj.clone(), true).await? {
+            SendResult::Sent(metadata) => return Ok(metadata),
+            SendResult::InputLimitExceeded(input_addresses) => input_addresses,
+        };
+
+        let input_limit_strategy = match &transfer_obj.input_limit_strategy {
+            InputLimitStrategy::Consolidate if transfer_obj.send_max => InputLimitStrategy::Split,
+            strategy => strategy.clone(),
+        };
+        let transfers = match input_limit_strategy {
+            InputLimitStrategy::Fail => {
+                return Err(crate::WalletError::TooManyInputs {
+                    count: count_outputs(&input_addresses),
+                    max: MAX_INPUTS_COUNT,
+                })
+            }
+            InputLimitStrategy::Split => split_transfer(&transfer_obj, &input_addresses)?,
+            InputLimitStrategy::Consolidate => {
+                let account = crate::storage::get_account(&self.storage_path, self.account_id.clone().into())?;
+                let mut transfers = consolidation_transfers(&account, &input_addresses)?;
+                transfers.push(transfer_obj.input_limit_strategy(InputLimitStrategy::Fail));
+                transfers
+            }
+        };
+
+        // the chain outcome is stored, so its retries don't send it again or mistake a partial chain for a success
+        let idempotency_key = transfer_obj.idempotency_key.clone();
+        if let Some(idempotency_key) = &idempotency_key {
+            self.update_account(|account| account.set_transfer_chain(TransferChain::new(idempotency_key.clone())))?;
+        }
+        let result = self.send_transfer_chain(transfers, &input_limit_strategy).await;
+        if let Some(idempotency_key) = idempotency_key {
+            let mut chain = TransferChain::new(idempotency_key.clone());
+            // ignore errors because a chain left in progress is reported as interrupted to its retries
+            let _ = match &result {
+                Ok(metadata) => {
+                    chain.set_completed(metadata.amount);
+                    self.update_account(|account| account.set_transfer_chain(chain))
+                }
+                Err(crate::WalletError::PartialTransfer { error, .. }) => {
+                    chain.set_failed(error.clone());
+                    self.update_account(|account| account.set_transfer_chain(chain))
+                }
+                // nothing was sent, so the transfer can be sent again
+                Err(_) => self.update_account(|account| account.remove_transfer_chain(&idempotency_key)),
+            };
+        }
+        result
+    }
+
+    /// Loads the stored account, updates it and saves it.
+    fn update_account<F: FnOnce(&mut Account)>(&self, cb: F) -> crate::Result<()> {
+        let mut account = crate::storage::get_account(&self.storage_path, self.account_id.clone().into())?;
+        cb(&mut account);
+        account.save()
+    }
+
+    /// Sends the transactions of a split or consolidated transfer, stopping on the first failure.
+    async fn send_transfer_chain(
+        &self,
+        transfers: Vec<Transfer>,
+        input_limit_strategy: &InputLimitStrategy,
+    ) -> crate::Result<TransferMetadata> {
+        let mut messages: Vec<Message> = vec![];
+        let mut amount = 0;
+        let mut last_metadata = None;
+        let transfers_count = transfers.len();
+        for (index, transfer) in transfers.into_iter().enumerate() {
+            // the consolidated outputs can only be spent once the consolidation is confirmed
+            if input_limit_strategy == &InputLimitStrategy::Consolidate
+                && index == transfers_count - 1
+                && !messages.is_empty()
+            {
+                let message_ids: Vec<MessageId> = messages.iter().map(|message| *message.id()).collect();
+                if let Err(e) = self.wait_for_confirmation(&message_ids).await {
+                    return Err(partial_transfer_error(messages, e));
+                }
+            }
+            match self.send_transfer(transfer, false).await {
+                Ok(SendResult::Sent(metadata)) => {
+                    messages.extend(metadata.messages.iter().cloned());
+                    amount += metadata.amount;
+                    last_metadata = Some(metadata);
+                }
+                Ok(SendResult::InputLimitExceeded(input_addresses)) => {
+                    let error = crate::WalletError::TooManyInputs {
+                        count: count_outputs(&input_addresses),
+                        max: MAX_INPUTS_COUNT,
+                    };
+                    return Err(partial_transfer_error(messages, error));
+                }
+                Err(e) => return Err(partial_transfer_error(messages, e)),
+            }
+        }
+        let metadata = last_metadata.ok_or_else(|| anyhow::anyhow!("the transfer didn't produce any transaction"))?;
+        Ok(TransferMetadata {
+            message: metadata.message,
+            messages,
+            // the consolidation transactions don't send value to the recipients
+            amount: match input_limit_strategy {
+                InputLimitStrategy::Split => amount,
+                _ => metadata.amount,
+            },
+            account: metadata.account,
+        })
+    }
+
+    /// Waits until the given messages are confirmed, syncing the account with the tangle periodically.
+    async fn wait_for_confirmation(&self, message_ids: &[MessageId]) -> crate::Result<()> {
+        let account_id: AccountIdentifier = self.account_id.clone().into();
+        let started_at = Instant::now();
+        loop {
+            let mut account = crate::storage::get_account(&self.storage_path, account_id.clone())?;
+            account.sync().execute().await?;
+            let confirmed = message_ids.iter().all(|message_id| {
+                account
+                    .get_message(message_id)
+                    .map(|message| *message.confirmed())
+                    .unwrap_or(false)
+            });
+            if confirmed {
+                return Ok(());
+            }
+            if started_at.elapsed() >= CONFIRMATION_TIMEOUT {
+                return Err(anyhow::anyhow!(
+                    "the consolidation wasn't confirmed in {} seconds",
+                    CONFIRMATION_TIMEOUT.as_secs()
+                )
+                .into());
+            }
+            tokio::time::delay_for(CONFIRMATION_POLL_INTERVAL).await;
         }
+    }
+
+    /// Selects the inputs of a transfer, stores its outbox entry and signs its transaction.
+    /// The input addresses stay locked until the prepared transfer is posted or dropped.
+    async fn prepare_transfer(
+        &self,
+        mut transfer_obj: Transfer,
+        check_idempotency: bool,
+    ) -> crate::Result<PrepareResult> {
+        validate_transfer(&transfer_obj)?;
 
         let account_id: AccountIdentifier = self.account_id.clone().into();
 
@@ -438,10 +1333,26 @@ impl SyncedAccount {
         let mut locked_addresses = account_addresses_locker.lock().unwrap();
 
         // prepare the transfer getting some needed objects and values
-        let value: u64 = transfer_obj.amount;
         let mut account = crate::storage::get_account(&self.storage_path, account_id.clone())?;
+        // checked while holding the lock so concurrent retries see the outbox entry of the original transfer
+        if let (true, Some(idempotency_key)) = (check_idempotency, &transfer_obj.idempotency_key) {
+            if let Some(metadata) = find_idempotent_transfer(&account, idempotency_key)? {
+                return Ok(PrepareResult::Done(SendResult::Sent(metadata)));
+            }
+        }
+        // rejects recipients of another network and encodes the outputs with the account HRP
+        transfer_obj.use_bech32_hrp(&account.bech32_hrp())?;
+        // resolve the send-max amount while holding the lock so it matches the selected inputs
+        resolve_send_max_amount(&mut transfer_obj, &locked_addresses, &account)?;
+        let value: u64 = transfer_obj.amount();
+        let transfer_outputs = transfer_obj.merged_outputs();
+        let recipients: Vec<IotaAddress> = transfer_outputs.iter().map(|o| o.address().clone()).collect();
         let mut addresses_to_watch = vec![];
 
+        account.check_spending_policy(&transfer_outputs)?;
+        // only the value leaving the account counts against the spending limits
+        let spent_value = account.external_amount(&transfer_outputs);
+
         if value > account.total_balance() {
             return Err(crate::WalletError::InsufficientFunds);
         }
@@ -479,28 +1390,56 @@ impl SyncedAccount {
             }
         }
 
-        let client = crate::client::get_client(account.client_options());
-        let client = client.read().unwrap();
+        validate_remainder_value_strategy(&account, &transfer_obj.remainder_value_strategy)?;
 
-        if let RemainderValueStrategy::AccountAddress(ref remainder_target_address) =
-            transfer_obj.remainder_value_strategy
-        {
-            if !account
-                .addresses()
-                .iter()
-                .any(|addr| addr.address() == remainder_target_address)
-            {
-                return Err(crate::WalletError::InvalidRemainderValueAddress);
-            }
+        // select the input addresses and check if a remainder address is needed
+        let (input_addresses, remainder_address) =
+            self.select_inputs(&locked_addresses, value, &account, &recipients, &transfer_obj)?;
+        if count_outputs(&input_addresses) > MAX_INPUTS_COUNT {
+            return Ok(PrepareResult::Done(SendResult::InputLimitExceeded(input_addresses)));
+        }
+        // the remainder output must also fit in the transaction
+        if remainder_address.is_some() && transfer_outputs.len() + 1 > MAX_OUTPUTS_COUNT {
+            return Err(crate::WalletError::TooManyOutputs {
+                count: transfer_outputs.len() + 1,
+                max: MAX_OUTPUTS_COUNT,
+            });
         }
 
-        // select the input addresses and check if a remainder address is needed
-        let (input_addresses, remainder_address) = self.select_inputs(
-            &mut locked_addresses,
-            transfer_obj.amount,
-            &mut account,
-            &transfer_obj.address,
-        )?;
+        // the change address is derived while holding the lock and persisted with the outbox entry,
+        // so concurrent or later transfers don't derive the same change address
+        let remainder_target_address = match &remainder_address {
+            Some(remainder_address) => {
+                let (target_address, change_address) =
+                    reserve_remainder_target(&mut account, &transfer_obj.remainder_value_strategy, remainder_address)?;
+                addresses_to_watch.extend(change_address);
+                Some(target_address)
+            }
+            None => None,
+        };
+
+        // persist the transfer before signing so it can be recovered if the process stops mid-transfer
+        let outbox_entry = OutboxEntry::new(
+            input_addresses
+                .iter()
+                .flat_map(|a| a.available_outputs())
+                .map(|output| output.id())
+                .collect(),
+            input_addresses.iter().map(|a| a.address().clone()).collect(),
+            spent_value,
+            transfer_obj.idempotency_key.clone(),
+        );
+        let outbox_entry_id = outbox_entry.id().clone();
+        account.add_outbox_entry(outbox_entry);
+        account.save()?;
+        locked_addresses.extend(input_addresses.iter().map(|a| a.address().clone()));
+        let transfer_guard = TransferGuard {
+            storage_path: self.storage_path.clone(),
+            account_id: account_id.clone(),
+            outbox_entry_id: outbox_entry_id.clone(),
+            input_addresses: input_addresses.clone(),
+            posted: false,
+        };
 
         // unlock the transfer process since we already selected the input addresses and locked them
         drop(locked_addresses);
@@ -529,8 +1468,10 @@ impl SyncedAccount {
         }
 
         let mut essence_builder = TransactionEssence::builder();
-        let mut current_output_sum = 0;
-        let mut remainder_value = 0;
+        if let Some(indexation) = transfer_obj.indexation()? {
+            essence_builder = essence_builder.with_payload(Payload::Indexation(Box::new(indexation)));
+        }
+        let mut input_sum = 0;
         for (utxo, address_index, address_path) in utxos {
             let input: Input = UTXOInput::new(*utxo.transaction_id(), *utxo.index())
                 .map_err(|e| anyhow::anyhow!(e.to_string()))?
@@ -541,59 +1482,28 @@ impl SyncedAccount {
                 address_index,
                 address_path,
             });
-            if current_output_sum == value {
-                // already filled the transfer value; just collect the output value as remainder
-                remainder_value += *utxo.amount();
-            } else if current_output_sum + *utxo.amount() > value {
-                // if the used UTXO amount is greater than the transfer value, this is the last iteration and we'll have
-                // remainder value. we add an Output for the missing value and collect the remainder
-                let missing_value = value - current_output_sum;
-                remainder_value += *utxo.amount() - missing_value;
-                essence_builder = essence_builder.add_output(
-                    SignatureLockedSingleOutput::new(
-                        transfer_obj.address.clone(),
-                        NonZeroU64::new(missing_value).ok_or_else(|| anyhow::anyhow!("invalid amount"))?,
-                    )
-                    .into(),
-                );
-                current_output_sum += missing_value;
-            } else {
-                essence_builder = essence_builder.add_output(
-                    SignatureLockedSingleOutput::new(
-                        transfer_obj.address.clone(),
-                        NonZeroU64::new(*utxo.amount()).ok_or_else(|| anyhow::anyhow!("invalid amount"))?,
-                    )
-                    .into(),
-                );
-                current_output_sum += *utxo.amount();
-            }
+            input_sum += *utxo.amount();
         }
 
-        // if there's remainder value, we check the strategy defined in the transfer
+        // one output per recipient address; the value exceeding the transfer amount is collected as remainder
+        for transfer_output in &transfer_outputs {
+            essence_builder = essence_builder.add_output(
+                SignatureLockedSingleOutput::new(
+                    transfer_output.address().clone(),
+                    NonZeroU64::new(*transfer_output.amount()).ok_or_else(|| anyhow::anyhow!("invalid amount"))?,
+                )
+                .into(),
+            );
+        }
+        let remainder_value = input_sum
+            .checked_sub(value)
+            .ok_or(crate::WalletError::InsufficientFunds)?;
+
+        // if there's remainder value, it goes to the target resolved while holding the lock
         let mut remainder_value_deposit_address = None;
         if remainder_value > 0 {
-            let remainder_address =
-                remainder_address.ok_or_else(|| anyhow::anyhow!("remainder address not defined"))?;
-
-            let remainder_target_address = match transfer_obj.remainder_value_strategy {
-                // use one of the account's addresses to send the remainder value
-                RemainderValueStrategy::AccountAddress(target_address) => target_address,
-                // generate a new change address to send the remainder value
-                RemainderValueStrategy::ChangeAddress => {
-                    if *remainder_address.internal() {
-                        let deposit_address = account.latest_address().unwrap().address().clone();
-                        deposit_address
-                    } else {
-                        let change_address = crate::address::get_new_change_address(&account, &remainder_address)?;
-                        let addr = change_address.address().clone();
-                        account.append_addresses(vec![change_address]);
-                        addresses_to_watch.push(addr.clone());
-                        addr
-                    }
-                }
-                // keep the remainder value on the address
-                RemainderValueStrategy::ReuseAddress => remainder_address.address().clone(),
-            };
+            let remainder_target_address =
+                remainder_target_address.ok_or_else(|| anyhow::anyhow!("remainder address not defined"))?;
             remainder_value_deposit_address = Some(remainder_target_address.clone());
             essence_builder = essence_builder.add_output(
                 SignatureLockedSingleOutput::new(
@@ -604,12 +1514,10 @@ impl SyncedAccount {
             );
         }
 
-        let (parent1, parent2) = client.get_tips().await?;
-
+        // on errors, the transfer guard marks the entry as failed so the inputs can be spent again
         let essence = essence_builder
             .finish()
             .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
-
         let unlock_blocks = crate::signing::with_signer(account.signer_type(), |signer| {
             signer.sign_message(&account, &essence, &mut address_index_recorders)
         })?;
@@ -618,37 +1526,105 @@ impl SyncedAccount {
             tx_builder = tx_builder.add_unlock_block(unlock_block);
         }
         let transaction = tx_builder.finish().map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+        // catch input selection or signing issues before anything reaches the network
+        account.validate_transaction(&transaction)?;
 
+        Ok(PrepareResult::Prepared(Box::new(PreparedTransfer {
+            account,
+            transaction,
+            outbox_entry_id,
+            transfer_guard,
+            input_addresses,
+            recipients,
+            remainder_value_deposit_address,
+            addresses_to_watch,
+            value,
+            spent_value,
+            idempotency_key: transfer_obj.idempotency_key,
+        })))
+    }
+
+    async fn send_transfer(&self, transfer_obj: Transfer, check_idempotency: bool) -> crate::Result<SendResult> {
+        let PreparedTransfer {
+            mut account,
+            transaction,
+            outbox_entry_id,
+            mut transfer_guard,
+            input_addresses,
+            recipients,
+            remainder_value_deposit_address,
+            mut addresses_to_watch,
+            value,
+            spent_value,
+            idempotency_key,
+        } = match self.prepare_transfer(transfer_obj, check_idempotency).await? {
+            PrepareResult::Prepared(prepared) => *prepared,
+            PrepareResult::Done(result) => return Ok(result),
+        };
+        let account_id: AccountIdentifier = self.account_id.clone().into();
+
+        // on errors, the transfer guard marks the entry as failed so the inputs can be spent again
+        let network_id = account.resolve_network_id().await?;
+        let client = crate::client::get_client(account.client_options());
+        let client = client.read().unwrap();
+        let (parent1, parent2) = client.get_tips().await?;
         let message = IotaMessage::builder()
             .with_parent1(parent1)
             .with_parent2(parent2)
             .with_payload(Payload::Transaction(Box::new(transaction)))
-            .with_network_id(0)
+            .with_network_id(network_id)
             .finish()
             .map_err(|e| anyhow::anyhow!(e.to_string()))?;
-
-        let message_id = client.post_message(&message).await?;
+        account.update_outbox_entry(&outbox_entry_id, |entry| entry.set_signed(&message));
+        account.save()?;
+
+        // if posting fails but the message reached the node, the next sync finds its outputs spent
+        let message_id = match crate::client::with_message_posting(
+            account.id(),
+            account.client_options(),
+            client.post_message(&message),
+        )
+        .await
+        {
+            Ok(message_id) => message_id,
+            Err(e) => {
+                if let crate::WalletError::PowTimeout(_) = e {
+                    // the message may still reach the node, so the entry stays signed and its inputs stay reserved
+                    // until the outbox reconciliation settles it
+                    transfer_guard.posted = true;
+                }
+                return Err(e);
+            }
+        };
+        transfer_guard.posted = true;
+        account.update_outbox_entry(&outbox_entry_id, |entry| entry.set_posted(message_id));
+        // from now on the outbox reconciliation recovers the transfer if it's interrupted
+        account.save()?;
 
         for input_address in &input_addresses {
+            let spent_outputs: Vec<OutputId> = input_address.available_outputs().iter().map(|o| o.id()).collect();
             // get a mutable reference to the account address
             let account_address = account
                 .addresses_mut()
                 .iter_mut()
                 .find(|a| a == &input_address)
                 .unwrap();
+            // only the spent outputs are pending; frozen or unselected outputs stay available
             for output in account_address.available_outputs_mut().iter_mut() {
-                output.set_pending_on_message_id(Some(message_id));
+                if spent_outputs.contains(&output.id()) {
+                    output.set_pending_on_message_id(Some(message_id));
+                }
             }
         }
 
         // if this is a transfer to the account's latest address or we used the latest as deposit of the remainder
         // value, we generate a new one to keep the latest address unused
         let latest_address = account.latest_address().unwrap().address();
-        if latest_address == &transfer_obj.address
+        if recipients.contains(latest_address)
             || (remainder_value_deposit_address.is_some()
                 && &remainder_value_deposit_address.unwrap() == latest_address)
         {
-            let addr = crate::address::get_new_address(&account)?;
+            let addr = crate::address::get_new_address(&mut account)?;
             addresses_to_watch.push(addr.address().clone());
             account.append_addresses(vec![addr]);
         }
@@ -663,26 +1639,88 @@ impl SyncedAccount {
             let _ = crate::monitor::monitor_address_balance(&account, &address);
         }
 
-        let message = Message::from_iota_message(message_id, account.addresses(), &message)?;
+        let mut message = Message::from_iota_message(message_id, account.addresses(), &message, &account.bech32_hrp())?;
+        message.idempotency_key = idempotency_key;
+        message.transfer_amount = Some(spent_value);
         account.append_messages(vec![message.clone()]);
         crate::storage::with_adapter(&self.storage_path, |storage| {
             storage.set(account_id.clone(), serde_json::to_string(&account)?)
         })?;
 
-        let account_addresses_locker = get_account_addresses_lock(account_id.clone());
-        let mut locked_addresses = account_addresses_locker.lock().unwrap();
-        for input_address in &input_addresses {
-            let index = locked_addresses
-                .iter()
-                .position(|a| a == input_address.address())
-                .unwrap();
-            locked_addresses.remove(index);
-        }
+        drop(transfer_guard);
+
+        // ignore errors because the address book is optional
+        let _ = crate::address_book::mark_used(&self.storage_path, &recipients);
 
         // ignore errors because we fallback to the polling system
         let _ = crate::monitor::monitor_confirmation_state_change(&account, &message_id);
 
-        Ok(TransferMetadata { message, account })
+        Ok(SendResult::Sent(TransferMetadata {
+            message: message.clone(),
+            messages: vec![message],
+            amount: value,
+            account,
+        }))
+    }
+
+    /// Merges the account's available outputs into a single output on the target address,
+    /// in batches of at most `MAX_INPUTS_COUNT` inputs.
+    /// Frozen outputs and addresses are left untouched.
+    ///
+    /// Returns the consolidation messages; it's empty if there was nothing to consolidate.
+    pub async fn consolidate(&self, options: ConsolidationOptions) -> crate::Result<Vec<Message>> {
+        let account = crate::storage::get_account(&self.storage_path, self.account_id.clone().into())?;
+        let target_address = match options.target_address {
+            Some(address) => {
+                if !account.addresses().iter().any(|a| a.address() == &address) {
+                    return Err(anyhow::anyhow!("the consolidation address doesn't belong to the account").into());
+                }
+                address
+            }
+            None => account
+                .latest_address()
+                .ok_or_else(|| anyhow::anyhow!("account address list empty"))?
+                .address()
+                .clone(),
+        };
+
+        let utxos = movable_outputs(&account, &target_address);
+        if utxos.is_empty() || utxos.len() < options.min_outputs {
+            return Ok(vec![]);
+        }
+
+        let transfers = utxos
+            .chunks(MAX_INPUTS_COUNT)
+            .map(|chunk| chunk_transfer(&target_address, chunk))
+            .collect();
+        self.send_transfers(transfers).await
+    }
+
+    /// Moves the entire available balance of the account to the given address,
+    /// in batches of at most `MAX_INPUTS_COUNT` inputs.
+    pub async fn sweep(&self, to_address: IotaAddress) -> crate::Result<Vec<Message>> {
+        let account = crate::storage::get_account(&self.storage_path, self.account_id.clone().into())?;
+        let utxos = movable_outputs(&account, &to_address);
+        if utxos.is_empty() {
+            return Err(crate::WalletError::InsufficientFunds);
+        }
+
+        let transfers = utxos
+            .chunks(MAX_INPUTS_COUNT)
+            .map(|chunk| chunk_transfer(&to_address, chunk))
+            .collect();
+        self.send_transfers(transfers).await
+    }
+
+    async fn send_transfers(&self, transfers: Vec<Transfer>) -> crate::Result<Vec<Message>> {
+        let mut messages = vec![];
+        for transfer in transfers {
+            match self.transfer(transfer).await {
+                Ok(metadata) => messages.extend(metadata.messages),
+                Err(e) => return Err(partial_transfer_error(messages, e)),
+            }
+        }
+        Ok(messages)
     }
 
     /// Retry message.
@@ -753,7 +1791,12 @@ pub(crate) async fn repost_message(
                 RepostAction::Promote => {
                     let metadata = client.get_message().metadata(message_id).await?;
                     if metadata.should_promote.unwrap_or(false) {
-                        client.promote(message_id).await?
+                        crate::client::with_message_posting(
+                            account.id(),
+                            account.client_options(),
+                            client.promote(message_id),
+                        )
+                        .await?
                     } else {
                         return Err(crate::WalletError::ClientError(
                             iota::client::Error::NoNeedPromoteOrReattach(message_id.to_string()),
@@ -763,16 +1806,28 @@ pub(crate) async fn repost_message(
                 RepostAction::Reattach => {
                     let metadata = client.get_message().metadata(message_id).await?;
                     if metadata.should_reattach.unwrap_or(false) {
-                        client.reattach(message_id).await?
+                        crate::client::with_message_posting(
+                            account.id(),
+                            account.client_options(),
+                            client.reattach(message_id),
+                        )
+                        .await?
                     } else {
                         return Err(crate::WalletError::ClientError(
                             iota::client::Error::NoNeedPromoteOrReattach(message_id.to_string()),
                         ));
                     }
                 }
-                RepostAction::Retry => client.retry(message_id).await?,
+                RepostAction::Retry => {
+                    crate::client::with_message_posting(
+                        account.id(),
+                        account.client_options(),
+                        client.retry(message_id),
+                    )
+                    .await?
+                }
             };
-            let message = Message::from_iota_message(id, account.addresses(), &message)?;
+            let message = Message::from_iota_message(id, account.addresses(), &message, &account.bech32_hrp())?;
 
             if message.payload() == message_to_repost.payload() {
                 account.on_reattachment(message_to_repost.id(), message.id());
@@ -791,11 +1846,737 @@ pub(crate) async fn repost_message(
     Ok(message)
 }
 
+/// Reconciles the account's outbox with the node.
+///
+/// Transfers that weren't signed are marked as failed, signed messages are posted again and
+/// the outputs spent by posted messages are marked as pending, with the message stored on the account.
+///
+/// Returns whether the account was updated or not.
+pub(crate) async fn reconcile_outbox(account: &mut Account) -> crate::Result<bool> {
+    let entries: Vec<OutboxEntry> = account
+        .outbox()
+        .iter()
+        .filter(|entry| !entry.is_settled())
+        .cloned()
+        .collect();
+    if entries.is_empty() {
+        return Ok(false);
+    }
+
+    let client = get_client(account.client_options());
+    let client = client.read().unwrap();
+
+    for entry in entries {
+        // a failed entry doesn't stop the others; the error is emitted when dropped
+        let _ = reconcile_outbox_entry(account, &client, &entry).await;
+    }
+
+    Ok(true)
+}
+
+async fn reconcile_outbox_entry(account: &mut Account, client: &Client, entry: &OutboxEntry) -> crate::Result<()> {
+    let message_id = match entry.state() {
+        // the transaction was never signed so it can't be on the tangle
+        OutboxState::Prepared => {
+            account.update_outbox_entry(entry.id(), |entry| entry.set_state(OutboxState::Failed));
+            return Ok(());
+        }
+        OutboxState::Signed => {
+            let message = entry.signed_message()?;
+            // on errors, the entry stays signed and it's posted on the next reconciliation
+            let message_id = crate::client::with_message_posting(
+                account.id(),
+                account.client_options(),
+                client.post_message(&message),
+            )
+            .await?;
+            account.update_outbox_entry(entry.id(), |entry| entry.set_posted(message_id));
+            message_id
+        }
+        _ => match entry.message_id() {
+            Some(message_id) => *message_id,
+            None => return Ok(()),
+        },
+    };
+
+    // restore the output locks
+    for address in account.addresses_mut() {
+        for output in address.available_outputs_mut() {
+            if entry.inputs().contains(&output.id()) {
+                output.set_pending_on_message_id(Some(message_id));
+            }
+        }
+    }
+
+    if account.get_message(&message_id).is_none() {
+        let message = client.get_message().data(&message_id).await?;
+        let mut message = Message::from_iota_message(message_id, account.addresses(), &message, &account.bech32_hrp())?;
+        message.idempotency_key = entry.idempotency_key().clone();
+        message.transfer_amount = Some(*entry.amount());
+        account.append_messages(vec![message]);
+    }
+
+    let metadata = client.get_message().metadata(&message_id).await?;
+    if metadata.ledger_inclusion_state.as_deref() == Some("included") {
+        if let Some(message) = account.messages_mut().iter_mut().find(|m| m.id() == &message_id) {
+            message.set_confirmed(true);
+        }
+        account.on_message_confirmed(&message_id);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::client::ClientOptionsBuilder;
+    use super::{
+        chunk_transfer, consolidation_transfers, movable_outputs, partial_transfer_error, reserve_remainder_target,
+        resolve_send_max_amount, split_transfer, validate_transfer, IdempotencyKeyGuard, InputSelectionStrategy,
+        PrepareResult, SyncedAccount, TransferGuard, MAX_INPUTS_COUNT,
+    };
+    use crate::{
+        account::{get_account_addresses_lock, Account, AccountIdentifier},
+        address::{
+            to_bech32, Address, AddressBuilder, AddressOutput, IotaAddress, OutputId, MAINNET_BECH32_HRP,
+            TESTNET_BECH32_HRP,
+        },
+        client::ClientOptionsBuilder,
+        message::{InputLimitStrategy, Message, RemainderValueStrategy, Transfer, TransferOutput},
+        outbox::{OutboxEntry, OutboxState, TransferChain},
+        signing::{set_signer, SeedSigner, SignerType},
+        test_utils::{account_with_funds, funded_address, iota_message, output, transaction_payload, MNEMONIC},
+    };
+    use chrono::Utc;
+    use iota::message::prelude::{Ed25519Address, MessageId, TransactionId, UnlockBlock};
     use rusty_fork::rusty_fork_test;
 
+    fn address(byte: u8) -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    fn with_outputs(address: &Address, outputs: Vec<AddressOutput>) -> Address {
+        AddressBuilder::new()
+            .address(address.address().clone())
+            .key_index(*address.key_index())
+            .internal(*address.internal())
+            .balance(outputs.iter().map(|output| *output.amount()).sum())
+            .outputs(outputs)
+            .build()
+            .unwrap()
+    }
+
+    /// Creates an account whose public addresses are derived by the mnemonic signer, each holding an output.
+    fn signed_account_with_funds(amounts: &[u64]) -> Account {
+        set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+        let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+            .expect("invalid node URL")
+            .build();
+        let mut account = crate::test_utils::get_account_manager()
+            .create_account(client_options)
+            .signer_type(SignerType::Mnemonic)
+            .mnemonic(MNEMONIC)
+            .initialise()
+            .expect("failed to add account");
+        for (index, amount) in amounts.iter().enumerate() {
+            let address = crate::address::get_new_address(&mut account).unwrap();
+            let outputs = vec![output(index as u8 + 1, 0, *amount, address.address())];
+            account.append_addresses(vec![with_outputs(&address, outputs)]);
+        }
+        account.save().unwrap();
+        account
+    }
+
+    fn synced_account(account: &Account) -> SyncedAccount {
+        SyncedAccount {
+            account_id: account.id().clone(),
+            deposit_address: account.latest_address().unwrap().clone(),
+            is_empty: false,
+            messages: account.messages().clone(),
+            addresses: account.addresses().clone(),
+            storage_path: account.storage_path().clone(),
+        }
+    }
+
+    #[test]
+    fn transfer_validation() {
+        assert!(validate_transfer(&Transfer::with_outputs(vec![])).is_err());
+        assert!(validate_transfer(&Transfer::new(address(1), 1_000_000)).is_ok());
+        assert!(matches!(
+            validate_transfer(&Transfer::new(address(1), 0)),
+            Err(crate::WalletError::ZeroAmount)
+        ));
+        assert!(matches!(
+            validate_transfer(&Transfer::with_outputs(
+                (0..=super::MAX_OUTPUTS_COUNT as u8)
+                    .map(|byte| (address(byte), 1_000_000))
+                    .collect()
+            )),
+            Err(crate::WalletError::TooManyOutputs { .. })
+        ));
+        assert!(matches!(
+            validate_transfer(&Transfer::with_outputs(vec![(address(1), u64::MAX), (address(2), 1)])),
+            Err(crate::WalletError::AmountOverflow(_))
+        ));
+        assert!(matches!(
+            validate_transfer(&Transfer::with_outputs(vec![
+                (address(1), 1_000_000),
+                (address(2), 500)
+            ])),
+            Err(crate::WalletError::InvalidTransaction(
+                crate::validation::TransactionValidationError::DustOutput { index: 1, amount: 500 }
+            ))
+        ));
+        // outputs to the same address are merged before the dust check
+        assert!(validate_transfer(&Transfer::with_outputs(vec![
+            (address(1), 500_000),
+            (address(1), 500_000)
+        ]))
+        .is_ok());
+
+        let send_max_transfer = Transfer::max(address(1));
+        assert!(send_max_transfer.is_send_max());
+        // the amount is only known when the transfer is sent
+        assert!(validate_transfer(&send_max_transfer).is_ok());
+        let mut send_max_transfer = send_max_transfer;
+        send_max_transfer
+            .outputs
+            .push(TransferOutput::new(address(2), 1_000_000));
+        assert!(validate_transfer(&send_max_transfer).is_err());
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn send_max_amount() {
+            let mut account = account_with_funds(vec![
+                funded_address(0, &[2_000_000, 3_000_000]),
+                funded_address(1, &[4_000_000]),
+                funded_address(2, &[1_000_000]),
+            ]);
+            let first_address = account.addresses()[0].address().clone();
+            let second_address = account.addresses()[1].address().clone();
+            let third_address = account.addresses()[2].address().clone();
+
+            let resolve = |transfer: Transfer, locked_addresses: &[IotaAddress], account: &Account| {
+                let mut transfer = transfer;
+                resolve_send_max_amount(&mut transfer, locked_addresses, account).map(|_| transfer.amount())
+            };
+
+            assert_eq!(resolve(Transfer::max(address(1)), &[], &account).unwrap(), 10_000_000);
+            // regular transfers keep their amount
+            assert_eq!(resolve(Transfer::new(address(1), 1_000_000), &[], &account).unwrap(), 1_000_000);
+            // addresses used by transfers in progress are skipped
+            assert_eq!(resolve(Transfer::max(address(1)), &[third_address.clone()], &account).unwrap(), 9_000_000);
+            assert_eq!(
+                resolve(Transfer::max(address(1)).source_addresses(vec![second_address.clone()]), &[], &account).unwrap(),
+                4_000_000
+            );
+            assert_eq!(
+                resolve(
+                    Transfer::max(address(1)).inputs(vec![OutputId::new(TransactionId::new([100; 32]), 1)]),
+                    &[],
+                    &account
+                )
+                .unwrap(),
+                3_000_000
+            );
+            // sending the whole balance to an account address doesn't spend it
+            assert_eq!(resolve(Transfer::max(third_address.clone()), &[], &account).unwrap(), 9_000_000);
+
+            account.freeze_output(OutputId::new(TransactionId::new([100; 32]), 0));
+            account.freeze_address(second_address.clone());
+            assert_eq!(resolve(Transfer::max(address(1)), &[], &account).unwrap(), 4_000_000);
+
+            assert!(matches!(
+                resolve(
+                    Transfer::max(address(1)),
+                    &[first_address, third_address],
+                    &account
+                ),
+                Err(crate::WalletError::InsufficientFunds)
+            ));
+        }
+    }
+
+    #[test]
+    fn split_fragmented_transfer() {
+        let input_addresses = vec![funded_address(0, &[1_000_000; 200])];
+        let transfer = Transfer::new(address(1), 150_000_000).data("invoice 42".to_string());
+
+        let transfers = split_transfer(&transfer, &input_addresses).unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].amount(), 127_000_000);
+        assert_eq!(transfers[0].inputs.len(), MAX_INPUTS_COUNT);
+        assert_eq!(transfers[0].data, Some("invoice 42".to_string()));
+        assert_eq!(transfers[1].amount(), 23_000_000);
+        // the last transaction only spends the inputs it needs
+        assert_eq!(transfers[1].inputs.len(), 23);
+        assert!(transfers[1].data.is_none());
+        for transfer in &transfers {
+            assert_eq!(transfer.input_limit_strategy, InputLimitStrategy::Fail);
+            assert_eq!(transfer.outputs[0].address(), &address(1));
+        }
+    }
+
+    #[test]
+    fn split_transfer_without_dust() {
+        let input_addresses = vec![funded_address(0, &[1_000_000; 200])];
+        let inputs_value = |transfer: &Transfer| transfer.inputs.len() as u64 * 1_000_000;
+
+        // sending everything from the first transaction would leave 0.5 Mi for the second one
+        let transfers = split_transfer(&Transfer::new(address(1), 127_500_000), &input_addresses).unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(
+            transfers.iter().map(|transfer| transfer.amount()).sum::<u64>(),
+            127_500_000
+        );
+        for transfer in &transfers {
+            assert!(transfer.inputs.len() <= MAX_INPUTS_COUNT);
+            assert!(transfer.outputs.iter().all(|output| *output.amount() >= DUST_THRESHOLD));
+            let remainder = inputs_value(transfer) - transfer.amount();
+            assert!(remainder == 0 || remainder >= DUST_THRESHOLD);
+        }
+
+        // several recipients are split on the same rules
+        let transfer = Transfer::with_outputs(vec![(address(1), 126_500_000), (address(2), 10_000_000)]);
+        let transfers = split_transfer(&transfer, &input_addresses).unwrap();
+        assert_eq!(
+            transfers.iter().map(|transfer| transfer.amount()).sum::<u64>(),
+            136_500_000
+        );
+        for transfer in &transfers {
+            assert!(transfer.outputs.iter().all(|output| *output.amount() >= DUST_THRESHOLD));
+            let remainder = inputs_value(transfer) - transfer.amount();
+            assert!(remainder == 0 || remainder >= DUST_THRESHOLD);
+        }
+
+        // the 0.5 Mi left by the inputs can't be avoided, so nothing is planned
+        let input_addresses = vec![funded_address(0, &[1_000_000; 128])];
+        assert!(matches!(
+            split_transfer(&Transfer::new(address(1), 127_500_000), &input_addresses),
+            Err(crate::WalletError::DustRemainder(500_000))
+        ));
+    }
+
+    #[test]
+    fn partial_transfer() {
+        assert!(matches!(
+            partial_transfer_error(vec![], crate::WalletError::InsufficientFunds),
+            crate::WalletError::InsufficientFunds
+        ));
+
+        let message_id = MessageId::new([1; 32]);
+        let message = Message::from_iota_message(
+            message_id,
+            &[],
+            &iota_message(transaction_payload(None)),
+            MAINNET_BECH32_HRP,
+        )
+        .unwrap();
+        let error = partial_transfer_error(vec![message], crate::WalletError::InsufficientFunds);
+        match &error {
+            crate::WalletError::PartialTransfer { messages, error } => {
+                assert_eq!(messages.len(), 1);
+                assert_eq!(messages[0].id(), &message_id);
+                assert_eq!(error, "insufficient funds");
+            }
+            _ => panic!("unexpected error {:?}", error),
+        }
+        let serialized = serde_json::to_string(&error).unwrap();
+        assert!(serialized.contains("PartialTransfer"));
+        assert!(serialized.contains(&message_id.to_string()));
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn consolidate_before_transfer() {
+            let account = account_with_funds(vec![
+                funded_address(0, &[1_000_000; 200]),
+                funded_address(1, &[1_000_000]),
+            ]);
+            let target_address = account.latest_address().unwrap().address().clone();
+
+            let transfers = consolidation_transfers(&account, account.addresses()).unwrap();
+            // after a single consolidation, the 73 remaining outputs and the target's outputs fit in a transaction
+            assert_eq!(transfers.len(), 1);
+            assert_eq!(transfers[0].inputs.len(), MAX_INPUTS_COUNT);
+            assert_eq!(transfers[0].amount(), 127_000_000);
+            assert_eq!(transfers[0].outputs[0].address(), &target_address);
+            assert_eq!(transfers[0].input_limit_strategy, InputLimitStrategy::Fail);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn retry_idempotent_transfer() {
+            let mut account = account_with_funds(vec![funded_address(0, &[5_000_000])]);
+            let transfer = Transfer::new(address(1), 2_000_000).idempotency_key("invoice-42".to_string());
+
+            // the first attempt is still being sent
+            let entry = OutboxEntry::new(
+                vec![account.addresses()[0].outputs()[0].id()],
+                vec![account.addresses()[0].address().clone()],
+                2_000_000,
+                Some("invoice-42".to_string()),
+            );
+            let entry_id = entry.id().clone();
+            account.add_outbox_entry(entry);
+            account.save().unwrap();
+            let synced = synced_account(&account);
+            let response = crate::block_on(synced.transfer(transfer.clone()));
+            assert!(matches!(response, Err(crate::WalletError::TransferInProgress(ref key)) if key == "invoice-42"));
+
+            // the first attempt was posted
+            let mut message = Message::from_iota_message(
+                MessageId::new([1; 32]),
+                &[],
+                &iota_message(transaction_payload(None)),
+                MAINNET_BECH32_HRP,
+            )
+            .unwrap();
+            message.idempotency_key = Some("invoice-42".to_string());
+            message.transfer_amount = Some(2_000_000);
+            account.update_outbox_entry(&entry_id, |entry| entry.set_posted(*message.id()));
+            account.append_messages(vec![message.clone()]);
+            account.save().unwrap();
+            for _ in 0..2 {
+                let metadata = crate::block_on(synced.transfer(transfer.clone())).unwrap();
+                assert_eq!(metadata.message.id(), message.id());
+                assert_eq!(metadata.messages.len(), 1);
+                assert_eq!(metadata.amount, 2_000_000);
+            }
+            let stored_account =
+                crate::storage::get_account(account.storage_path(), account.id().clone().into()).unwrap();
+            assert_eq!(stored_account.messages().len(), 1);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn retry_transfer_chain() {
+            let mut account = account_with_funds(vec![funded_address(0, &[5_000_000])]);
+            let transfer = Transfer::new(address(1), 2_000_000).idempotency_key("invoice-43".to_string());
+            let synced = synced_account(&account);
+
+            // the process stopped after sending the first transaction of the chain
+            let mut message = Message::from_iota_message(
+                MessageId::new([1; 32]),
+                &[],
+                &iota_message(transaction_payload(None)),
+                MAINNET_BECH32_HRP,
+            )
+            .unwrap();
+            message.idempotency_key = Some("invoice-43".to_string());
+            account.append_messages(vec![message.clone()]);
+            account.set_transfer_chain(TransferChain::new("invoice-43".to_string()));
+            account.save().unwrap();
+            match crate::block_on(synced.transfer(transfer.clone())) {
+                Err(crate::WalletError::PartialTransfer { ref messages, .. }) => {
+                    assert_eq!(messages.len(), 1);
+                    assert_eq!(messages[0].id(), message.id());
+                }
+                _ => panic!("expected a partial transfer"),
+            }
+
+            // the second transaction failed
+            let mut chain = TransferChain::new("invoice-43".to_string());
+            chain.set_failed("insufficient funds".to_string());
+            account.set_transfer_chain(chain);
+            account.save().unwrap();
+            for _ in 0..2 {
+                match crate::block_on(synced.transfer(transfer.clone())) {
+                    Err(crate::WalletError::PartialTransfer { ref messages, ref error }) => {
+                        assert_eq!(messages.len(), 1);
+                        assert_eq!(error, "insufficient funds");
+                    }
+                    _ => panic!("expected a partial transfer"),
+                }
+            }
+
+            // the chain was completed
+            let mut chain = TransferChain::new("invoice-43".to_string());
+            chain.set_completed(2_000_000);
+            account.set_transfer_chain(chain);
+            account.save().unwrap();
+            let metadata = crate::block_on(synced.transfer(transfer.clone())).unwrap();
+            assert_eq!(metadata.message.id(), message.id());
+            assert_eq!(metadata.amount, 2_000_000);
+
+            // a retry doesn't look at the outcome while this process is still sending the transfer
+            let _guard = IdempotencyKeyGuard::acquire(account.id(), "invoice-43").unwrap();
+            let response = crate::block_on(synced.transfer(transfer));
+            assert!(matches!(response, Err(crate::WalletError::TransferInProgress(ref key)) if key == "invoice-43"));
+
+            let stored_account =
+                crate::storage::get_account(account.storage_path(), account.id().clone().into()).unwrap();
+            assert_eq!(stored_account.messages().len(), 1);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn transfer_guard() {
+            let mut account = account_with_funds(vec![funded_address(0, &[1_000_000])]);
+            let account_id: AccountIdentifier = account.id().clone().into();
+            let input_addresses = account.addresses().clone();
+
+            let mut entry_ids = vec![];
+            for _ in 0..2 {
+                let entry = OutboxEntry::new(
+                    vec![input_addresses[0].outputs()[0].id()],
+                    vec![input_addresses[0].address().clone()],
+                    1_000_000,
+                    None,
+                );
+                entry_ids.push(entry.id().clone());
+                account.add_outbox_entry(entry);
+            }
+            account.save().unwrap();
+
+            let outbox_entry_state = |entry_id: &str| {
+                let account = crate::storage::get_account(account.storage_path(), account_id.clone()).unwrap();
+                *account.outbox().iter().find(|entry| entry.id() == entry_id).unwrap().state()
+            };
+            for (entry_id, posted) in entry_ids.iter().zip(&[false, true]) {
+                get_account_addresses_lock(account_id.clone())
+                    .lock()
+                    .unwrap()
+                    .push(input_addresses[0].address().clone());
+                let guard = TransferGuard {
+                    storage_path: account.storage_path().clone(),
+                    account_id: account_id.clone(),
+                    outbox_entry_id: entry_id.clone(),
+                    input_addresses: input_addresses.clone(),
+                    posted: *posted,
+                };
+                drop(guard);
+                assert!(get_account_addresses_lock(account_id.clone()).lock().unwrap().is_empty());
+            }
+            // the posted transfers are recovered by the outbox reconciliation instead
+            assert_eq!(outbox_entry_state(&entry_ids[0]), OutboxState::Failed);
+            assert_eq!(outbox_entry_state(&entry_ids[1]), OutboxState::Prepared);
+
+            // the inputs of the transfer in progress can't be spent until the reconciliation settles it
+            let account = crate::storage::get_account(account.storage_path(), account_id).unwrap();
+            assert!(matches!(
+                synced_account(&account).preview_transfer(&Transfer::new(address(1), 1_000_000)),
+                Err(crate::WalletError::InsufficientFunds)
+            ));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn sweep_outputs() {
+            let mut account = account_with_funds(vec![
+                funded_address(0, &[1_000_000, 2_000_000]),
+                funded_address(1, &[3_000_000]),
+                funded_address(2, &[4_000_000]),
+            ]);
+            let outputs_value = |account: &Account, target_address: &IotaAddress| {
+                movable_outputs(account, target_address)
+                    .iter()
+                    .fold(0, |acc, output| acc + *output.amount())
+            };
+
+            assert_eq!(outputs_value(&account, &address(1)), 10_000_000);
+            // the target address outputs are left untouched
+            let latest_address = account.latest_address().unwrap().address().clone();
+            assert_eq!(outputs_value(&account, &latest_address), 6_000_000);
+
+            account.freeze_output(OutputId::new(TransactionId::new([100; 32]), 1));
+            account.freeze_address(account.addresses()[1].address().clone());
+            assert_eq!(outputs_value(&account, &address(1)), 5_000_000);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn preview_transfer() {
+            let account = account_with_funds(vec![funded_address(0, &[5_000_000])]);
+            let synced = synced_account(&account);
+            let funded_address = account.addresses()[0].address().clone();
+
+            let preview = synced.preview_transfer(&Transfer::new(address(1), 2_000_000)).unwrap();
+            assert_eq!(preview.inputs().len(), 1);
+            assert_eq!(preview.inputs()[0].0, funded_address);
+            assert_eq!(*preview.remainder_value(), 3_000_000);
+            // the remainder goes to a new change address
+            assert_eq!(preview.generated_addresses().len(), 1);
+            assert!(*preview.generated_addresses()[0].internal());
+            assert_eq!(preview.remainder_address().as_ref(), Some(preview.generated_addresses()[0].address()));
+
+            let preview = synced
+                .preview_transfer(
+                    &Transfer::new(address(1), 2_000_000).remainder_value_strategy(RemainderValueStrategy::ReuseAddress),
+                )
+                .unwrap();
+            // the remainder stays on the deposit address, so a new public address is generated
+            assert_eq!(preview.remainder_address(), &Some(funded_address));
+            assert_eq!(preview.generated_addresses().len(), 1);
+            assert!(!preview.generated_addresses()[0].internal());
+
+            let preview = synced.preview_transfer(&Transfer::max(address(1))).unwrap();
+            assert_eq!(preview.outputs()[0].amount(), &5_000_000);
+            assert_eq!(*preview.remainder_value(), 0);
+            assert!(preview.remainder_address().is_none());
+
+            assert!(matches!(
+                synced.preview_transfer(&Transfer::new(address(1), 6_000_000)),
+                Err(crate::WalletError::InsufficientFunds)
+            ));
+
+            // recipients given as bech32 must belong to the account network
+            let testnet_transfer: Transfer = serde_json::from_value(serde_json::json!({
+                "address": to_bech32(&address(1), TESTNET_BECH32_HRP).unwrap(),
+                "amount": 1_000_000,
+                "remainder_value_strategy": { "strategy": "ChangeAddress" },
+            }))
+            .unwrap();
+            assert!(matches!(
+                synced.preview_transfer(&testnet_transfer),
+                Err(crate::WalletError::Bech32HrpMismatch { .. })
+            ));
+            let preview = synced.preview_transfer(&Transfer::new(address(1), 1_000_000)).unwrap();
+            assert_eq!(preview.outputs()[0].bech32_hrp(), &Some(account.bech32_hrp()));
+
+            // nothing is locked or persisted
+            let account_id: AccountIdentifier = account.id().clone().into();
+            assert!(get_account_addresses_lock(account_id.clone()).lock().unwrap().is_empty());
+            let stored_account = crate::storage::get_account(account.storage_path(), account_id).unwrap();
+            assert_eq!(stored_account.addresses().len(), 1);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn change_addresses_are_not_reused() {
+            let mut account = account_with_funds(vec![funded_address(0, &[5_000_000])]);
+            let remainder_address = account.addresses()[0].clone();
+
+            // repeated sends from the same address move forward on the change chain
+            let mut change_addresses = vec![];
+            for expected_index in 0..3 {
+                let (target_address, change_address) =
+                    reserve_remainder_target(&mut account, &RemainderValueStrategy::ChangeAddress, &remainder_address)
+                        .unwrap();
+                assert_eq!(change_address.as_ref(), Some(&target_address));
+                assert!(!change_addresses.contains(&target_address));
+                let stored_address = account.addresses().iter().find(|a| a.address() == &target_address).unwrap();
+                assert!(*stored_address.internal());
+                assert_eq!(*stored_address.key_index(), expected_index);
+                change_addresses.push(target_address);
+            }
+            assert_eq!(*account.next_change_index(), 3);
+
+            // keeping the remainder on the address doesn't use the change chain
+            let (target_address, change_address) =
+                reserve_remainder_target(&mut account, &RemainderValueStrategy::ReuseAddress, &remainder_address)
+                    .unwrap();
+            assert_eq!(&target_address, remainder_address.address());
+            assert!(change_address.is_none());
+            assert_eq!(*account.next_change_index(), 3);
+
+            // the change chain position is persisted with the account
+            account.save().unwrap();
+            let account_id: AccountIdentifier = account.id().clone().into();
+            let stored_account = crate::storage::get_account(account.storage_path(), account_id).unwrap();
+            assert_eq!(*stored_account.next_change_index(), 3);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn spend_change_and_deposit_with_the_same_index() {
+            let account = signed_account_with_funds(&[5_000_000]);
+            let deposit_address = account.addresses()[0].clone();
+            let account_id: AccountIdentifier = account.id().clone().into();
+
+            // the first send leaves its remainder on the change address #0
+            let transfer = Transfer::new(address(1), 2_000_000);
+            let prepared = match crate::block_on(synced_account(&account).prepare_transfer(transfer, false)).unwrap() {
+                PrepareResult::Prepared(prepared) => prepared,
+                PrepareResult::Done(_) => panic!("expected a signed transaction"),
+            };
+            let change_address = prepared.account.addresses().iter().find(|a| *a.internal()).unwrap().clone();
+            assert_eq!(*change_address.key_index(), *deposit_address.key_index());
+            assert_eq!(prepared.transaction.essence().inputs().len(), 1);
+            drop(prepared);
+
+            // once it's confirmed, the deposit address #0 receives new funds
+            let mut account = crate::storage::get_account(account.storage_path(), account_id).unwrap();
+            account.set_addresses(vec![
+                with_outputs(&deposit_address, vec![output(2, 0, 2_000_000, deposit_address.address())]),
+                with_outputs(&change_address, vec![output(1, 1, 3_000_000, change_address.address())]),
+            ]);
+            account.save().unwrap();
+
+            // the second send spends both addresses #0, so each input needs its own signature
+            let transfer = Transfer::new(address(1), 5_000_000);
+            let prepared = match crate::block_on(synced_account(&account).prepare_transfer(transfer, false)).unwrap() {
+                PrepareResult::Prepared(prepared) => prepared,
+                PrepareResult::Done(_) => panic!("expected a signed transaction"),
+            };
+            assert_eq!(prepared.transaction.essence().inputs().len(), 2);
+            assert!(prepared
+                .transaction
+                .unlock_blocks()
+                .iter()
+                .all(|unlock_block| matches!(unlock_block, UnlockBlock::Signature(_))));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn consolidation_doesnt_spend() {
+            let account = signed_account_with_funds(&[3_000_000, 2_000_000]);
+            let account_id: AccountIdentifier = account.id().clone().into();
+            let since = Utc::now() - chrono::Duration::days(1);
+            let target_address = account.latest_address().unwrap().address().clone();
+
+            let transfer = chunk_transfer(&target_address, &movable_outputs(&account, &target_address));
+            let prepared = match crate::block_on(synced_account(&account).prepare_transfer(transfer, false)).unwrap() {
+                PrepareResult::Prepared(prepared) => prepared,
+                PrepareResult::Done(_) => panic!("expected a signed transaction"),
+            };
+            assert_eq!(prepared.value, 3_000_000);
+            assert_eq!(prepared.spent_value, 0);
+
+            // the consolidation in progress isn't counted against the spending limits
+            let stored_account = crate::storage::get_account(account.storage_path(), account_id).unwrap();
+            assert!(stored_account.outbox().iter().any(|entry| entry.is_in_progress()));
+            assert_eq!(stored_account.spent_since(since), 0);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn dust_remainder() {
+            let account =
+                account_with_funds(vec![funded_address(0, &[3_000_000]), funded_address(1, &[2_000_000])]);
+            let synced = synced_account(&account);
+
+            // spending the largest address alone would leave a dust remainder, so both addresses are spent
+            let preview = synced
+                .preview_transfer(
+                    &Transfer::new(address(1), 2_500_000).input_selection_strategy(InputSelectionStrategy::LargestFirst),
+                )
+                .unwrap();
+            assert_eq!(preview.inputs().len(), 2);
+            assert_eq!(*preview.remainder_value(), 2_500_000);
+
+            assert!(matches!(
+                synced.preview_transfer(&Transfer::new(address(1), 4_500_000)),
+                Err(crate::WalletError::DustRemainder(500_000))
+            ));
+            // explicit inputs can't be extended
+            let input = account.addresses()[0].outputs()[0].id();
+            assert!(matches!(
+                synced.preview_transfer(&Transfer::new(address(1), 2_500_000).inputs(vec![input])),
+                Err(crate::WalletError::DustRemainder(500_000))
+            ));
+        }
+    }
+
     rusty_fork_test! {
         #[test]
         fn account_sync() {
@@ -3,13 +3,20 @@
 
 use crate::{
     account::{
-        account_id_to_stronghold_record_id, repost_message, Account, AccountIdentifier, AccountInitialiser,
-        RepostAction, SyncedAccount,
+        account_id_to_stronghold_record_id, movable_outputs, reconcile_outbox, repost_message, Account,
+        AccountIdentifier, AccountInitialiser, ConsolidationOptions, RepostAction, SyncedAccount,
     },
+    address::IotaAddress,
+    address_book::Contact,
     client::ClientOptions,
-    event::{emit_balance_change, emit_confirmation_state_change, emit_transaction_event, TransactionEventType},
+    event::{
+        emit_balance_change, emit_confirmation_state_change, emit_scheduled_transfer_event, emit_transaction_event,
+        ScheduledTransferEventType, TransactionEventType,
+    },
     message::{Message, MessageType, Transfer},
-    signing::SignerType,
+    schedule::InsufficientFundsPolicy,
+    signing::{with_signer, Mnemonic, SignerType},
+    spending_policy::SpendingPolicy,
     storage::StorageAdapter,
 };
 
@@ -41,9 +48,22 @@ pub struct AccountManager {
     /// the polling interval.
     #[getset(get = "pub", set = "pub")]
     polling_interval: Duration,
+    /// the number of available outputs that triggers the automatic consolidation on the polling system.
+    /// `None` disables the automatic consolidation.
+    #[getset(get = "pub", set = "pub")]
+    consolidation_threshold: Option<usize>,
     started_monitoring: bool,
 }
 
+/// The source of the mnemonic imported into the target signer when migrating an account between signers.
+pub enum MnemonicSource {
+    /// The account's BIP39 mnemonic.
+    Mnemonic(Mnemonic),
+    /// Reads the mnemonic from the account's current Stronghold record.
+    #[cfg(feature = "stronghold")]
+    Stronghold,
+}
+
 /// Internal transfer response metadata.
 pub struct InternalTransferMetadata {
     /// Transfer message.
@@ -75,6 +95,7 @@ impl AccountManager {
         let instance = Self {
             storage_path: storage_path.as_ref().to_path_buf(),
             polling_interval: Duration::from_millis(30_000),
+            consolidation_threshold: None,
             started_monitoring: false,
         };
         Ok(instance)
@@ -102,6 +123,8 @@ impl AccountManager {
         )?;
         crate::init_stronghold(&self.storage_path, stronghold);
         if !self.started_monitoring {
+            // a failure is emitted as an error event and the migrations are completed on the next unlock
+            let _ = self.complete_signer_migrations();
             let monitoring_disabled = self.start_monitoring().is_err();
             self.start_polling(monitoring_disabled);
             self.started_monitoring = true;
@@ -113,13 +136,20 @@ impl AccountManager {
     fn start_polling(&self, is_monitoring_disabled: bool) -> thread::JoinHandle<()> {
         let storage_path = self.storage_path.clone();
         let interval = self.polling_interval;
+        let consolidation_threshold = self.consolidation_threshold;
         thread::spawn(move || {
+            let storage_path_ = storage_path.clone();
+            crate::block_on(async move {
+                // the error is emitted as an event when it's dropped
+                let _ = reconcile_outboxes(&storage_path_).await;
+            });
             loop {
                 let storage_path_ = storage_path.clone();
                 crate::block_on(async move {
-                    if let Err(panic) = AssertUnwindSafe(poll(storage_path_, is_monitoring_disabled))
-                        .catch_unwind()
-                        .await
+                    if let Err(panic) =
+                        AssertUnwindSafe(poll(storage_path_, is_monitoring_disabled, consolidation_threshold))
+                            .catch_unwind()
+                            .await
                     {
                         let msg = if let Some(message) = panic.downcast_ref::<String>() {
                             format!("Internal error: {}", message)
@@ -153,6 +183,40 @@ impl AccountManager {
         Ok(())
     }
 
+    /// Adds a contact to the address book.
+    /// The contact name and address must not be used by another contact.
+    pub fn add_contact(&self, contact: Contact) -> crate::Result<()> {
+        crate::address_book::update(&self.storage_path, |address_book| address_book.add(contact))
+    }
+
+    /// Gets the address book contact with the given name.
+    pub fn get_contact(&self, name: &str) -> crate::Result<Contact> {
+        let address_book = crate::address_book::load(&self.storage_path)?;
+        Ok(address_book.get(name)?.clone())
+    }
+
+    /// Gets the address book contacts.
+    pub fn get_contacts(&self) -> crate::Result<Vec<Contact>> {
+        let address_book = crate::address_book::load(&self.storage_path)?;
+        Ok(address_book.contacts().clone())
+    }
+
+    /// Replaces the address book contact with the given name.
+    pub fn update_contact(&self, name: &str, contact: Contact) -> crate::Result<()> {
+        crate::address_book::update(&self.storage_path, |address_book| address_book.update(name, contact))
+    }
+
+    /// Removes the address book contact with the given name.
+    pub fn remove_contact(&self, name: &str) -> crate::Result<Contact> {
+        crate::address_book::update(&self.storage_path, |address_book| address_book.remove(name))
+    }
+
+    /// Reconciles the accounts' outbox with the node, recovering the transfers interrupted by a process stop.
+    /// This is automatically performed when the polling system starts.
+    pub async fn reconcile_outbox(&self) -> crate::Result<()> {
+        reconcile_outboxes(&self.storage_path).await
+    }
+
     /// Syncs all accounts.
     pub async fn sync_accounts(&self) -> crate::Result<Vec<SyncedAccount>> {
         sync_accounts(&self.storage_path, None).await
@@ -164,6 +228,31 @@ impl AccountManager {
         from_account_id: AccountIdentifier,
         to_account_id: AccountIdentifier,
         amount: u64,
+    ) -> crate::Result<InternalTransferMetadata> {
+        self.send_internal_transfer(from_account_id, to_account_id, amount, None)
+            .await
+    }
+
+    /// Transfers an amount from an account to another, identified by an idempotency key.
+    ///
+    /// If the source account already sent a transfer with the given idempotency key, the original message is returned.
+    pub async fn internal_transfer_with_idempotency_key(
+        &self,
+        from_account_id: AccountIdentifier,
+        to_account_id: AccountIdentifier,
+        amount: u64,
+        idempotency_key: impl Into<String>,
+    ) -> crate::Result<InternalTransferMetadata> {
+        self.send_internal_transfer(from_account_id, to_account_id, amount, Some(idempotency_key.into()))
+            .await
+    }
+
+    async fn send_internal_transfer(
+        &self,
+        from_account_id: AccountIdentifier,
+        to_account_id: AccountIdentifier,
+        amount: u64,
+        idempotency_key: Option<String>,
     ) -> crate::Result<InternalTransferMetadata> {
         let mut from_account = self.get_account(from_account_id)?;
         let to_account = self.get_account(to_account_id)?;
@@ -172,9 +261,11 @@ impl AccountManager {
             .ok_or_else(|| anyhow::anyhow!("destination account address list empty"))?
             .clone();
         let from_synchronized = from_account.sync().execute().await?;
-        let metadata = from_synchronized
-            .transfer(Transfer::new(to_address.address().clone(), amount))
-            .await?;
+        let mut transfer = Transfer::new(to_address.address().clone(), amount);
+        if let Some(idempotency_key) = idempotency_key {
+            transfer = transfer.idempotency_key(idempotency_key);
+        }
+        let metadata = from_synchronized.transfer(transfer).await?;
         Ok(InternalTransferMetadata {
             to_account,
             from_account: metadata.account,
@@ -182,6 +273,107 @@ impl AccountManager {
         })
     }
 
+    /// Migrates the account to another signer type.
+    ///
+    /// The mnemonic is imported into the target signer and every stored address is re-derived with it; an account
+    /// without addresses is checked against the first public address of its current signer.
+    /// The account record is only replaced if all addresses match, otherwise the stored account is left untouched.
+    /// The migrated account is stored before the previous record and its signer secrets are removed; if the process
+    /// stops in between or the previous record can't be removed, the migration is completed by
+    /// [complete_signer_migrations](#method.complete_signer_migrations).
+    ///
+    /// This is also how the accounts created by the former environment mnemonic signer are carried over, see the
+    /// [SeedSigner](../signing/struct.SeedSigner.html) documentation.
+    pub fn migrate_signer(
+        &self,
+        account_id: AccountIdentifier,
+        target_signer: SignerType,
+        mnemonic_source: MnemonicSource,
+    ) -> crate::Result<Account> {
+        let account = self.get_account(account_id)?;
+        if account.signer_type() == &target_signer {
+            return Ok(account);
+        }
+
+        let mnemonic = match mnemonic_source {
+            MnemonicSource::Mnemonic(mnemonic) => mnemonic,
+            #[cfg(feature = "stronghold")]
+            MnemonicSource::Stronghold => {
+                if account.signer_type() != &SignerType::Stronghold {
+                    return Err(anyhow::anyhow!("the account isn't stored on Stronghold").into());
+                }
+                let record_id = account_id_to_stronghold_record_id(account.id())?;
+                crate::with_stronghold_from_path(&self.storage_path, |stronghold| {
+                    stronghold
+                        .account_get_by_id(&record_id)
+                        .map(|stronghold_account| Mnemonic::new(stronghold_account.mnemonic().to_string()))
+                })?
+            }
+        };
+
+        let mut migrated_account = account.clone();
+        migrated_account.set_signer_type(target_signer.clone());
+        migrated_account.address_cache_mut().clear();
+        let id = with_signer(&target_signer, |signer| {
+            signer.init_account(&migrated_account, Some(mnemonic))
+        })?;
+        migrated_account.set_id(id);
+
+        let mut expected_addresses: Vec<(usize, bool, IotaAddress)> = account
+            .addresses()
+            .iter()
+            .map(|address| (*address.key_index(), *address.internal(), address.address().clone()))
+            .collect();
+        if expected_addresses.is_empty() {
+            let address = with_signer(account.signer_type(), |signer| {
+                signer.generate_address(&account, 0, false)
+            })?;
+            expected_addresses.push((0, false, address));
+        }
+        for (key_index, internal, address) in expected_addresses {
+            let derived_address = with_signer(&target_signer, |signer| {
+                signer.generate_address(&migrated_account, key_index, internal)
+            });
+            if derived_address.as_ref().ok() != Some(&address) {
+                // the secrets imported into the target signer aren't used by any account
+                let _ = with_signer(&target_signer, |signer| signer.remove_account(&migrated_account));
+                derived_address?;
+                return Err(crate::WalletError::SignerMigrationAddressMismatch {
+                    index: key_index,
+                    internal,
+                });
+            }
+        }
+
+        if account.id() == migrated_account.id() {
+            migrated_account.save()?;
+            // the account is already migrated; a failure is emitted as an error event
+            let _ = with_signer(account.signer_type(), |signer| signer.remove_account(&account));
+        } else {
+            // the previous record is removed by `complete_signer_migration`, even if the process stops right now
+            migrated_account.set_migrated_from(Some(account.id().clone()));
+            migrated_account.save()?;
+            // the account is already migrated; the marker is kept if the previous record can't be removed yet
+            let _ = complete_signer_migration(&self.storage_path, &mut migrated_account);
+        }
+
+        Ok(migrated_account)
+    }
+
+    /// Completes the signer migrations interrupted by a process stop, removing the previous account records.
+    ///
+    /// Called when the Stronghold password is set; removing the previous signer secrets is best-effort and a failure is
+    /// emitted as an error event.
+    pub fn complete_signer_migrations(&self) -> crate::Result<()> {
+        let accounts = crate::storage::with_adapter(&self.storage_path, |storage| storage.get_all())?;
+        for mut account in crate::storage::parse_accounts(&self.storage_path, &accounts)? {
+            if account.migrated_from().is_some() {
+                complete_signer_migration(&self.storage_path, &mut account)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Backups the accounts to the given destination
     pub fn backup<P: AsRef<Path>>(&self, destination: P) -> crate::Result<PathBuf> {
         let storage_path = &self.storage_path;
@@ -260,6 +452,26 @@ impl AccountManager {
         Ok(account)
     }
 
+    /// Replaces the spending policy of the account associated with the given identifier.
+    /// The stronghold password is required so a caller holding the account can't lift its own limits.
+    pub fn set_spending_policy<P: AsRef<str>>(
+        &self,
+        account_id: AccountIdentifier,
+        policy: SpendingPolicy,
+        password: P,
+    ) -> crate::Result<()> {
+        let stronghold_path = self.storage_path.join(crate::storage::stronghold_snapshot_filename());
+        // without a snapshot there's nothing to check the password against
+        if !stronghold_path.exists() {
+            return Err(crate::WalletError::StrongholdSnapshotNotFound);
+        }
+        Stronghold::new(&stronghold_path, false, password.as_ref().to_string(), None)
+            .map_err(|_| crate::WalletError::InvalidStrongholdPassword)?;
+        let mut account = self.get_account(account_id)?;
+        account.set_spending_policy(policy);
+        account.save()
+    }
+
     /// Gets the account associated with the given alias (case insensitive).
     pub fn get_account_by_alias<S: Into<String>>(&self, alias: S) -> Option<Account> {
         let alias = alias.into().to_lowercase();
@@ -296,7 +508,7 @@ impl AccountManager {
     }
 }
 
-async fn poll(storage_path: PathBuf, syncing: bool) -> crate::Result<()> {
+async fn poll(storage_path: PathBuf, syncing: bool, consolidation_threshold: Option<usize>) -> crate::Result<()> {
     let retried = if syncing {
         let accounts_before_sync = crate::storage::with_adapter(&storage_path, |storage| storage.get_all())?;
         let accounts_before_sync = crate::storage::parse_accounts(&storage_path, &accounts_before_sync)?;
@@ -404,6 +616,131 @@ async fn poll(storage_path: PathBuf, syncing: bool) -> crate::Result<()> {
             );
         });
     });
+
+    execute_scheduled_transfers(&storage_path).await?;
+
+    if let Some(threshold) = consolidation_threshold {
+        consolidate_outputs(&storage_path, threshold).await?;
+    }
+
+    Ok(())
+}
+
+/// Sends the scheduled transfers that are due.
+/// An account failing to send them doesn't stop the others; its error is emitted when dropped.
+async fn execute_scheduled_transfers(storage_path: &PathBuf) -> crate::Result<()> {
+    let now = chrono::Utc::now();
+    let accounts = crate::storage::with_adapter(&storage_path, |storage| storage.get_all())?;
+    for mut account in crate::storage::parse_accounts(&storage_path, &accounts)? {
+        let _ = execute_account_scheduled_transfers(storage_path, &mut account, now).await;
+    }
+    Ok(())
+}
+
+async fn execute_account_scheduled_transfers(
+    storage_path: &PathBuf,
+    account: &mut Account,
+    now: chrono::DateTime<chrono::Utc>,
+) -> crate::Result<()> {
+    let due_transfers: Vec<_> = account
+        .scheduled_transfers()
+        .iter()
+        .filter(|scheduled_transfer| scheduled_transfer.is_due(now))
+        .cloned()
+        .collect();
+    if due_transfers.is_empty() {
+        return Ok(());
+    }
+
+    let account_id: AccountIdentifier = account.id().into();
+    let synced = account.sync().execute().await?;
+    for scheduled_transfer in due_transfers {
+        let result = synced.transfer(scheduled_transfer.next_transfer()).await;
+
+        // the transfer updates the stored account, so we read it again
+        let mut account = crate::storage::get_account(&storage_path, account_id.clone())?;
+        let (event_type, message, error, skipped, scheduled_transfer) = {
+            let stored = match account
+                .scheduled_transfers_mut()
+                .iter_mut()
+                .find(|s| s.id() == scheduled_transfer.id())
+            {
+                Some(stored) => stored,
+                // cancelled while the transfer was being sent
+                None => continue,
+            };
+            let (event_type, message, error, skipped) = match &result {
+                Ok(metadata) => {
+                    stored.on_run(now);
+                    (
+                        ScheduledTransferEventType::Executed,
+                        Some(metadata.message.clone()),
+                        None,
+                        false,
+                    )
+                }
+                Err(e) => {
+                    let skipped = matches!(e, crate::WalletError::InsufficientFunds)
+                        && stored.on_insufficient_funds() == &InsufficientFundsPolicy::Skip;
+                    if skipped {
+                        stored.advance(now);
+                    }
+                    (ScheduledTransferEventType::Failed, None, Some(e.to_string()), skipped)
+                }
+            };
+            (event_type, message, error, skipped, stored.clone())
+        };
+        // the runs without a next run time are finished
+        account.scheduled_transfers_mut().retain(|s| s.next_run().is_some());
+        account.save()?;
+
+        emit_scheduled_transfer_event(
+            event_type,
+            account.id().clone(),
+            &scheduled_transfer,
+            message.as_ref(),
+            error,
+            skipped,
+        );
+    }
+    Ok(())
+}
+
+async fn reconcile_outboxes(storage_path: &PathBuf) -> crate::Result<()> {
+    let accounts = crate::storage::with_adapter(&storage_path, |storage| storage.get_all())?;
+    for mut account in crate::storage::parse_accounts(&storage_path, &accounts)? {
+        if reconcile_outbox(&mut account).await? {
+            account.save()?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the account has at least `threshold` outputs to move into its latest address.
+fn needs_consolidation(account: &Account, threshold: usize) -> bool {
+    match account.latest_address() {
+        Some(target_address) => movable_outputs(account, target_address.address()).len() >= threshold,
+        None => false,
+    }
+}
+
+/// Consolidates the outputs of the accounts with at least `threshold` outputs to merge.
+/// A failed consolidation doesn't stop the others; its error is emitted when dropped.
+async fn consolidate_outputs(storage_path: &PathBuf, threshold: usize) -> crate::Result<()> {
+    let accounts = crate::storage::with_adapter(&storage_path, |storage| storage.get_all())?;
+    for mut account in crate::storage::parse_accounts(&storage_path, &accounts)? {
+        if needs_consolidation(&account, threshold) {
+            let _ = consolidate_account_outputs(&mut account, threshold).await;
+        }
+    }
+    Ok(())
+}
+
+async fn consolidate_account_outputs(account: &mut Account, threshold: usize) -> crate::Result<()> {
+    let synced = account.sync().execute().await?;
+    synced
+        .consolidate(ConsolidationOptions::new().min_outputs(threshold))
+        .await?;
     Ok(())
 }
 
@@ -501,6 +838,21 @@ async fn retry_unconfirmed_transactions(accounts: Vec<(&SyncedAccount, &Account)
     Ok(retried_messages)
 }
 
+/// Removes the account record replaced by a signer migration and its signer secrets, then clears the marker.
+fn complete_signer_migration(storage_path: &PathBuf, account: &mut Account) -> crate::Result<()> {
+    let old_id = match account.migrated_from() {
+        Some(old_id) => old_id.clone(),
+        None => return Ok(()),
+    };
+    if let Ok(old_account) = crate::storage::get_account(storage_path, old_id.clone().into()) {
+        // the secrets left on the previous signer aren't used by any account; a failure is emitted as an error event
+        let _ = with_signer(old_account.signer_type(), |signer| signer.remove_account(&old_account));
+        crate::storage::with_adapter(storage_path, |storage| storage.remove(old_id.into()))?;
+    }
+    account.set_migrated_from(None);
+    account.save()
+}
+
 fn copy_dir<U: AsRef<Path>, V: AsRef<Path>>(from: U, to: V) -> Result<(), std::io::Error> {
     let mut stack = Vec::new();
     stack.push(PathBuf::from(from.as_ref()));
@@ -537,10 +889,14 @@ fn copy_dir<U: AsRef<Path>, V: AsRef<Path>>(from: U, to: V) -> Result<(), std::i
 
 #[cfg(test)]
 mod tests {
+    use super::{AccountManager, MnemonicSource};
     use crate::{
-        address::{AddressBuilder, IotaAddress},
+        address::{AddressBuilder, IotaAddress, MAINNET_BECH32_HRP},
+        address_book::ContactBuilder,
         client::ClientOptionsBuilder,
         message::Message,
+        signing::{set_signer, with_signer, Mnemonic, SeedSigner, SignerType},
+        spending_policy::{SpendingPolicy, SpendingPolicyBuilder},
     };
     use iota::message::prelude::{Ed25519Address, Indexation, MessageBuilder, MessageId, Payload};
     use rusty_fork::rusty_fork_test;
@@ -584,9 +940,9 @@ mod tests {
                         "index".to_string(),
                         &[0; 16],
                     ).unwrap())))
-                    .with_network_id(0)
+                    .with_network_id(crate::client::network_id_from_name("devnet"))
                     .finish()
-                    .unwrap()).unwrap()])
+                    .unwrap(), MAINNET_BECH32_HRP).unwrap()])
                 .initialise().unwrap();
 
             let remove_response = manager.remove_account(account.id().into());
@@ -639,4 +995,232 @@ mod tests {
             assert!(create_response.is_err());
         }
     }
+
+    rusty_fork_test! {
+        #[test]
+        fn migrate_signer() {
+            let manager = crate::test_utils::get_account_manager();
+            let mnemonic = "error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip";
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+            set_signer(SignerType::Custom("target".to_string()), SeedSigner::new("passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mut account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic(mnemonic)
+                .initialise()
+                .expect("failed to add account");
+            let address = account.generate_address().unwrap();
+
+            let migrated_account = manager
+                .migrate_signer(
+                    account.id().into(),
+                    SignerType::Custom("target".to_string()),
+                    MnemonicSource::Mnemonic(Mnemonic::new(mnemonic.to_string())),
+                )
+                .unwrap();
+            assert_eq!(migrated_account.signer_type(), &SignerType::Custom("target".to_string()));
+            assert_eq!(migrated_account.addresses(), &vec![address]);
+            assert!(manager.get_account(account.id().into()).is_err());
+            assert_eq!(manager.get_account(migrated_account.id().into()).unwrap(), migrated_account);
+            assert_eq!(migrated_account.migrated_from(), &None);
+            // the previous signer doesn't keep the account seed
+            let response = with_signer(&SignerType::Mnemonic, |signer| signer.generate_address(&account, 0, false));
+            assert!(matches!(response, Err(crate::WalletError::SeedNotLoaded)));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn complete_interrupted_signer_migration() {
+            let manager = crate::test_utils::get_account_manager();
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic("error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip")
+                .initialise()
+                .expect("failed to add account");
+
+            // the process stopped after storing the migrated account
+            let mut migrated_account = account.clone();
+            migrated_account.set_id("migrated".to_string());
+            migrated_account.set_migrated_from(Some(account.id().clone()));
+            migrated_account.save().unwrap();
+            assert_eq!(manager.get_accounts().unwrap().len(), 2);
+
+            manager.complete_signer_migrations().unwrap();
+            let accounts = manager.get_accounts().unwrap();
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].id(), "migrated");
+            assert_eq!(accounts[0].migrated_from(), &None);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn migrate_signer_without_addresses() {
+            let manager = crate::test_utils::get_account_manager();
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+            set_signer(SignerType::Custom("target".to_string()), SeedSigner::new("other passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mnemonic = "error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip";
+            let account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic(mnemonic)
+                .initialise()
+                .expect("failed to add account");
+            assert!(account.addresses().is_empty());
+
+            // the target signer passphrase derives other addresses
+            let response = manager.migrate_signer(
+                account.id().into(),
+                SignerType::Custom("target".to_string()),
+                MnemonicSource::Mnemonic(Mnemonic::new(mnemonic.to_string())),
+            );
+            assert!(matches!(
+                response,
+                Err(crate::WalletError::SignerMigrationAddressMismatch { index: 0, internal: false })
+            ));
+            assert_eq!(manager.get_account(account.id().into()).unwrap().signer_type(), &SignerType::Mnemonic);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn migrate_signer_with_wrong_mnemonic() {
+            let manager = crate::test_utils::get_account_manager();
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+            set_signer(SignerType::Custom("target".to_string()), SeedSigner::new("passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mut account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic("error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip")
+                .initialise()
+                .expect("failed to add account");
+            account.generate_address().unwrap();
+
+            let response = manager.migrate_signer(
+                account.id().into(),
+                SignerType::Custom("target".to_string()),
+                MnemonicSource::Mnemonic(Mnemonic::new(
+                    "legal winner thank year wave sausage worth useful legal winner thank yellow".to_string(),
+                )),
+            );
+            match response {
+                Err(crate::WalletError::SignerMigrationAddressMismatch { index: 0, internal: false }) => {}
+                _ => panic!("unexpected migration response"),
+            }
+            assert_eq!(manager.get_account(account.id().into()).unwrap().signer_type(), &SignerType::Mnemonic);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn set_spending_policy() {
+            let manager = crate::test_utils::get_account_manager();
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let account = manager
+                .create_account(client_options)
+                .initialise()
+                .expect("failed to add account");
+            let policy = SpendingPolicyBuilder::new().daily_limit(1_000_000).build();
+
+            let response = manager.set_spending_policy(account.id().into(), policy.clone(), "wrong password");
+            assert!(matches!(response, Err(crate::WalletError::InvalidStrongholdPassword)));
+            assert_eq!(manager.get_account(account.id().into()).unwrap().spending_policy(), &SpendingPolicy::new());
+
+            manager
+                .set_spending_policy(account.id().into(), policy.clone(), "password")
+                .expect("failed to set spending policy");
+            assert_eq!(manager.get_account(account.id().into()).unwrap().spending_policy(), &policy);
+
+            // without a snapshot the password can't be checked
+            let storage_path = manager.storage_path.join("without-snapshot");
+            let manager_without_snapshot = AccountManager::with_storage_path(storage_path).unwrap();
+            let response = manager_without_snapshot.set_spending_policy(account.id().into(), policy, "any password");
+            assert!(matches!(response, Err(crate::WalletError::StrongholdSnapshotNotFound)));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn consolidation_threshold() {
+            let manager = crate::test_utils::get_account_manager();
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mut account = manager
+                .create_account(client_options)
+                .addresses(vec![
+                    crate::test_utils::funded_address(0, &[1_000_000; 3]),
+                    crate::test_utils::funded_address(1, &[1_000_000; 3]),
+                ])
+                .initialise()
+                .expect("failed to add account");
+
+            // the outputs of the latest address aren't consolidated, so they don't count
+            assert!(super::needs_consolidation(&account, 3));
+            assert!(!super::needs_consolidation(&account, 4));
+
+            let output_id = account.addresses()[0].outputs()[0].id();
+            account.freeze_output(output_id);
+            assert!(!super::needs_consolidation(&account, 3));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn address_book_storage_roundtrip() {
+            let manager = crate::test_utils::get_account_manager();
+            let alice = IotaAddress::Ed25519(Ed25519Address::new([1; 32]));
+            let bob = IotaAddress::Ed25519(Ed25519Address::new([2; 32]));
+
+            assert!(manager.get_contacts().unwrap().is_empty());
+            manager
+                .add_contact(ContactBuilder::new("Alice", alice.clone()).label("friends").build())
+                .unwrap();
+            manager.add_contact(ContactBuilder::new("Bob", bob.clone()).build()).unwrap();
+
+            let stored_alice = manager.get_contact("alice").unwrap();
+            assert_eq!(stored_alice.address(), &alice);
+            assert_eq!(stored_alice.labels(), &vec!["friends".to_string()]);
+            assert_eq!(manager.get_contacts().unwrap().len(), 2);
+
+            manager
+                .update_contact("Alice", ContactBuilder::new("Alice", alice).note("work").build())
+                .unwrap();
+            let updated_alice = manager.get_contact("Alice").unwrap();
+            assert_eq!(updated_alice.note(), &Some("work".to_string()));
+            assert_eq!(updated_alice.created_at(), stored_alice.created_at());
+
+            assert_eq!(manager.remove_contact("Bob").unwrap().address(), &bob);
+            // every update replaces the stored record, so only the latest address book is read back
+            let contacts = manager.get_contacts().unwrap();
+            assert_eq!(contacts, vec![updated_alice]);
+            assert!(matches!(
+                manager.get_contact("Bob"),
+                Err(crate::WalletError::ContactNotFound(_))
+            ));
+        }
+    }
 }
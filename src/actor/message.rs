@@ -4,8 +4,11 @@
 use crate::{
     account::{Account, AccountIdentifier, SyncedAccount},
     address::Address,
+    address_book::Contact,
     client::ClientOptions,
     message::{Message as WalletMessage, MessageType as WalletMessageType, Transfer},
+    signing::Mnemonic,
+    spending_policy::SpendingPolicy,
     WalletError,
 };
 use serde::{ser::Serializer, Deserialize, Serialize};
@@ -18,7 +21,8 @@ pub struct AccountToCreate {
     #[serde(rename = "clientOptions")]
     pub client_options: ClientOptions,
     /// The account mnemonic.
-    pub mnemonic: Option<String>,
+    #[serde(default, with = "crate::serde::mnemonic_serde")]
+    pub mnemonic: Option<Mnemonic>,
     /// The account alias.
     pub alias: Option<String>,
     /// The account createdAt date string.
@@ -112,7 +116,7 @@ pub enum MessageType {
         /// The account identifier.
         #[serde(rename = "accountId")]
         account_id: AccountIdentifier,
-        /// The transfer details.
+        /// The transfer details, with either a single `address` and `amount` or an `outputs` list.
         transfer: Transfer,
     },
     /// Move funds on stored accounts.
@@ -123,8 +127,49 @@ pub enum MessageType {
         /// The destination account identifier.
         #[serde(rename = "toAccountId")]
         to_account_id: AccountIdentifier,
-        /// The transfer amount.
+        /// The transfer amount, either a number of iotas or a string like `1.5 Mi`.
+        #[serde(with = "crate::serde::amount_serde")]
         amount: u64,
+        /// (Optional) idempotency key. Repeating the request with the same key returns the original message.
+        #[serde(rename = "idempotencyKey", default)]
+        idempotency_key: Option<String>,
+    },
+    /// Add a contact to the address book.
+    AddContact(Contact),
+    /// Read the address book contact with the given name.
+    GetContact(String),
+    /// Read the address book contacts.
+    GetContacts,
+    /// Replace an address book contact.
+    UpdateContact {
+        /// The name of the contact to replace.
+        name: String,
+        /// The new contact details.
+        contact: Contact,
+    },
+    /// Remove the address book contact with the given name.
+    RemoveContact(String),
+    /// Send funds to an address book contact.
+    SendTransferToContact {
+        /// The account identifier.
+        #[serde(rename = "accountId")]
+        account_id: AccountIdentifier,
+        /// The contact name.
+        #[serde(rename = "contactName")]
+        contact_name: String,
+        /// The transfer amount, either a number of iotas or a string like `1.5 Mi`.
+        #[serde(with = "crate::serde::amount_serde")]
+        amount: u64,
+    },
+    /// Replace the spending policy of an account.
+    SetSpendingPolicy {
+        /// The account identifier.
+        #[serde(rename = "accountId")]
+        account_id: AccountIdentifier,
+        /// The new spending policy.
+        policy: SpendingPolicy,
+        /// The stronghold password.
+        password: String,
     },
 }
 
@@ -160,7 +205,25 @@ impl Serialize for MessageType {
                 from_account_id: _,
                 to_account_id: _,
                 amount: _,
+                idempotency_key: _,
             } => serializer.serialize_unit_variant("MessageType", 11, "InternalTransfer"),
+            MessageType::AddContact(_) => serializer.serialize_unit_variant("MessageType", 12, "AddContact"),
+            MessageType::GetContact(_) => serializer.serialize_unit_variant("MessageType", 13, "GetContact"),
+            MessageType::GetContacts => serializer.serialize_unit_variant("MessageType", 14, "GetContacts"),
+            MessageType::UpdateContact { name: _, contact: _ } => {
+                serializer.serialize_unit_variant("MessageType", 15, "UpdateContact")
+            }
+            MessageType::RemoveContact(_) => serializer.serialize_unit_variant("MessageType", 16, "RemoveContact"),
+            MessageType::SendTransferToContact {
+                account_id: _,
+                contact_name: _,
+                amount: _,
+            } => serializer.serialize_unit_variant("MessageType", 17, "SendTransferToContact"),
+            MessageType::SetSpendingPolicy {
+                account_id: _,
+                policy: _,
+                password: _,
+            } => serializer.serialize_unit_variant("MessageType", 18, "SetSpendingPolicy"),
         }
     }
 }
@@ -226,8 +289,20 @@ pub enum ResponseType {
     BackupRestored,
     /// SetStrongholdPassword response.
     StrongholdPasswordSet,
-    /// SendTransfer and InternalTransfer response.
+    /// SendTransfer, InternalTransfer and SendTransferToContact response.
     SentTransfer(WalletMessage),
+    /// AddContact response.
+    ContactAdded,
+    /// GetContact response.
+    Contact(Contact),
+    /// GetContacts response.
+    Contacts(Vec<Contact>),
+    /// UpdateContact response.
+    ContactUpdated,
+    /// RemoveContact response.
+    ContactRemoved(Contact),
+    /// SetSpendingPolicy response.
+    SpendingPolicySet,
     /// An error occurred.
     Error(WalletError),
     /// A panic occurred.
@@ -4,7 +4,9 @@
 use crate::{
     account::AccountIdentifier,
     account_manager::AccountManager,
+    address_book::Contact,
     message::{Message as WalletMessage, Transfer},
+    spending_policy::SpendingPolicy,
     DateTime, Result, Utc,
 };
 use futures::{Future, FutureExt};
@@ -108,9 +110,33 @@ impl WalletMessageHandler {
                 from_account_id,
                 to_account_id,
                 amount,
+                idempotency_key,
             } => {
-                convert_async_panics(|| async { self.internal_transfer(from_account_id, to_account_id, *amount).await })
-                    .await
+                convert_async_panics(|| async {
+                    self.internal_transfer(from_account_id, to_account_id, *amount, idempotency_key)
+                        .await
+                })
+                .await
+            }
+            MessageType::AddContact(contact) => convert_panics(|| self.add_contact(contact)),
+            MessageType::GetContact(name) => convert_panics(|| self.get_contact(name)),
+            MessageType::GetContacts => convert_panics(|| self.get_contacts()),
+            MessageType::UpdateContact { name, contact } => convert_panics(|| self.update_contact(name, contact)),
+            MessageType::RemoveContact(name) => convert_panics(|| self.remove_contact(name)),
+            MessageType::SetSpendingPolicy {
+                account_id,
+                policy,
+                password,
+            } => convert_panics(|| self.set_spending_policy(account_id, policy, password)),
+            MessageType::SendTransferToContact {
+                account_id,
+                contact_name,
+                amount,
+            } => {
+                convert_async_panics(|| async {
+                    self.send_transfer_to_contact(account_id, contact_name, *amount).await
+                })
+                .await
             }
         };
 
@@ -215,7 +241,7 @@ impl WalletMessageHandler {
         let mut builder = self.account_manager.create_account(account.client_options.clone());
 
         if let Some(mnemonic) = &account.mnemonic {
-            builder = builder.mnemonic(mnemonic);
+            builder = builder.mnemonic(mnemonic.as_str());
         }
         if let Some(alias) = &account.alias {
             builder = builder.alias(alias);
@@ -253,17 +279,80 @@ impl WalletMessageHandler {
         Ok(ResponseType::SentTransfer(message))
     }
 
+    async fn send_transfer_to_contact(
+        &self,
+        account_id: &AccountIdentifier,
+        contact_name: &str,
+        amount: u64,
+    ) -> Result<ResponseType> {
+        let mut account = self.account_manager.get_account(account_id.clone())?;
+        let transfer = account.transfer_to_contact(contact_name, amount)?;
+        let synced = account.sync().execute().await?;
+        let message = synced.transfer(transfer).await?.message;
+        Ok(ResponseType::SentTransfer(message))
+    }
+
+    fn add_contact(&self, contact: &Contact) -> Result<ResponseType> {
+        self.account_manager.add_contact(contact.clone())?;
+        Ok(ResponseType::ContactAdded)
+    }
+
+    fn get_contact(&self, name: &str) -> Result<ResponseType> {
+        let contact = self.account_manager.get_contact(name)?;
+        Ok(ResponseType::Contact(contact))
+    }
+
+    fn get_contacts(&self) -> Result<ResponseType> {
+        let contacts = self.account_manager.get_contacts()?;
+        Ok(ResponseType::Contacts(contacts))
+    }
+
+    fn update_contact(&self, name: &str, contact: &Contact) -> Result<ResponseType> {
+        self.account_manager.update_contact(name, contact.clone())?;
+        Ok(ResponseType::ContactUpdated)
+    }
+
+    fn remove_contact(&self, name: &str) -> Result<ResponseType> {
+        let contact = self.account_manager.remove_contact(name)?;
+        Ok(ResponseType::ContactRemoved(contact))
+    }
+
+    fn set_spending_policy(
+        &self,
+        account_id: &AccountIdentifier,
+        policy: &SpendingPolicy,
+        password: &str,
+    ) -> Result<ResponseType> {
+        self.account_manager
+            .set_spending_policy(account_id.clone(), policy.clone(), password)?;
+        Ok(ResponseType::SpendingPolicySet)
+    }
+
     async fn internal_transfer(
         &self,
         from_account_id: &AccountIdentifier,
         to_account_id: &AccountIdentifier,
         amount: u64,
+        idempotency_key: &Option<String>,
     ) -> Result<ResponseType> {
-        let message = self
-            .account_manager
-            .internal_transfer(from_account_id.clone(), to_account_id.clone(), amount)
-            .await?
-            .message;
+        let metadata = match idempotency_key {
+            Some(idempotency_key) => {
+                self.account_manager
+                    .internal_transfer_with_idempotency_key(
+                        from_account_id.clone(),
+                        to_account_id.clone(),
+                        amount,
+                        idempotency_key.clone(),
+                    )
+                    .await?
+            }
+            None => {
+                self.account_manager
+                    .internal_transfer(from_account_id.clone(), to_account_id.clone(), amount)
+                    .await?
+            }
+        };
+        let message = metadata.message;
         Ok(ResponseType::SentTransfer(message))
     }
 }
@@ -1,7 +1,7 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{account::Account, message::MessageType};
+use crate::{account::Account, client::Network, message::MessageType};
 use bech32::FromBase32;
 use getset::{Getters, Setters};
 pub use iota::message::prelude::{Address as IotaAddress, Ed25519Address};
@@ -9,13 +9,74 @@ use iota::{
     message::prelude::{MessageId, TransactionId},
     OutputMetadata,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     cmp::Ordering,
+    collections::{BTreeMap, HashMap},
     convert::{TryFrom, TryInto},
     hash::{Hash, Hasher},
+    ops::Range,
+    sync::{Arc, Mutex},
+    thread,
 };
 
+/// Number of addresses derived on each thread when generating an address range.
+const ADDRESS_DERIVATION_BATCH_SIZE: usize = 5;
+/// Maximum number of threads deriving an address range.
+const ADDRESS_DERIVATION_MAX_THREADS: usize = 4;
+/// Maximum number of public or internal addresses kept on the account address cache.
+const MAX_CACHED_ADDRESSES: usize = 1000;
+
+/// The bech32 human-readable part of the mainnet addresses.
+pub const MAINNET_BECH32_HRP: &str = "iota";
+/// The bech32 human-readable part of the testnet (devnet and comnet) addresses.
+pub const TESTNET_BECH32_HRP: &str = "atoi";
+
+/// Gets the bech32 human-readable part of the addresses on the given network.
+/// Mainnet is assumed when the network isn't known.
+pub fn network_bech32_hrp(network: Option<&Network>) -> &'static str {
+    match network {
+        Some(Network::Mainnet) | None => MAINNET_BECH32_HRP,
+        Some(_) => TESTNET_BECH32_HRP,
+    }
+}
+
+/// Checks that the bech32 human-readable part has 1 to 83 lowercase ASCII characters.
+pub(crate) fn is_valid_bech32_hrp(bech32_hrp: &str) -> bool {
+    !bech32_hrp.is_empty()
+        && bech32_hrp.len() <= 83
+        && bech32_hrp
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !c.is_ascii_uppercase())
+}
+
+/// Encodes the address as bech32 with the given human-readable part.
+pub fn to_bech32(address: &IotaAddress, bech32_hrp: &str) -> crate::Result<String> {
+    if !is_valid_bech32_hrp(bech32_hrp) {
+        return Err(crate::WalletError::InvalidBech32Hrp(bech32_hrp.to_string()));
+    }
+    // re-encode the address payload; the iota client always uses its own HRP
+    let (_, data) = bech32::decode(&address.to_bech32())?;
+    Ok(bech32::encode(bech32_hrp, data)?)
+}
+
+/// An output identifier: the transaction that created the output and the output index.
+#[derive(Debug, Getters, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct OutputId {
+    /// Transaction ID of the output.
+    transaction_id: TransactionId,
+    /// Output index.
+    index: u16,
+}
+
+impl OutputId {
+    /// Initialises a new output identifier.
+    pub fn new(transaction_id: TransactionId, index: u16) -> Self {
+        Self { transaction_id, index }
+    }
+}
+
 /// An Address output.
 #[derive(Debug, Getters, Setters, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[getset(get = "pub")]
@@ -35,6 +96,13 @@ pub struct AddressOutput {
     pending_on_message_id: Option<MessageId>,
 }
 
+impl AddressOutput {
+    /// The output identifier.
+    pub fn id(&self) -> OutputId {
+        OutputId::new(self.transaction_id, self.index)
+    }
+}
+
 impl TryFrom<OutputMetadata> for AddressOutput {
     type Error = crate::WalletError;
 
@@ -63,6 +131,7 @@ impl TryFrom<OutputMetadata> for AddressOutput {
 #[derive(Default)]
 pub struct AddressBuilder {
     address: Option<IotaAddress>,
+    bech32_hrp: Option<String>,
     balance: Option<u64>,
     key_index: Option<usize>,
     internal: bool,
@@ -81,6 +150,13 @@ impl AddressBuilder {
         self
     }
 
+    /// Sets the bech32 human-readable part used to serialize the address.
+    /// Defaults to the mainnet HRP.
+    pub fn bech32_hrp(mut self, bech32_hrp: impl Into<String>) -> Self {
+        self.bech32_hrp = Some(bech32_hrp.into());
+        self
+    }
+
     /// Sets the address balance.
     pub fn balance(mut self, balance: u64) -> Self {
         self.balance = Some(balance);
@@ -110,8 +186,13 @@ impl AddressBuilder {
         let iota_address = self
             .address
             .ok_or_else(|| anyhow::anyhow!("the `address` field is required"))?;
+        let bech32_hrp = self.bech32_hrp.unwrap_or_else(|| MAINNET_BECH32_HRP.to_string());
+        if !is_valid_bech32_hrp(&bech32_hrp) {
+            return Err(crate::WalletError::InvalidBech32Hrp(bech32_hrp));
+        }
         let address = Address {
             address: iota_address,
+            bech32_hrp,
             balance: self
                 .balance
                 .ok_or_else(|| anyhow::anyhow!("the `balance` field is required"))?,
@@ -128,17 +209,18 @@ impl AddressBuilder {
 }
 
 /// An address.
-#[derive(Debug, Getters, Setters, Clone, Eq, Serialize, Deserialize)]
+#[derive(Debug, Getters, Setters, Clone, Eq)]
 #[getset(get = "pub")]
 pub struct Address {
     /// The address.
-    #[serde(with = "crate::serde::iota_address_serde")]
     address: IotaAddress,
+    /// The bech32 human-readable part used to serialize the address.
+    #[getset(set = "pub(crate)")]
+    bech32_hrp: String,
     /// The address balance.
     #[getset(set = "pub")]
     balance: u64,
     /// The address key index.
-    #[serde(rename = "keyIndex")]
     key_index: usize,
     /// Determines if an address is a public or an internal (change) address.
     internal: bool,
@@ -146,6 +228,55 @@ pub struct Address {
     pub(crate) outputs: Vec<AddressOutput>,
 }
 
+#[derive(Serialize)]
+struct AddressSerializer<'a> {
+    address: String,
+    balance: u64,
+    #[serde(rename = "keyIndex")]
+    key_index: usize,
+    internal: bool,
+    outputs: &'a [AddressOutput],
+}
+
+#[derive(Deserialize)]
+struct AddressDto {
+    address: String,
+    balance: u64,
+    #[serde(rename = "keyIndex")]
+    key_index: usize,
+    internal: bool,
+    outputs: Vec<AddressOutput>,
+}
+
+// the address is serialized with its own bech32 HRP, which is recovered on deserialization
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        AddressSerializer {
+            address: self.to_bech32(),
+            balance: self.balance,
+            key_index: self.key_index,
+            internal: self.internal,
+            outputs: &self.outputs,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let dto = AddressDto::deserialize(deserializer)?;
+        let (address, bech32_hrp) = parse_bech32(&dto.address).map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            address,
+            bech32_hrp,
+            balance: dto.balance,
+            key_index: dto.key_index,
+            internal: dto.internal,
+            outputs: dto.outputs,
+        })
+    }
+}
+
 impl PartialOrd for Address {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -171,6 +302,11 @@ impl PartialEq for Address {
 }
 
 impl Address {
+    /// Encodes the address as bech32 with its network's human-readable part.
+    pub fn to_bech32(&self) -> String {
+        to_bech32(&self.address, &self.bech32_hrp).expect("the address bech32 HRP is validated when it's set")
+    }
+
     pub(crate) fn handle_new_output(&mut self, output: AddressOutput) {
         if !self.outputs.iter().any(|o| o == &output) {
             let spent_existing_output = self.outputs.iter().position(|o| {
@@ -227,6 +363,21 @@ impl Address {
             .collect()
     }
 
+    /// Removes the outputs that aren't in the given filter, updating the balance accordingly.
+    pub(crate) fn retain_available_outputs<F: Fn(&AddressOutput) -> bool>(&mut self, filter: F) {
+        let mut removed_value = 0;
+        self.outputs.retain(|output| {
+            let available = !output.is_spent() && output.pending_on_message_id().is_none();
+            if available && !filter(output) {
+                removed_value += output.amount;
+                false
+            } else {
+                true
+            }
+        });
+        self.balance -= removed_value;
+    }
+
     pub(crate) fn available_balance(&self) -> u64 {
         let spent = self.outputs().iter().fold(0, |acc, o| {
             acc + if o.pending_on_message_id().is_some() {
@@ -239,29 +390,213 @@ impl Address {
     }
 }
 
-/// Parses a bech32 address string.
-pub fn parse(address: String) -> crate::Result<IotaAddress> {
-    let address_ed25519 = Vec::from_base32(&bech32::decode(&address)?.1)?;
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedAddress(#[serde(with = "crate::serde::iota_address_serde")] IotaAddress);
+
+/// The cache of the addresses derived for an account, keyed by address index and internal flag.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AddressCache {
+    #[serde(default)]
+    public: BTreeMap<usize, CachedAddress>,
+    #[serde(default)]
+    internal: BTreeMap<usize, CachedAddress>,
+}
+
+impl AddressCache {
+    fn addresses(&self, internal: bool) -> &BTreeMap<usize, CachedAddress> {
+        if internal {
+            &self.internal
+        } else {
+            &self.public
+        }
+    }
+
+    /// Gets the cached address with the given index.
+    pub(crate) fn get(&self, address_index: usize, internal: bool) -> Option<&IotaAddress> {
+        self.addresses(internal).get(&address_index).map(|address| &address.0)
+    }
+
+    /// Caches the address with the given index.
+    /// Once the cache is full, the highest indexes are dropped; every sync goes through the lowest ones.
+    pub(crate) fn insert(&mut self, address_index: usize, internal: bool, address: IotaAddress) {
+        let addresses = if internal { &mut self.internal } else { &mut self.public };
+        addresses.insert(address_index, CachedAddress(address));
+        if addresses.len() > MAX_CACHED_ADDRESSES {
+            if let Some(highest_index) = addresses.keys().next_back().cloned() {
+                addresses.remove(&highest_index);
+            }
+        }
+    }
+
+    /// Removes every cached address.
+    pub(crate) fn clear(&mut self) {
+        self.public.clear();
+        self.internal.clear();
+    }
+}
+
+/// Parses a bech32 address string, returning the address and its human-readable part.
+pub fn parse_bech32(address: &str) -> crate::Result<(IotaAddress, String)> {
+    let (bech32_hrp, data) = bech32::decode(address)?;
+    let bech32_hrp = bech32_hrp.to_lowercase();
+    if !is_valid_bech32_hrp(&bech32_hrp) {
+        return Err(crate::WalletError::InvalidBech32Hrp(bech32_hrp));
+    }
+    let address_ed25519 = Vec::from_base32(&data)?;
+    if address_ed25519.is_empty() {
+        return Err(crate::WalletError::InvalidAddressLength);
+    }
+    // 1 is the Ed25519 address type
+    if address_ed25519[0] != 1 {
+        return Err(anyhow::anyhow!("invalid address type").into());
+    }
     let iota_address = IotaAddress::Ed25519(Ed25519Address::new(
         address_ed25519[1..]
             .try_into()
             .map_err(|_| crate::WalletError::InvalidAddressLength)?,
     ));
+    Ok((iota_address, bech32_hrp))
+}
+
+/// Parses a bech32 address string.
+/// The human-readable part isn't checked; use `parse_with_hrp` to reject addresses of other networks.
+pub fn parse(address: String) -> crate::Result<IotaAddress> {
+    parse_bech32(&address).map(|(address, _)| address)
+}
+
+/// Parses a bech32 address string, rejecting addresses whose human-readable part doesn't match the expected one.
+pub fn parse_with_hrp(address: &str, bech32_hrp: &str) -> crate::Result<IotaAddress> {
+    let (iota_address, address_bech32_hrp) = parse_bech32(address)?;
+    if address_bech32_hrp != bech32_hrp {
+        return Err(crate::WalletError::Bech32HrpMismatch {
+            expected: bech32_hrp.to_string(),
+            found: address_bech32_hrp,
+        });
+    }
     Ok(iota_address)
 }
 
-pub(crate) fn get_iota_address(account: &Account, address_index: usize, internal: bool) -> crate::Result<IotaAddress> {
-    crate::signing::with_signer(account.signer_type(), |signer| {
+/// Gets the address with the given index, deriving and caching it if it isn't on the account cache.
+pub(crate) fn get_iota_address(
+    account: &mut Account,
+    address_index: usize,
+    internal: bool,
+) -> crate::Result<IotaAddress> {
+    if let Some(address) = account.address_cache().get(address_index, internal) {
+        return Ok(address.clone());
+    }
+    let address = crate::signing::with_signer(account.signer_type(), |signer| {
         signer.generate_address(&account, address_index, internal)
-    })
+    })?;
+    account
+        .address_cache_mut()
+        .insert(address_index, internal, address.clone());
+    Ok(address)
+}
+
+/// Gets the number of threads used to derive the given number of batches.
+fn derivation_thread_count(account: &Account, batch_count: usize) -> usize {
+    match account.signer_type() {
+        // stronghold serializes the derivations on its global instance
+        #[cfg(feature = "stronghold")]
+        crate::signing::SignerType::Stronghold => 1,
+        _ => batch_count.min(ADDRESS_DERIVATION_MAX_THREADS),
+    }
+}
+
+/// Gets the public and internal addresses on the given index range as (address_index, internal, address) tuples.
+/// The addresses missing on the account cache are derived in batches by a bounded set of threads and added to the
+/// cache.
+pub(crate) fn get_iota_addresses(
+    account: &mut Account,
+    range: Range<usize>,
+) -> crate::Result<Vec<(usize, bool, IotaAddress)>> {
+    // collect the uncached address ranges, split into batches
+    let mut derived_addresses = HashMap::new();
+    let mut batches: Vec<(Range<usize>, bool)> = vec![];
+    for internal in &[false, true] {
+        for address_index in range.clone() {
+            if account.address_cache().get(address_index, *internal).is_some() {
+                continue;
+            }
+            match batches.last_mut() {
+                Some((batch, batch_internal))
+                    if batch_internal == internal
+                        && batch.end == address_index
+                        && batch.len() < ADDRESS_DERIVATION_BATCH_SIZE =>
+                {
+                    batch.end += 1;
+                }
+                _ => batches.push((address_index..address_index + 1, *internal)),
+            }
+        }
+    }
+
+    if !batches.is_empty() {
+        let thread_count = derivation_thread_count(account, batches.len());
+        let account_ = Arc::new(account.clone());
+        let queue = Arc::new(Mutex::new(batches.into_iter()));
+        type DerivedBatch = (Range<usize>, bool, crate::Result<Vec<IotaAddress>>);
+        let handles: Vec<thread::JoinHandle<Vec<DerivedBatch>>> = (0..thread_count)
+            .map(|_| {
+                let account_ = account_.clone();
+                let queue = queue.clone();
+                thread::spawn(move || {
+                    let mut derived = vec![];
+                    loop {
+                        let next_batch = queue.lock().unwrap().next();
+                        let (batch, internal) = match next_batch {
+                            Some(batch) => batch,
+                            None => break,
+                        };
+                        let addresses = crate::signing::with_signer(account_.signer_type(), |signer| {
+                            signer.generate_addresses(&account_, batch.clone(), internal)
+                        });
+                        derived.push((batch, internal, addresses));
+                    }
+                    derived
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let derived = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("address derivation thread panicked"))?;
+            for (batch, internal, addresses) in derived {
+                for (address_index, address) in batch.zip(addresses?.into_iter()) {
+                    account
+                        .address_cache_mut()
+                        .insert(address_index, internal, address.clone());
+                    derived_addresses.insert((address_index, internal), address);
+                }
+            }
+        }
+    }
+
+    let mut addresses = vec![];
+    for address_index in range {
+        for internal in &[false, true] {
+            // the full cache may drop the derived addresses with the highest indexes
+            let address = account
+                .address_cache()
+                .get(address_index, *internal)
+                .or_else(|| derived_addresses.get(&(address_index, *internal)))
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("signer didn't generate address {}", address_index))?;
+            addresses.push((address_index, *internal, address));
+        }
+    }
+    Ok(addresses)
 }
 
 /// Gets an unused public address for the given account.
-pub(crate) fn get_new_address(account: &Account) -> crate::Result<Address> {
+pub(crate) fn get_new_address(account: &mut Account) -> crate::Result<Address> {
     let key_index = account.addresses().iter().filter(|a| !a.internal()).count();
-    let iota_address = get_iota_address(&account, key_index, false)?;
+    let iota_address = get_iota_address(account, key_index, false)?;
     let address = Address {
         address: iota_address,
+        bech32_hrp: account.bech32_hrp(),
         balance: 0,
         key_index,
         internal: false,
@@ -270,12 +605,26 @@ pub(crate) fn get_new_address(account: &Account) -> crate::Result<Address> {
     Ok(address)
 }
 
-/// Gets an unused change address for the given account and address.
-pub(crate) fn get_new_change_address(account: &Account, address: &Address) -> crate::Result<Address> {
-    let key_index = *address.key_index();
-    let iota_address = get_iota_address(&account, key_index, true)?;
+/// Gets the next index of the change chain: the index following every change address known to the account,
+/// and at least the account's recorded next change index.
+/// Indexes are never reused, even if the change address was emptied.
+pub(crate) fn next_change_index(addresses: &[Address], recorded_next_index: usize) -> usize {
+    addresses
+        .iter()
+        .filter(|address| address.internal)
+        .map(|address| address.key_index + 1)
+        .max()
+        .unwrap_or(0)
+        .max(recorded_next_index)
+}
+
+/// Gets an unused change address for the given account, on the next index of its change chain.
+pub(crate) fn get_new_change_address(account: &mut Account) -> crate::Result<Address> {
+    let key_index = next_change_index(account.addresses(), *account.next_change_index());
+    let iota_address = get_iota_address(account, key_index, true)?;
     let address = Address {
         address: iota_address,
+        bech32_hrp: account.bech32_hrp(),
         balance: 0,
         key_index,
         internal: true,
@@ -285,10 +634,10 @@ pub(crate) fn get_new_change_address(account: &Account, address: &Address) -> cr
 }
 
 /// Batch address generation.
-pub(crate) fn get_addresses(account: &Account, count: usize) -> crate::Result<Vec<Address>> {
+pub(crate) fn get_addresses(account: &mut Account, count: usize) -> crate::Result<Vec<Address>> {
     let mut addresses = vec![];
     for i in 0..count {
-        addresses.push(get_new_address(&account)?);
+        addresses.push(get_new_address(account)?);
     }
     Ok(addresses)
 }
@@ -299,3 +648,192 @@ pub(crate) fn is_unspent(account: &Account, address: &IotaAddress) -> bool {
         .iter()
         .any(|message| message.addresses().contains(&address))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::ClientOptionsBuilder,
+        signing::{set_signer, Mnemonic, Signer, SignerType, TransactionInput},
+    };
+    use rusty_fork::rusty_fork_test;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    static DERIVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    /// A signer counting the address derivations.
+    struct CountingSigner;
+
+    impl Signer for CountingSigner {
+        fn init_account(&self, _account: &Account, _mnemonic: Option<Mnemonic>) -> crate::Result<String> {
+            Ok(hex::encode([7u8; 32]))
+        }
+
+        fn generate_address(&self, _account: &Account, index: usize, internal: bool) -> crate::Result<IotaAddress> {
+            DERIVATIONS.fetch_add(1, AtomicOrdering::SeqCst);
+            let mut bytes = [0u8; 32];
+            bytes[0] = index as u8;
+            bytes[1] = internal as u8;
+            Ok(IotaAddress::Ed25519(Ed25519Address::new(bytes)))
+        }
+
+        fn sign_message(
+            &self,
+            _account: &Account,
+            _essence: &iota::TransactionEssence,
+            _inputs: &mut Vec<TransactionInput>,
+        ) -> crate::Result<Vec<iota::UnlockBlock>> {
+            Err(anyhow::anyhow!("the counting signer can't sign").into())
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn derived_addresses_are_cached() {
+            let manager = crate::test_utils::get_account_manager();
+            let signer_type = SignerType::Custom("counting".to_string());
+            set_signer(signer_type.clone(), CountingSigner);
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let mut account = manager
+                .create_account(client_options)
+                .signer_type(signer_type)
+                .skip_persistance()
+                .initialise()
+                .expect("failed to add account");
+
+            // a 10 address gap limit derives 20 addresses, each one once
+            let addresses = get_iota_addresses(&mut account, 0..10).unwrap();
+            assert_eq!(addresses.len(), 20);
+            assert_eq!(DERIVATIONS.load(AtomicOrdering::SeqCst), 20);
+            for (index, internal, address) in &addresses {
+                let mut bytes = [0u8; 32];
+                bytes[0] = *index as u8;
+                bytes[1] = *internal as u8;
+                assert_eq!(address, &IotaAddress::Ed25519(Ed25519Address::new(bytes)));
+            }
+
+            get_iota_addresses(&mut account, 0..10).unwrap();
+            get_iota_address(&mut account, 3, true).unwrap();
+            assert_eq!(DERIVATIONS.load(AtomicOrdering::SeqCst), 20);
+
+            // single derivations populate the cache too
+            get_iota_address(&mut account, 10, false).unwrap();
+            get_iota_addresses(&mut account, 10..11).unwrap();
+            assert_eq!(DERIVATIONS.load(AtomicOrdering::SeqCst), 22);
+        }
+    }
+
+    fn address() -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([3; 32]))
+    }
+
+    #[test]
+    fn address_cache_limit() {
+        let mut cache = AddressCache::default();
+        for address_index in 0..=MAX_CACHED_ADDRESSES {
+            cache.insert(address_index, false, address());
+        }
+        cache.insert(0, true, address());
+        assert_eq!(cache.public.len(), MAX_CACHED_ADDRESSES);
+        assert!(cache.get(MAX_CACHED_ADDRESSES - 1, false).is_some());
+        assert!(cache.get(MAX_CACHED_ADDRESSES, false).is_none());
+        assert!(cache.get(0, true).is_some());
+    }
+
+    #[test]
+    fn bech32_hrp_roundtrip() {
+        let testnet_address = to_bech32(&address(), TESTNET_BECH32_HRP).unwrap();
+        assert!(testnet_address.starts_with("atoi1"));
+        let (parsed, bech32_hrp) = parse_bech32(&testnet_address).unwrap();
+        assert_eq!(parsed, address());
+        assert_eq!(bech32_hrp, TESTNET_BECH32_HRP);
+
+        assert_eq!(parse_with_hrp(&testnet_address, TESTNET_BECH32_HRP).unwrap(), address());
+        match parse_with_hrp(&testnet_address, MAINNET_BECH32_HRP) {
+            Err(crate::WalletError::Bech32HrpMismatch { expected, found }) => {
+                assert_eq!(expected, MAINNET_BECH32_HRP);
+                assert_eq!(found, TESTNET_BECH32_HRP);
+            }
+            _ => panic!("expected a bech32 HRP mismatch"),
+        }
+
+        for bech32_hrp in &["", "IOTA", "io ta"] {
+            assert!(matches!(
+                to_bech32(&address(), bech32_hrp),
+                Err(crate::WalletError::InvalidBech32Hrp(_))
+            ));
+        }
+        assert!(matches!(
+            AddressBuilder::new()
+                .address(address())
+                .bech32_hrp("")
+                .balance(0)
+                .key_index(0)
+                .outputs(vec![])
+                .build(),
+            Err(crate::WalletError::InvalidBech32Hrp(_))
+        ));
+    }
+
+    #[test]
+    fn address_serialization_keeps_hrp() {
+        let address = AddressBuilder::new()
+            .address(address())
+            .bech32_hrp(TESTNET_BECH32_HRP)
+            .balance(0)
+            .key_index(0)
+            .outputs(vec![])
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert!(json.contains(&to_bech32(address.address(), TESTNET_BECH32_HRP).unwrap()));
+        let deserialized: Address = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.bech32_hrp(), TESTNET_BECH32_HRP);
+        assert_eq!(deserialized.address(), address.address());
+    }
+
+    fn change_address(key_index: usize) -> Address {
+        AddressBuilder::new()
+            .address(address())
+            .balance(0)
+            .key_index(key_index)
+            .outputs(vec![])
+            .internal(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn change_chain() {
+        let public_address = AddressBuilder::new()
+            .address(address())
+            .balance(10)
+            .key_index(3)
+            .outputs(vec![])
+            .build()
+            .unwrap();
+        let mut addresses = vec![public_address];
+        assert_eq!(next_change_index(&addresses, 0), 0);
+
+        // a recorded index is skipped even if its address isn't stored anymore
+        assert_eq!(next_change_index(&addresses, 3), 3);
+
+        // change addresses found by the sync are also considered used
+        addresses.push(change_address(7));
+        assert_eq!(next_change_index(&addresses, 3), 8);
+        assert_eq!(next_change_index(&addresses, 10), 10);
+    }
+
+    #[test]
+    fn network_hrp() {
+        assert_eq!(network_bech32_hrp(None), MAINNET_BECH32_HRP);
+        assert_eq!(network_bech32_hrp(Some(&Network::Mainnet)), MAINNET_BECH32_HRP);
+        assert_eq!(network_bech32_hrp(Some(&Network::Devnet)), TESTNET_BECH32_HRP);
+        assert!(!is_valid_bech32_hrp(""));
+        assert!(!is_valid_bech32_hrp("IOTA"));
+        assert!(is_valid_bech32_hrp("atoi"));
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{address::IotaAddress, client::Network};
+
+use chrono::prelude::{DateTime, Utc};
+use getset::Getters;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use std::{path::PathBuf, sync::Mutex};
+
+/// An address book contact.
+#[derive(Debug, Getters, Clone, PartialEq, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct Contact {
+    /// The contact name, unique on the address book.
+    name: String,
+    /// The contact address.
+    #[serde(with = "crate::serde::iota_address_serde")]
+    address: IotaAddress,
+    /// The contact labels.
+    #[serde(default)]
+    labels: Vec<String>,
+    /// A note about the contact.
+    #[serde(default)]
+    note: Option<String>,
+    /// The network the contact address belongs to.
+    #[serde(default)]
+    network: Option<Network>,
+    /// The time the contact was created.
+    #[serde(rename = "createdAt", default = "Utc::now")]
+    created_at: DateTime<Utc>,
+    /// The last time a transfer was sent to the contact.
+    #[serde(rename = "lastUsedAt", default)]
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl Contact {
+    /// Initialises a new contact.
+    pub fn new(name: impl Into<String>, address: IotaAddress) -> Self {
+        Self {
+            name: name.into(),
+            address,
+            labels: Vec::new(),
+            note: None,
+            network: None,
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    fn has_name(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.trim().to_lowercase()
+    }
+}
+
+/// The contact builder.
+pub struct ContactBuilder {
+    contact: Contact,
+}
+
+impl ContactBuilder {
+    /// Initialises a new instance of the contact builder.
+    pub fn new(name: impl Into<String>, address: IotaAddress) -> Self {
+        Self {
+            contact: Contact::new(name, address),
+        }
+    }
+
+    /// Adds a label to the contact.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.contact.labels.push(label.into());
+        self
+    }
+
+    /// Sets the note about the contact.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.contact.note = Some(note.into());
+        self
+    }
+
+    /// Sets the network the contact address belongs to.
+    /// Transfers to the contact are rejected on accounts connected to another network.
+    pub fn network(mut self, network: Network) -> Self {
+        self.contact.network = Some(network);
+        self
+    }
+
+    /// Builds the contact.
+    pub fn build(self) -> Contact {
+        self.contact
+    }
+}
+
+/// The contacts saved on the wallet storage.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct AddressBook {
+    contacts: Vec<Contact>,
+}
+
+impl AddressBook {
+    pub(crate) fn contacts(&self) -> &Vec<Contact> {
+        &self.contacts
+    }
+
+    /// Finds a contact by its name, ignoring case.
+    pub(crate) fn get(&self, name: &str) -> crate::Result<&Contact> {
+        self.contacts
+            .iter()
+            .find(|contact| contact.has_name(name))
+            .ok_or_else(|| crate::WalletError::ContactNotFound(name.to_string()))
+    }
+
+    /// Ensures the contact name and address aren't used by another contact.
+    fn validate(&self, contact: &mut Contact, replaced_name: Option<&str>) -> crate::Result<()> {
+        contact.name = contact.name.trim().to_string();
+        if contact.name.is_empty() {
+            return Err(crate::WalletError::InvalidContact(
+                "the contact name can't be empty".to_string(),
+            ));
+        }
+        for existing in &self.contacts {
+            if replaced_name.map(|name| existing.has_name(name)).unwrap_or(false) {
+                continue;
+            }
+            if existing.has_name(&contact.name) {
+                return Err(crate::WalletError::ContactAlreadyExists(existing.name.clone()));
+            }
+            // the same address under two names is usually a pasting mistake
+            if existing.address == contact.address {
+                return Err(crate::WalletError::ContactAlreadyExists(existing.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add(&mut self, mut contact: Contact) -> crate::Result<()> {
+        self.validate(&mut contact, None)?;
+        self.contacts.push(contact);
+        Ok(())
+    }
+
+    /// Replaces the contact with the given name, keeping its creation and last use time.
+    pub(crate) fn update(&mut self, name: &str, mut contact: Contact) -> crate::Result<()> {
+        let existing = self.get(name)?.clone();
+        self.validate(&mut contact, Some(name))?;
+        contact.created_at = existing.created_at;
+        contact.last_used_at = existing.last_used_at;
+        let index = self.contacts.iter().position(|c| c.has_name(name)).unwrap();
+        self.contacts[index] = contact;
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> crate::Result<Contact> {
+        let index = self
+            .contacts
+            .iter()
+            .position(|contact| contact.has_name(name))
+            .ok_or_else(|| crate::WalletError::ContactNotFound(name.to_string()))?;
+        Ok(self.contacts.remove(index))
+    }
+
+    /// Sets the last use time of the contacts with the given addresses.
+    /// Returns whether any contact was updated.
+    pub(crate) fn mark_used(&mut self, addresses: &[IotaAddress]) -> bool {
+        let now = Utc::now();
+        let mut updated = false;
+        for contact in self.contacts.iter_mut() {
+            if addresses.contains(&contact.address) {
+                contact.last_used_at = Some(now);
+                updated = true;
+            }
+        }
+        updated
+    }
+}
+
+fn lock() -> &'static Mutex<()> {
+    static LOCK: OnceCell<Mutex<()>> = OnceCell::new();
+    LOCK.get_or_init(Default::default)
+}
+
+/// Reads the address book from the storage.
+pub(crate) fn load(storage_path: &PathBuf) -> crate::Result<AddressBook> {
+    let address_book = crate::storage::with_adapter(storage_path, |storage| storage.get_address_book())?;
+    match address_book {
+        Some(address_book) => Ok(serde_json::from_str(&address_book)?),
+        None => Ok(AddressBook::default()),
+    }
+}
+
+/// Reads the address book, applies the callback and saves it.
+/// The address book is locked so concurrent updates aren't lost.
+pub(crate) fn update<T, F: FnOnce(&mut AddressBook) -> crate::Result<T>>(
+    storage_path: &PathBuf,
+    cb: F,
+) -> crate::Result<T> {
+    let _lock = lock().lock().unwrap();
+    let mut address_book = load(storage_path)?;
+    let res = cb(&mut address_book)?;
+    let address_book = serde_json::to_string(&address_book)?;
+    crate::storage::with_adapter(storage_path, |storage| storage.set_address_book(address_book))?;
+    Ok(res)
+}
+
+/// Sets the last use time of the contacts with the given addresses.
+pub(crate) fn mark_used(storage_path: &PathBuf, addresses: &[IotaAddress]) -> crate::Result<()> {
+    let _lock = lock().lock().unwrap();
+    let mut address_book = load(storage_path)?;
+    if address_book.mark_used(addresses) {
+        let address_book = serde_json::to_string(&address_book)?;
+        crate::storage::with_adapter(storage_path, |storage| storage.set_address_book(address_book))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressBook, Contact, ContactBuilder};
+    use crate::address::IotaAddress;
+    use iota::message::prelude::Ed25519Address;
+
+    fn address(byte: u8) -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    #[test]
+    fn add_validates_contacts() {
+        let mut address_book = AddressBook::default();
+        address_book
+            .add(ContactBuilder::new(" Alice ", address(1)).label("friends").build())
+            .unwrap();
+        assert_eq!(address_book.get("alice").unwrap().name(), "Alice");
+
+        // duplicated name
+        assert!(address_book.add(Contact::new("ALICE", address(2))).is_err());
+        // duplicated address
+        assert!(address_book.add(Contact::new("Bob", address(1))).is_err());
+        // empty name
+        assert!(address_book.add(Contact::new("  ", address(3))).is_err());
+
+        address_book.add(Contact::new("Bob", address(2))).unwrap();
+        assert_eq!(address_book.contacts().len(), 2);
+    }
+
+    #[test]
+    fn update_and_remove() {
+        let mut address_book = AddressBook::default();
+        address_book.add(Contact::new("Alice", address(1))).unwrap();
+        address_book.add(Contact::new("Bob", address(2))).unwrap();
+        assert!(address_book.mark_used(&[address(1)]));
+        let last_used_at = *address_book.get("Alice").unwrap().last_used_at();
+        assert!(last_used_at.is_some());
+
+        // renaming to an existing name is rejected
+        assert!(address_book.update("Alice", Contact::new("Bob", address(3))).is_err());
+        address_book
+            .update(
+                "alice",
+                ContactBuilder::new("Alice Smith", address(3))
+                    .note("new address")
+                    .build(),
+            )
+            .unwrap();
+        let contact = address_book.get("Alice Smith").unwrap();
+        assert_eq!(contact.address(), &address(3));
+        assert_eq!(contact.last_used_at(), &last_used_at);
+        assert!(address_book.get("Alice").is_err());
+
+        address_book.remove("bob").unwrap();
+        assert!(address_book.remove("Bob").is_err());
+        assert_eq!(address_book.contacts().len(), 1);
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reference remote signer server.
+//! Serves the mnemonic signer over a Unix domain socket, only accessible to its owner, or a TCP port.
+//! The mnemonic and its passphrase are loaded from the `IOTA_WALLET_MNEMONIC` and `IOTA_WALLET_MNEMONIC_PASSWORD`
+//! environment variables, and the clients must send the token set on the `IOTA_WALLET_REMOTE_SIGNER_TOKEN` variable.
+//!
+//! Usage: `remote_signer unix /tmp/iota-wallet-signer.sock` or `remote_signer tcp 127.0.0.1:5555`
+
+use iota_wallet::signing::{
+    serve_remote_signer, set_signer, RemoteSignerEndpoint, RemoteSignerListener, SeedSigner, SignerType,
+    REMOTE_SIGNER_TOKEN_ENV_VAR,
+};
+
+use std::env;
+
+fn main() -> iota_wallet::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let endpoint = match (args.get(1).map(|s| s.as_str()), args.get(2)) {
+        #[cfg(unix)]
+        (Some("unix"), Some(path)) => RemoteSignerEndpoint::Unix(path.into()),
+        (Some("tcp"), Some(address)) => RemoteSignerEndpoint::Tcp(
+            address
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid socket address {}", address))?,
+        ),
+        _ => {
+            return Err(anyhow::anyhow!("usage: remote_signer <unix|tcp> <path|address>").into());
+        }
+    };
+    let token = env::var(REMOTE_SIGNER_TOKEN_ENV_VAR)
+        .map_err(|_| anyhow::anyhow!("{} must be set", REMOTE_SIGNER_TOKEN_ENV_VAR))?;
+
+    set_signer(SignerType::Mnemonic, SeedSigner::from_env()?);
+
+    let listener = RemoteSignerListener::bind(&endpoint)?;
+    println!("remote signer listening on {:?}", endpoint);
+    serve_remote_signer(listener, SignerType::Mnemonic, token)
+}
@@ -1,6 +1,10 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
 use getset::Getters;
 pub use iota::client::builder::Network;
 use iota::client::{BrokerOptions, Client, ClientBuilder};
@@ -9,11 +13,15 @@ use serde::{Deserialize, Serialize};
 use url::Url;
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    convert::TryInto,
+    future::Future,
     sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 
 type ClientInstanceMap = Arc<Mutex<HashMap<ClientOptions, Arc<RwLock<Client>>>>>;
+type NetworkIdMap = Arc<Mutex<HashMap<ClientOptions, u64>>>;
 
 /// Gets the client instances map.
 fn instances() -> &'static ClientInstanceMap {
@@ -21,6 +29,87 @@ fn instances() -> &'static ClientInstanceMap {
     &INSTANCES
 }
 
+/// Gets the network ids map, cached per client.
+fn network_ids() -> &'static NetworkIdMap {
+    static NETWORK_IDS: Lazy<NetworkIdMap> = Lazy::new(Default::default);
+    &NETWORK_IDS
+}
+
+/// Computes the network id from the network name: the first 8 bytes of its BLAKE2b-256 hash, little endian.
+pub fn network_id_from_name(network_name: &str) -> u64 {
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(network_name.as_bytes());
+    let mut network_id = 0;
+    hasher.finalize_variable(|hash| {
+        network_id = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+    });
+    network_id
+}
+
+/// The maximum time to wait for a node's info when resolving the network id.
+const NODE_INFO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Gets the network id of the client's nodes.
+///
+/// Every node reports its network on the info endpoint; the nodes are queried concurrently and the ones that don't
+/// answer within `NODE_INFO_TIMEOUT` are skipped. When the options only set a network, the client's own node pool is
+/// queried. Fails if the nodes belong to different networks or if no node answers.
+pub(crate) async fn get_network_id(options: &ClientOptions) -> crate::Result<u64> {
+    if let Some(network_id) = network_ids().lock().unwrap().get(options) {
+        return Ok(*network_id);
+    }
+
+    let nodes: Vec<Url> = options
+        .node()
+        .iter()
+        .chain(options.nodes().iter().flatten())
+        .cloned()
+        .collect();
+    let node_options = if nodes.is_empty() {
+        vec![("the node pool".to_string(), options.clone())]
+    } else {
+        let mut node_options = Vec::new();
+        for node in nodes {
+            node_options.push((node.to_string(), ClientOptionsBuilder::node(node.as_str())?.build()));
+        }
+        node_options
+    };
+
+    let infos = futures::future::join_all(node_options.into_iter().map(|(node, node_options)| async move {
+        let client = get_client(&node_options);
+        let client = client.read().unwrap();
+        match tokio::time::timeout(NODE_INFO_TIMEOUT, client.get_info()).await {
+            Ok(Ok(info)) => Some((node, info.network_id)),
+            _ => None,
+        }
+    }))
+    .await;
+
+    // network name => nodes reporting it
+    let mut node_networks: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (node, network) in infos.into_iter().flatten() {
+        node_networks.entry(network).or_default().push(node);
+    }
+
+    let network_id = if node_networks.len() > 1 {
+        let networks: Vec<String> = node_networks
+            .iter()
+            .map(|(network, nodes)| format!("`{}` ({})", network, nodes.join(", ")))
+            .collect();
+        return Err(crate::WalletError::NetworkMismatch(format!(
+            "the nodes belong to different networks: {}",
+            networks.join(", ")
+        )));
+    } else if let Some(network_name) = node_networks.keys().next() {
+        network_id_from_name(network_name)
+    } else {
+        return Err(crate::WalletError::NetworkIdUnavailable);
+    };
+
+    network_ids().lock().unwrap().insert(options.clone(), network_id);
+    Ok(network_id)
+}
+
 pub(crate) fn get_client(options: &ClientOptions) -> Arc<RwLock<Client>> {
     let mut map = instances()
         .lock()
@@ -48,6 +137,11 @@ pub(crate) fn get_client(options: &ClientOptions) -> Arc<RwLock<Client>> {
             client_builder = client_builder.quorum_size(*quorum_size);
         }
 
+        client_builder = client_builder.local_pow(*options.local_pow());
+        if let Some(pow_worker_count) = options.pow_worker_count() {
+            client_builder = client_builder.pow_worker_count(*pow_worker_count);
+        }
+
         let client = client_builder.build().expect("failed to initialise ClientBuilder");
 
         map.insert(options.clone(), Arc::new(RwLock::new(client)));
@@ -57,18 +151,74 @@ pub(crate) fn get_client(options: &ClientOptions) -> Arc<RwLock<Client>> {
     client.clone()
 }
 
+/// Runs a client operation that posts a message (posting, promoting or reattaching it), including its proof-of-work,
+/// emitting the message posting events and enforcing the `pow_timeout` of the client options.
+///
+/// The client computes the proof-of-work and submits the message in the same operation, so the timeout covers both:
+/// a message may still reach the node after a `PowTimeout`, and callers must treat it as an unknown posting outcome.
+/// The timeout is only checked when the operation yields, so it can't interrupt a local proof-of-work computed
+/// synchronously inside the operation; it returns once the computation finishes.
+pub(crate) async fn with_message_posting<T, E, F>(
+    account_id: &str,
+    options: &ClientOptions,
+    operation: F,
+) -> crate::Result<T>
+where
+    E: Into<crate::WalletError>,
+    F: Future<Output = std::result::Result<T, E>>,
+{
+    crate::event::emit_message_posting_started(account_id.to_string(), *options.local_pow());
+    let started_at = Instant::now();
+    let result = match options.pow_timeout_ms() {
+        Some(pow_timeout_ms) => match tokio::time::timeout(Duration::from_millis(*pow_timeout_ms), operation).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(_) => Err(crate::WalletError::PowTimeout(*pow_timeout_ms)),
+        },
+        None => operation.await.map_err(Into::into),
+    };
+    crate::event::emit_message_posting_finished(
+        account_id.to_string(),
+        *options.local_pow(),
+        started_at.elapsed(),
+        result.as_ref().err().map(|e| e.to_string()),
+    );
+    result
+}
+
 /// The options builder for a client connected to a single node.
 pub struct SingleNodeClientOptionsBuilder {
     node: Url,
+    pow: PowOptions,
 }
 
 impl SingleNodeClientOptionsBuilder {
     fn new(node: &str) -> crate::Result<Self> {
         let node_url = Url::parse(node)?;
-        let builder = Self { node: node_url };
+        let builder = Self {
+            node: node_url,
+            pow: Default::default(),
+        };
         Ok(builder)
     }
 
+    /// Sets whether the proof-of-work is done locally or by the node. Defaults to local.
+    pub fn local_pow(mut self, local_pow: bool) -> Self {
+        self.pow.local_pow = local_pow;
+        self
+    }
+
+    /// Sets the number of threads used by the local proof-of-work.
+    pub fn pow_worker_count(mut self, pow_worker_count: usize) -> Self {
+        self.pow.worker_count = Some(pow_worker_count);
+        self
+    }
+
+    /// Sets the maximum time to post a message, including its proof-of-work, with millisecond precision.
+    pub fn pow_timeout(mut self, pow_timeout: Duration) -> Self {
+        self.pow.timeout_ms = Some(pow_timeout.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
     /// Builds the options.
     pub fn build(self) -> ClientOptions {
         ClientOptions {
@@ -77,6 +227,26 @@ impl SingleNodeClientOptionsBuilder {
             network: None,
             quorum_size: None,
             quorum_threshold: 0,
+            local_pow: self.pow.local_pow,
+            pow_worker_count: self.pow.worker_count,
+            pow_timeout_ms: self.pow.timeout_ms,
+        }
+    }
+}
+
+/// The proof-of-work options shared by the client options builders.
+struct PowOptions {
+    local_pow: bool,
+    worker_count: Option<usize>,
+    timeout_ms: Option<u64>,
+}
+
+impl Default for PowOptions {
+    fn default() -> Self {
+        Self {
+            local_pow: true,
+            worker_count: None,
+            timeout_ms: None,
         }
     }
 }
@@ -87,6 +257,7 @@ pub struct MultiNodeClientOptionsBuilder {
     network: Option<Network>,
     quorum_size: Option<u8>,
     quorum_threshold: f32,
+    pow: PowOptions,
     // state_adapter:
 }
 
@@ -117,6 +288,7 @@ impl Default for MultiNodeClientOptionsBuilder {
             network: None,
             quorum_size: None,
             quorum_threshold: 0.5,
+            pow: Default::default(),
         }
     }
 }
@@ -163,6 +335,24 @@ impl MultiNodeClientOptionsBuilder {
         self
     }
 
+    /// Sets whether the proof-of-work is done locally or by the nodes. Defaults to local.
+    pub fn local_pow(mut self, local_pow: bool) -> Self {
+        self.pow.local_pow = local_pow;
+        self
+    }
+
+    /// Sets the number of threads used by the local proof-of-work.
+    pub fn pow_worker_count(mut self, pow_worker_count: usize) -> Self {
+        self.pow.worker_count = Some(pow_worker_count);
+        self
+    }
+
+    /// Sets the maximum time to post a message, including its proof-of-work, with millisecond precision.
+    pub fn pow_timeout(mut self, pow_timeout: Duration) -> Self {
+        self.pow.timeout_ms = Some(pow_timeout.as_millis().try_into().unwrap_or(u64::MAX));
+        self
+    }
+
     /// Builds the options.
     pub fn build(self) -> crate::Result<ClientOptions> {
         let node_len = match &self.nodes {
@@ -178,6 +368,9 @@ impl MultiNodeClientOptionsBuilder {
             network: self.network,
             quorum_size: self.quorum_size,
             quorum_threshold: (self.quorum_threshold * 100.0) as u8,
+            local_pow: self.pow.local_pow,
+            pow_worker_count: self.pow.worker_count,
+            pow_timeout_ms: self.pow.timeout_ms,
         };
         Ok(options)
     }
@@ -227,7 +420,7 @@ impl ClientOptionsBuilder {
 }
 
 /// The client options type.
-#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, Getters)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, Getters)]
 #[getset(get = "pub(crate)")]
 pub struct ClientOptions {
     node: Option<Url>,
@@ -237,4 +430,74 @@ pub struct ClientOptions {
     quorum_size: Option<u8>,
     #[serde(rename = "quorumThreshold", default)]
     quorum_threshold: u8,
+    /// Whether the proof-of-work is done locally or by the node.
+    #[serde(rename = "localPow", default = "default_local_pow")]
+    local_pow: bool,
+    /// The number of threads used by the local proof-of-work.
+    #[serde(rename = "powWorkerCount", default)]
+    pow_worker_count: Option<usize>,
+    /// The maximum time in milliseconds to post a message, including its proof-of-work.
+    #[serde(rename = "powTimeoutMs", default)]
+    pow_timeout_ms: Option<u64>,
+}
+
+fn default_local_pow() -> bool {
+    true
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            node: None,
+            nodes: None,
+            network: None,
+            quorum_size: None,
+            quorum_threshold: 0,
+            local_pow: default_local_pow(),
+            pow_worker_count: None,
+            pow_timeout_ms: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_network_id, network_id_from_name, ClientOptions, ClientOptionsBuilder};
+    use std::time::Duration;
+
+    #[test]
+    fn network_id() {
+        assert_eq!(network_id_from_name("mainnet"), 11132824894578709914);
+        assert_eq!(network_id_from_name("testnet5"), 3720706210109972015);
+    }
+
+    #[tokio::test]
+    async fn network_id_unavailable() {
+        let options = ClientOptionsBuilder::nodes(&["http://127.0.0.1:1", "http://127.0.0.1:2"])
+            .unwrap()
+            .build();
+        match get_network_id(&options).await {
+            Err(crate::WalletError::NetworkIdUnavailable) => {}
+            _ => panic!("unexpected network id result"),
+        }
+    }
+
+    #[test]
+    fn pow_options() {
+        let options: ClientOptions = serde_json::from_str(r#"{"node":"https://nodes.devnet.iota.org:443"}"#).unwrap();
+        assert!(*options.local_pow());
+        assert_eq!(options.pow_timeout_ms(), &None);
+
+        let options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+            .unwrap()
+            .local_pow(false)
+            .pow_worker_count(2)
+            .pow_timeout(Duration::from_millis(1500))
+            .build();
+        assert!(!*options.local_pow());
+        assert_eq!(options.pow_worker_count(), &Some(2));
+        assert_eq!(options.pow_timeout_ms(), &Some(1500));
+        let serialized = serde_json::to_string(&options).unwrap();
+        assert_eq!(serde_json::from_str::<ClientOptions>(&serialized).unwrap(), options);
+    }
 }
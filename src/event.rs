@@ -1,7 +1,7 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{address::Address, message::Message};
+use crate::{address::Address, message::Message, schedule::ScheduledTransfer};
 
 use getset::Getters;
 use once_cell::sync::Lazy;
@@ -9,6 +9,7 @@ use serde::Serialize;
 use std::{
     ops::Deref,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 /// The balance change event data.
@@ -69,6 +70,43 @@ impl<'a> TransactionConfirmationChangeEvent<'a> {
     }
 }
 
+/// A scheduled transfer run event data.
+#[derive(Getters, Serialize)]
+#[getset(get = "pub")]
+pub struct ScheduledTransferEvent<'a> {
+    /// The associated account identifier.
+    #[serde(rename = "accountId")]
+    account_id: String,
+    /// The scheduled transfer, with its next run already updated.
+    #[serde(rename = "scheduledTransfer")]
+    scheduled_transfer: &'a ScheduledTransfer,
+    /// The message sent by the run, if it was executed.
+    message: Option<&'a Message>,
+    /// The error message, if the run failed.
+    error: Option<String>,
+    /// Whether the failed run was skipped or it'll be retried.
+    skipped: bool,
+}
+
+/// A message posting event data, emitted when a message is posted, promoted or reattached.
+///
+/// The posting includes the message's proof-of-work, so the duration covers both.
+#[derive(Getters, Serialize)]
+#[getset(get = "pub")]
+pub struct MessagePostingEvent {
+    /// The associated account identifier.
+    #[serde(rename = "accountId")]
+    account_id: String,
+    /// Whether the proof-of-work is done locally or by the node.
+    #[serde(rename = "localPow")]
+    local_pow: bool,
+    /// The posting duration in milliseconds, proof-of-work included, set when it's finished.
+    #[serde(rename = "durationMs")]
+    duration_ms: Option<u64>,
+    /// The error message, if the posting failed.
+    error: Option<String>,
+}
+
 struct BalanceEventHandler {
     /// The on event callback.
     on_event: Box<dyn Fn(&BalanceEvent<'_>) + Send>,
@@ -97,9 +135,35 @@ struct TransactionConfirmationChangeEventHandler {
     on_event: Box<dyn Fn(&TransactionConfirmationChangeEvent<'_>) + Send>,
 }
 
+#[derive(PartialEq)]
+pub(crate) enum ScheduledTransferEventType {
+    Executed,
+    Failed,
+}
+
+struct ScheduledTransferEventHandler {
+    event_type: ScheduledTransferEventType,
+    /// The on event callback.
+    on_event: Box<dyn Fn(&ScheduledTransferEvent<'_>) + Send>,
+}
+
+#[derive(PartialEq)]
+pub(crate) enum MessagePostingEventType {
+    Started,
+    Finished,
+}
+
+struct MessagePostingEventHandler {
+    event_type: MessagePostingEventType,
+    /// The on event callback.
+    on_event: Box<dyn Fn(&MessagePostingEvent) + Send>,
+}
+
 type BalanceListeners = Arc<Mutex<Vec<BalanceEventHandler>>>;
 type TransactionListeners = Arc<Mutex<Vec<TransactionEventHandler>>>;
 type TransactionConfirmationChangeListeners = Arc<Mutex<Vec<TransactionConfirmationChangeEventHandler>>>;
+type ScheduledTransferListeners = Arc<Mutex<Vec<ScheduledTransferEventHandler>>>;
+type MessagePostingListeners = Arc<Mutex<Vec<MessagePostingEventHandler>>>;
 type ErrorListeners = Arc<Mutex<Vec<ErrorHandler>>>;
 
 /// Gets the balance change listeners array.
@@ -120,6 +184,18 @@ fn transaction_confirmation_change_listeners() -> &'static TransactionConfirmati
     &LISTENERS
 }
 
+/// Gets the scheduled transfer listeners array.
+fn scheduled_transfer_listeners() -> &'static ScheduledTransferListeners {
+    static LISTENERS: Lazy<ScheduledTransferListeners> = Lazy::new(Default::default);
+    &LISTENERS
+}
+
+/// Gets the message posting listeners array.
+fn message_posting_listeners() -> &'static MessagePostingListeners {
+    static LISTENERS: Lazy<MessagePostingListeners> = Lazy::new(Default::default);
+    &LISTENERS
+}
+
 /// Gets the balance change listeners array.
 fn error_listeners() -> &'static ErrorListeners {
     static LISTENERS: Lazy<ErrorListeners> = Lazy::new(Default::default);
@@ -214,6 +290,123 @@ pub fn on_broadcast<F: Fn(&TransactionEvent<'_>) + Send + 'static>(cb: F) {
     add_transaction_listener(TransactionEventType::Broadcast, cb);
 }
 
+/// Emits a scheduled transfer run event.
+pub(crate) fn emit_scheduled_transfer_event(
+    event_type: ScheduledTransferEventType,
+    account_id: String,
+    scheduled_transfer: &ScheduledTransfer,
+    message: Option<&Message>,
+    error: Option<String>,
+    skipped: bool,
+) {
+    let listeners = scheduled_transfer_listeners()
+        .lock()
+        .expect("Failed to lock scheduled_transfer_listeners: emit_scheduled_transfer_event()");
+    let event = ScheduledTransferEvent {
+        account_id,
+        scheduled_transfer,
+        message,
+        error,
+        skipped,
+    };
+    for listener in listeners.deref() {
+        if listener.event_type == event_type {
+            (listener.on_event)(&event);
+        }
+    }
+}
+
+/// Adds a scheduled transfer event listener.
+fn add_scheduled_transfer_listener<F: Fn(&ScheduledTransferEvent<'_>) + Send + 'static>(
+    event_type: ScheduledTransferEventType,
+    cb: F,
+) {
+    let mut l = scheduled_transfer_listeners()
+        .lock()
+        .expect("Failed to lock scheduled_transfer_listeners: add_scheduled_transfer_listener()");
+    l.push(ScheduledTransferEventHandler {
+        event_type,
+        on_event: Box::new(cb),
+    })
+}
+
+/// Listen to executed scheduled transfer runs.
+pub fn on_scheduled_transfer_executed<F: Fn(&ScheduledTransferEvent<'_>) + Send + 'static>(cb: F) {
+    add_scheduled_transfer_listener(ScheduledTransferEventType::Executed, cb);
+}
+
+/// Listen to failed scheduled transfer runs.
+pub fn on_scheduled_transfer_failed<F: Fn(&ScheduledTransferEvent<'_>) + Send + 'static>(cb: F) {
+    add_scheduled_transfer_listener(ScheduledTransferEventType::Failed, cb);
+}
+
+fn emit_message_posting_event(event_type: MessagePostingEventType, event: MessagePostingEvent) {
+    let listeners = message_posting_listeners()
+        .lock()
+        .expect("Failed to lock message_posting_listeners: emit_message_posting_event()");
+    for listener in listeners.deref() {
+        if listener.event_type == event_type {
+            (listener.on_event)(&event);
+        }
+    }
+}
+
+/// Emits a message posting start event.
+pub(crate) fn emit_message_posting_started(account_id: String, local_pow: bool) {
+    emit_message_posting_event(
+        MessagePostingEventType::Started,
+        MessagePostingEvent {
+            account_id,
+            local_pow,
+            duration_ms: None,
+            error: None,
+        },
+    );
+}
+
+/// Emits a message posting finish event.
+pub(crate) fn emit_message_posting_finished(
+    account_id: String,
+    local_pow: bool,
+    duration: Duration,
+    error: Option<String>,
+) {
+    emit_message_posting_event(
+        MessagePostingEventType::Finished,
+        MessagePostingEvent {
+            account_id,
+            local_pow,
+            duration_ms: Some(duration.as_millis() as u64),
+            error,
+        },
+    );
+}
+
+/// Adds a message posting event listener.
+fn add_message_posting_listener<F: Fn(&MessagePostingEvent) + Send + 'static>(
+    event_type: MessagePostingEventType,
+    cb: F,
+) {
+    let mut l = message_posting_listeners()
+        .lock()
+        .expect("Failed to lock message_posting_listeners: add_message_posting_listener()");
+    l.push(MessagePostingEventHandler {
+        event_type,
+        on_event: Box::new(cb),
+    })
+}
+
+/// Listen to the start of the posting of the messages sent, promoted or reattached by the wallet.
+/// The posting includes the message's proof-of-work.
+pub fn on_message_posting_started<F: Fn(&MessagePostingEvent) + Send + 'static>(cb: F) {
+    add_message_posting_listener(MessagePostingEventType::Started, cb);
+}
+
+/// Listen to the end of the posting of the messages sent, promoted or reattached by the wallet, successful or not.
+pub fn on_message_posting_finished<F: Fn(&MessagePostingEvent) + Send + 'static>(cb: F) {
+    add_message_posting_listener(MessagePostingEventType::Finished, cb);
+}
+
 pub(crate) fn emit_error(error: &crate::WalletError) {
     let listeners = error_listeners()
         .lock()
@@ -14,6 +14,8 @@ pub mod account_manager;
 pub mod actor;
 /// The address module.
 pub mod address;
+/// The address book module.
+pub mod address_book;
 /// The client module.
 pub mod client;
 /// The event module.
@@ -22,11 +24,21 @@ pub mod event;
 pub mod message;
 /// The monitor module.
 pub mod monitor;
+/// The outbox module.
+pub mod outbox;
+/// The payment request module.
+pub mod payment_request;
+/// The scheduled transfers module.
+pub mod schedule;
 pub(crate) mod serde;
 /// Signing interfaces.
 pub mod signing;
+/// The spending policy module.
+pub mod spending_policy;
 /// The storage module.
 pub mod storage;
+/// The transaction validation module.
+pub mod validation;
 
 /// The wallet Result type.
 pub type Result<T> = std::result::Result<T, WalletError>;
@@ -85,6 +97,28 @@ pub enum WalletError {
     /// Node list is empty.
     #[error("empty node list")]
     EmptyNodeList,
+    /// The nodes belong to different networks, or to a network other than the account's.
+    #[error("network mismatch: {0}")]
+    NetworkMismatch(String),
+    /// The network id couldn't be fetched because no node answered.
+    #[error("the network id is unavailable; no node answered")]
+    NetworkIdUnavailable,
+    /// Posting the message, including its proof-of-work, exceeded the `pow_timeout` of the client options.
+    /// The message may still have reached the node; a transfer stays in the outbox until the next sync settles it.
+    #[error("the proof-of-work didn't finish in {0} milliseconds")]
+    PowTimeout(u64),
+    /// The transaction failed the local validation.
+    #[error("invalid transaction: {0}")]
+    InvalidTransaction(#[from] crate::validation::TransactionValidationError),
+    /// The transfer was rejected by the account spending policy.
+    #[error("spending policy violation: {0}")]
+    SpendingPolicyViolation(#[from] crate::spending_policy::SpendingPolicyError),
+    /// The stronghold password doesn't open the stronghold snapshot.
+    #[error("invalid stronghold password")]
+    InvalidStrongholdPassword,
+    /// The stronghold snapshot doesn't exist yet, so the stronghold password can't be verified.
+    #[error("the stronghold snapshot doesn't exist; set the stronghold password first")]
+    StrongholdSnapshotNotFound,
     /// Address length invalid.
     #[error("unexpected address length")]
     InvalidAddressLength,
@@ -97,6 +131,17 @@ pub enum WalletError {
     /// bech32 error.
     #[error("`{0}`")]
     Bech32Error(#[from] bech32::Error),
+    /// The address belongs to another network.
+    #[error("expected an address with the `{expected}` prefix, found `{found}`")]
+    Bech32HrpMismatch {
+        /// The bech32 human-readable part of the account network.
+        expected: String,
+        /// The bech32 human-readable part of the address.
+        found: String,
+    },
+    /// The bech32 human-readable part isn't valid.
+    #[error("invalid bech32 human-readable part `{0}`")]
+    InvalidBech32Hrp(String),
     /// An account is already imported.
     #[error("acount `{alias}` already imported")]
     AccountAlreadyImported {
@@ -125,6 +170,80 @@ pub enum WalletError {
     /// the address must belong to the account.
     #[error("the remainder value address doesn't belong to the account")]
     InvalidRemainderValueAddress,
+    /// The mnemonic signer doesn't have a passphrase set.
+    #[error("the mnemonic signer passphrase wasn't set")]
+    PassphraseNotSet,
+    /// The mnemonic signer doesn't have the seed of the account loaded.
+    #[error(
+        "the account seed isn't loaded on the mnemonic signer; load it with `SeedSigner::load_mnemonic` or register a \
+         signer created with `SeedSigner::from_env`"
+    )]
+    SeedNotLoaded,
+    /// An address derived by the target signer of a signer migration doesn't match the stored address.
+    #[error("the {} address with index {index} derived by the target signer doesn't match the stored address", if *.internal { "internal" } else { "public" })]
+    SignerMigrationAddressMismatch {
+        /// The address key index.
+        index: usize,
+        /// Whether the address is an internal (change) address or not.
+        internal: bool,
+    },
+    /// No single address covers the transfer value (privacy input selection).
+    #[error("no single address has enough balance to cover the transfer value")]
+    NoSingleInputAddress,
+    /// The transfer input doesn't exist, is already spent or is being spent by another transfer.
+    #[error("transfer input {0:?} isn't available")]
+    InputNotAvailable(crate::address::OutputId),
+    /// The transaction exceeds the protocol's maximum input count.
+    #[error("the transaction has {count} inputs, the maximum is {max}")]
+    TooManyInputs {
+        /// The number of inputs.
+        count: usize,
+        /// The maximum number of inputs.
+        max: usize,
+    },
+    /// The transaction exceeds the protocol's maximum output count.
+    #[error("the transaction has {count} outputs, the maximum is {max}")]
+    TooManyOutputs {
+        /// The number of outputs.
+        count: usize,
+        /// The maximum number of outputs.
+        max: usize,
+    },
+    /// A step of a split or consolidated transfer failed after the previous steps were posted.
+    #[error("the transfer failed after posting {} messages: {error}", .messages.len())]
+    PartialTransfer {
+        /// The messages posted before the failure.
+        messages: Vec<crate::message::Message>,
+        /// The error of the failed step.
+        error: String,
+    },
+    /// A transfer with the same idempotency key is being sent.
+    #[error("a transfer with the idempotency key `{0}` is in progress")]
+    TransferInProgress(String),
+    /// Invalid transfer schedule.
+    #[error("invalid schedule: {0}")]
+    InvalidSchedule(String),
+    /// Invalid payment request URI.
+    #[error("invalid payment request: {0}")]
+    InvalidPaymentRequest(String),
+    /// Address book contact not found.
+    #[error("contact `{0}` not found")]
+    ContactNotFound(String),
+    /// The contact name or address is already used by another contact.
+    #[error("contact `{0}` already has this name or address")]
+    ContactAlreadyExists(String),
+    /// Invalid address book contact.
+    #[error("invalid contact: {0}")]
+    InvalidContact(String),
+    /// Invalid amount.
+    #[error("invalid amount: {0}")]
+    InvalidAmount(String),
+    /// The amount doesn't fit in 64 bits once converted to iotas.
+    #[error("amount `{0}` overflows")]
+    AmountOverflow(String),
+    /// The transfer leaves a remainder value below the dust threshold and no other input can raise it.
+    #[error("the transfer leaves a remainder of {0}, below the dust threshold")]
+    DustRemainder(u64),
 }
 
 impl Drop for WalletError {
@@ -160,10 +279,26 @@ pub(crate) fn block_on<C: futures::Future>(cb: C) -> C::Output {
 
 #[cfg(test)]
 mod test_utils {
-    use super::account_manager::AccountManager;
+    use super::{
+        account::Account,
+        account_manager::AccountManager,
+        address::{Address, AddressBuilder, AddressOutput, IotaAddress},
+        client::ClientOptionsBuilder,
+        message::IotaMessage,
+    };
+    use iota::{
+        message::prelude::{
+            Ed25519Address, Ed25519Signature, Indexation, MessageId, Payload, SignatureLockedSingleOutput,
+            SignatureUnlock, Transaction, TransactionEssence, TransactionId, UTXOInput, UnlockBlock,
+        },
+        OutputMetadata,
+    };
     use once_cell::sync::OnceCell;
     use rand::{thread_rng, Rng};
-    use std::path::PathBuf;
+    use std::{convert::TryInto, num::NonZeroU64, path::PathBuf};
+
+    /// A BIP39 mnemonic for the tests that need addresses derived by a signer.
+    pub const MNEMONIC: &str = "error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip";
 
     static MANAGER_INSTANCE: OnceCell<AccountManager> = OnceCell::new();
     pub fn get_account_manager() -> &'static AccountManager {
@@ -176,4 +311,78 @@ mod test_utils {
             manager
         })
     }
+
+    /// Builds an unspent output of the given transaction.
+    pub fn output(transaction_byte: u8, index: u16, amount: u64, address: &IotaAddress) -> AddressOutput {
+        OutputMetadata {
+            message_id: vec![transaction_byte; 32],
+            transaction_id: vec![transaction_byte; 32],
+            output_index: index,
+            is_spent: false,
+            amount,
+            address: address.clone(),
+        }
+        .try_into()
+        .unwrap()
+    }
+
+    /// Builds a public account address holding unspent outputs with the given amounts.
+    pub fn funded_address(key_index: usize, amounts: &[u64]) -> Address {
+        let address = IotaAddress::Ed25519(Ed25519Address::new([100 + key_index as u8; 32]));
+        let outputs = amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| output(100 + key_index as u8, index as u16, *amount, &address))
+            .collect();
+        AddressBuilder::new()
+            .address(address)
+            .key_index(key_index)
+            .balance(amounts.iter().sum())
+            .outputs(outputs)
+            .build()
+            .unwrap()
+    }
+
+    /// Creates an account on the devnet node holding the given addresses.
+    pub fn account_with_funds(addresses: Vec<Address>) -> Account {
+        let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+            .expect("invalid node URL")
+            .build();
+        get_account_manager()
+            .create_account(client_options)
+            .addresses(addresses)
+            .initialise()
+            .expect("failed to add account")
+    }
+
+    /// Builds a message with the given payload.
+    pub fn iota_message(payload: Payload) -> IotaMessage {
+        IotaMessage::builder()
+            .with_parent1(MessageId::new([0; 32]))
+            .with_parent2(MessageId::new([0; 32]))
+            .with_payload(payload)
+            .with_network_id(0)
+            .finish()
+            .unwrap()
+    }
+
+    /// Builds a transaction payload with an unverified signature, embedding the indexation in its essence.
+    pub fn transaction_payload(indexation: Option<Indexation>) -> Payload {
+        let address = IotaAddress::Ed25519(Ed25519Address::new([1; 32]));
+        let mut essence_builder = TransactionEssence::builder()
+            .add_input(UTXOInput::new(TransactionId::new([1; 32]), 0).unwrap().into())
+            .add_output(SignatureLockedSingleOutput::new(address, NonZeroU64::new(1_000_000).unwrap()).into());
+        if let Some(indexation) = indexation {
+            essence_builder = essence_builder.with_payload(Payload::Indexation(Box::new(indexation)));
+        }
+        let transaction = Transaction::builder()
+            .with_essence(essence_builder.finish().unwrap())
+            .add_unlock_block(UnlockBlock::Signature(SignatureUnlock::Ed25519(Ed25519Signature::new(
+                [0; 32],
+                Box::new([0; 64]),
+            ))))
+            .finish()
+            .unwrap();
+        Payload::Transaction(Box::new(transaction))
+    }
 }
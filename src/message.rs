@@ -1,16 +1,22 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::address::{Address, IotaAddress};
+use crate::{
+    account::InputSelectionStrategy,
+    address::{parse_bech32, to_bech32, Address, IotaAddress, OutputId, MAINNET_BECH32_HRP},
+};
 use chrono::prelude::{DateTime, Utc};
 use getset::{Getters, Setters};
+use iota::message::prelude::Indexation;
 pub use iota::message::prelude::{Message as IotaMessage, MessageId, Output, Payload};
 use serde::{Deserialize, Serialize};
 use serde_repr::Deserialize_repr;
 use std::{
     cmp::Ordering,
+    convert::TryFrom,
     fmt,
     hash::{Hash, Hasher},
+    str::FromStr,
 };
 
 /// A transaction tag.
@@ -44,58 +50,336 @@ impl Tag {
 }
 
 /// The strategy to use for the remainder value management when sending funds.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "strategy", content = "value")]
 pub enum RemainderValueStrategy {
     /// Keep the remainder value on the source address.
     ReuseAddress,
-    /// Move the remainder value to a change address.
+    /// Move the remainder value to the next unused address of the account change chain.
     ChangeAddress,
     /// Move the remainder value to an address that must belong to the source account.
     #[serde(with = "crate::serde::iota_address_serde")]
     AccountAddress(IotaAddress),
 }
 
-/// A transfer to make a transaction.
-#[derive(Debug, Clone, Deserialize)]
-pub struct Transfer {
-    /// The transfer value.
+/// The index used for the transfer indexation payload when the transfer has data but no index.
+pub const DEFAULT_TRANSFER_INDEX: &str = "WALLET";
+
+/// The strategy to use when the transfer inputs exceed the protocol's maximum input count per transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputLimitStrategy {
+    /// Fails with a `TooManyInputs` error.
+    Fail,
+    /// Consolidates the account outputs first, waits for the consolidation to be confirmed, then sends the transfer.
+    Consolidate,
+    /// Splits the transfer into multiple transactions.
+    Split,
+}
+
+impl Default for InputLimitStrategy {
+    fn default() -> Self {
+        Self::Split
+    }
+}
+
+/// A transfer output.
+///
+/// The address is serialized with the bech32 HRP it was parsed with, or the account's one once the transfer is sent.
+#[derive(Debug, Clone, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+#[serde(try_from = "TransferOutputDto", into = "TransferOutputDto")]
+pub struct TransferOutput {
+    /// The output value.
     pub(crate) amount: u64,
-    /// The transfer address.
-    #[serde(with = "crate::serde::iota_address_serde")]
+    /// The output address.
     pub(crate) address: IotaAddress,
-    /// (Optional) transfer data.
+    /// The bech32 human-readable part of the address network. `None` if the address wasn't given as bech32.
+    pub(crate) bech32_hrp: Option<String>,
+}
+
+// the HRP only affects how the address is displayed
+impl PartialEq for TransferOutput {
+    fn eq(&self, other: &Self) -> bool {
+        self.amount == other.amount && self.address == other.address
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TransferOutputDto {
+    #[serde(with = "crate::serde::amount_serde")]
+    amount: u64,
+    address: String,
+}
+
+impl TryFrom<TransferOutputDto> for TransferOutput {
+    type Error = crate::WalletError;
+
+    fn try_from(dto: TransferOutputDto) -> crate::Result<Self> {
+        let (address, bech32_hrp) = parse_bech32(&dto.address)?;
+        Ok(Self {
+            amount: dto.amount,
+            address,
+            bech32_hrp: Some(bech32_hrp),
+        })
+    }
+}
+
+impl From<TransferOutput> for TransferOutputDto {
+    fn from(output: TransferOutput) -> Self {
+        Self {
+            amount: output.amount,
+            address: output.to_bech32(),
+        }
+    }
+}
+
+impl TransferOutput {
+    /// Initialises a new transfer output.
+    pub fn new(address: IotaAddress, amount: u64) -> Self {
+        Self {
+            address,
+            amount,
+            bech32_hrp: None,
+        }
+    }
+
+    /// Encodes the address as bech32 with its network's human-readable part, or the mainnet one if it isn't known.
+    pub fn to_bech32(&self) -> String {
+        let bech32_hrp = self.bech32_hrp.as_deref().unwrap_or(MAINNET_BECH32_HRP);
+        to_bech32(&self.address, bech32_hrp).expect("the output bech32 HRP is validated when it's set")
+    }
+}
+
+/// The transfer recipients, either a single address or a list of outputs.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransferRecipients {
+    Single(TransferOutput),
+    Batch { outputs: Vec<TransferOutput> },
+}
+
+#[derive(Deserialize)]
+struct TransferDto {
+    #[serde(flatten)]
+    recipients: TransferRecipients,
+    index: Option<String>,
+    data: Option<String>,
+    remainder_value_strategy: RemainderValueStrategy,
+    input_selection_strategy: Option<InputSelectionStrategy>,
+    #[serde(default)]
+    inputs: Vec<OutputId>,
+    #[serde(default, with = "crate::serde::iota_address_vec_serde")]
+    source_addresses: Vec<IotaAddress>,
+    #[serde(default)]
+    input_limit_strategy: InputLimitStrategy,
+    #[serde(default)]
+    send_max: bool,
+    idempotency_key: Option<String>,
+}
+
+impl From<TransferDto> for Transfer {
+    fn from(dto: TransferDto) -> Self {
+        let outputs = match dto.recipients {
+            TransferRecipients::Single(output) => vec![output],
+            TransferRecipients::Batch { outputs } => outputs,
+        };
+        Self {
+            outputs,
+            index: dto.index,
+            data: dto.data,
+            remainder_value_strategy: dto.remainder_value_strategy,
+            input_selection_strategy: dto.input_selection_strategy,
+            inputs: dto.inputs,
+            source_addresses: dto.source_addresses,
+            input_limit_strategy: dto.input_limit_strategy,
+            send_max: dto.send_max,
+            idempotency_key: dto.idempotency_key,
+        }
+    }
+}
+
+/// A transfer to make a transaction.
+///
+/// Deserializes from either a single `address` and `amount` pair or an `outputs` list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "TransferDto")]
+pub struct Transfer {
+    /// The transfer outputs.
+    pub(crate) outputs: Vec<TransferOutput>,
+    /// (Optional) index of the indexation payload attached to the transaction.
+    pub(crate) index: Option<String>,
+    /// (Optional) transfer data, attached to the transaction as an indexation payload.
     pub(crate) data: Option<String>,
     /// The strategy to use for the remainder value.
     pub(crate) remainder_value_strategy: RemainderValueStrategy,
+    /// The input selection strategy. Defaults to the account's strategy.
+    pub(crate) input_selection_strategy: Option<InputSelectionStrategy>,
+    /// The outputs to spend. If empty, the inputs are selected automatically.
+    pub(crate) inputs: Vec<OutputId>,
+    /// The addresses the automatic input selection is restricted to. If empty, any account address can be used.
+    #[serde(with = "crate::serde::iota_address_vec_serde")]
+    pub(crate) source_addresses: Vec<IotaAddress>,
+    /// The strategy to use when the inputs exceed the protocol's maximum input count.
+    pub(crate) input_limit_strategy: InputLimitStrategy,
+    /// Whether the transfer spends every available output, sending the whole value to its single output.
+    pub(crate) send_max: bool,
+    /// (Optional) key identifying the transfer. Repeating a transfer with the same key returns the original result.
+    pub(crate) idempotency_key: Option<String>,
 }
 
 impl Transfer {
     /// Initialises a new transfer to the given address.
     pub fn new(address: IotaAddress, amount: u64) -> Self {
+        Self::with_outputs(vec![(address, amount)])
+    }
+
+    /// Initialises a new transfer to multiple recipients, sent on a single transaction.
+    pub fn with_outputs(outputs: Vec<(IotaAddress, u64)>) -> Self {
         Self {
-            address,
-            amount,
+            outputs: outputs
+                .into_iter()
+                .map(|(address, amount)| TransferOutput::new(address, amount))
+                .collect(),
+            index: None,
             data: None,
             remainder_value_strategy: RemainderValueStrategy::ChangeAddress,
+            input_selection_strategy: None,
+            inputs: vec![],
+            source_addresses: vec![],
+            input_limit_strategy: Default::default(),
+            send_max: false,
+            idempotency_key: None,
         }
     }
 
+    /// Initialises a new transfer sending the whole available balance to the given address, without remainder.
+    /// The amount is resolved when the transfer is sent, spending every available output that isn't frozen,
+    /// restricted to the `source_addresses` or the explicit `inputs` if set.
+    pub fn max(address: IotaAddress) -> Self {
+        let mut transfer = Self::new(address, 0);
+        transfer.send_max = true;
+        transfer
+    }
+
     /// Sets the remainder value strategy for the transfer.
     pub fn remainder_value_strategy(mut self, strategy: RemainderValueStrategy) -> Self {
         self.remainder_value_strategy = strategy;
         self
     }
 
+    /// Sets the input selection strategy for the transfer, overriding the account's strategy.
+    pub fn input_selection_strategy(mut self, strategy: InputSelectionStrategy) -> Self {
+        self.input_selection_strategy = Some(strategy);
+        self
+    }
+
+    /// Spends exactly the given outputs instead of selecting the inputs automatically.
+    /// Frozen outputs can be spent this way.
+    pub fn inputs(mut self, inputs: Vec<OutputId>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Sets the strategy to use when the inputs exceed the protocol's maximum input count.
+    pub fn input_limit_strategy(mut self, strategy: InputLimitStrategy) -> Self {
+        self.input_limit_strategy = strategy;
+        self
+    }
+
+    /// Restricts the automatic input selection to the given addresses.
+    pub fn source_addresses(mut self, addresses: Vec<IotaAddress>) -> Self {
+        self.source_addresses = addresses;
+        self
+    }
+
+    /// Sets the idempotency key of the transfer.
+    /// If the account already sent a transfer with the same key, it isn't sent again and its result is returned instead.
+    pub fn idempotency_key(mut self, key: String) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
     /// (Optional) transfer data.
     pub fn data(mut self, data: String) -> Self {
         self.data = Some(data);
         self
     }
+
+    /// (Optional) index (tag) of the transfer data.
+    /// Defaults to `DEFAULT_TRANSFER_INDEX` when only the data is set.
+    pub fn index(mut self, index: String) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// The indexation payload to embed in the transaction essence, if the transfer has an index or data.
+    pub(crate) fn indexation(&self) -> crate::Result<Option<Indexation>> {
+        if self.index.is_none() && self.data.is_none() {
+            return Ok(None);
+        }
+        let index = self.index.clone().unwrap_or_else(|| DEFAULT_TRANSFER_INDEX.to_string());
+        let data = self.data.as_ref().map(|data| data.as_bytes()).unwrap_or_default();
+        let indexation = Indexation::new(index, data).map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+        Ok(Some(indexation))
+    }
+
+    /// The transfer outputs.
+    pub fn outputs(&self) -> &Vec<TransferOutput> {
+        &self.outputs
+    }
+
+    /// Whether the transfer sends the whole available balance.
+    pub fn is_send_max(&self) -> bool {
+        self.send_max
+    }
+
+    /// The sum of the transfer outputs value, saturating at `u64::MAX`; transfers overflowing it are rejected when
+    /// sent. On send-max transfers it's only known after the transfer is sent; see `TransferMetadata::amount`.
+    pub fn amount(&self) -> u64 {
+        self.checked_amount().unwrap_or(u64::MAX)
+    }
+
+    /// The sum of the transfer outputs value, or `None` if it overflows.
+    pub fn checked_amount(&self) -> Option<u64> {
+        self.outputs
+            .iter()
+            .try_fold(0u64, |acc, output| acc.checked_add(output.amount))
+    }
+
+    /// Checks that the recipient addresses given as bech32 belong to the network with the given HRP,
+    /// then sets it as the HRP of every output.
+    pub(crate) fn use_bech32_hrp(&mut self, bech32_hrp: &str) -> crate::Result<()> {
+        for output in &self.outputs {
+            if let Some(output_bech32_hrp) = &output.bech32_hrp {
+                if output_bech32_hrp != bech32_hrp {
+                    return Err(crate::WalletError::Bech32HrpMismatch {
+                        expected: bech32_hrp.to_string(),
+                        found: output_bech32_hrp.clone(),
+                    });
+                }
+            }
+        }
+        for output in self.outputs.iter_mut() {
+            output.bech32_hrp = Some(bech32_hrp.to_string());
+        }
+        Ok(())
+    }
+
+    /// The transfer outputs merged by address, keeping the order of the first occurrence of each address.
+    pub(crate) fn merged_outputs(&self) -> Vec<TransferOutput> {
+        let mut merged: Vec<TransferOutput> = vec![];
+        for output in &self.outputs {
+            match merged.iter_mut().find(|o| o.address == output.address) {
+                Some(existing) => existing.amount = existing.amount.saturating_add(output.amount),
+                None => merged.push(output.clone()),
+            }
+        }
+        merged
+    }
 }
 
 /// Possible Value units.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum ValueUnit {
     /// i
     I,
@@ -124,8 +408,149 @@ impl fmt::Display for ValueUnit {
     }
 }
 
+impl FromStr for ValueUnit {
+    type Err = crate::WalletError;
+
+    /// Parses a unit, ignoring case.
+    fn from_str(unit: &str) -> crate::Result<Self> {
+        let unit = match unit.to_lowercase().as_str() {
+            "i" => ValueUnit::I,
+            "ki" => ValueUnit::Ki,
+            "mi" => ValueUnit::Mi,
+            "gi" => ValueUnit::Gi,
+            "ti" => ValueUnit::Ti,
+            "pi" => ValueUnit::Pi,
+            _ => return Err(crate::WalletError::InvalidAmount(format!("unknown unit `{}`", unit))),
+        };
+        Ok(unit)
+    }
+}
+
+impl ValueUnit {
+    const UNITS: [ValueUnit; 6] = [
+        ValueUnit::Pi,
+        ValueUnit::Ti,
+        ValueUnit::Gi,
+        ValueUnit::Mi,
+        ValueUnit::Ki,
+        ValueUnit::I,
+    ];
+
+    /// The number of iotas in one unit.
+    pub fn multiplier(&self) -> u64 {
+        match self {
+            ValueUnit::I => 1,
+            ValueUnit::Ki => 1000,
+            ValueUnit::Mi => 1000000,
+            ValueUnit::Gi => 1000000000,
+            ValueUnit::Ti => 1000000000000,
+            ValueUnit::Pi => 1000000000000000,
+        }
+    }
+
+    /// The number of decimal places needed to express an iota in this unit.
+    fn decimals(&self) -> usize {
+        match self {
+            ValueUnit::I => 0,
+            ValueUnit::Ki => 3,
+            ValueUnit::Mi => 6,
+            ValueUnit::Gi => 9,
+            ValueUnit::Ti => 12,
+            ValueUnit::Pi => 15,
+        }
+    }
+
+    /// The largest unit that isn't greater than the given amount of iotas, used to display it.
+    pub fn best_fit(amount: u64) -> Self {
+        *Self::UNITS
+            .iter()
+            .find(|unit| amount >= unit.multiplier())
+            .unwrap_or(&ValueUnit::I)
+    }
+}
+
+/// Parses an amount of iotas from a decimal number with an optional unit, e.g. `150`, `1.5 Mi` or `0.25Gi`.
+/// Units are case insensitive and default to `i`.
+///
+/// Fails if the amount has more decimals than the unit allows (fractions of an iota) or if it overflows.
+pub fn parse_amount(amount: &str) -> crate::Result<u64> {
+    let invalid_amount = |reason: &str| crate::WalletError::InvalidAmount(format!("`{}` {}", amount, reason));
+    let trimmed = amount.trim();
+    let unit_index = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or_else(|| trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_index);
+    let unit = match unit.trim_start() {
+        "" => ValueUnit::I,
+        unit => unit.parse::<ValueUnit>()?,
+    };
+
+    let mut parts = number.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid_amount("isn't a number"));
+    }
+    if fraction.contains('.') {
+        return Err(invalid_amount("has more than one decimal point"));
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > unit.decimals() {
+        return Err(invalid_amount("has fractions of an iota"));
+    }
+
+    // the integer part only has digits, so parsing can only fail on overflow
+    let integer = match integer {
+        "" => 0,
+        integer => integer
+            .parse::<u64>()
+            .map_err(|_| crate::WalletError::AmountOverflow(amount.to_string()))?,
+    };
+    let fraction = match fraction {
+        "" => 0,
+        fraction => {
+            fraction.parse::<u64>().map_err(|_| invalid_amount("isn't a number"))?
+                * 10u64.pow((unit.decimals() - fraction.len()) as u32)
+        }
+    };
+    integer
+        .checked_mul(unit.multiplier())
+        .and_then(|value| value.checked_add(fraction))
+        .ok_or_else(|| crate::WalletError::AmountOverflow(amount.to_string()))
+}
+
+/// Formats an amount of iotas with the unit that fits it best and a fixed number of decimals, e.g. `1.50 Mi`.
+pub fn format_amount(amount: u64, precision: usize) -> String {
+    format_amount_with_unit(amount, ValueUnit::best_fit(amount), precision)
+}
+
+/// Formats an amount of iotas in the given unit with a fixed number of decimals, rounding half up.
+pub fn format_amount_with_unit(amount: u64, unit: ValueUnit, precision: usize) -> String {
+    // decimals beyond the unit's are always zero
+    let significant_decimals = precision.min(unit.decimals());
+    let step = (unit.multiplier() / 10u64.pow(significant_decimals as u32)) as u128;
+    let rounded = (amount as u128 + step / 2) / step;
+    let scale = 10u128.pow(significant_decimals as u32);
+    let integer = rounded / scale;
+    if precision == 0 {
+        return format!("{} {}", integer, unit);
+    }
+    let fraction = if significant_decimals == 0 {
+        String::new()
+    } else {
+        format!("{:0width$}", rounded % scale, width = significant_decimals)
+    };
+    format!(
+        "{}.{}{} {}",
+        integer,
+        fraction,
+        "0".repeat(precision - significant_decimals),
+        unit
+    )
+}
+
 /// The transaction Value struct.
-#[derive(Debug, Getters, Serialize, Deserialize, Clone)]
+#[derive(Debug, Getters, Serialize, Deserialize, Clone, PartialEq)]
 #[getset(get = "pub")]
 pub struct Value {
     /// The value.
@@ -140,22 +565,39 @@ impl Value {
         Self { value, unit }
     }
 
+    /// Initialises a Value from an amount of iotas, using the largest unit that represents it exactly.
+    pub fn from_iotas(amount: u64) -> Self {
+        let unit = *ValueUnit::UNITS
+            .iter()
+            .find(|unit| amount >= unit.multiplier() && amount % unit.multiplier() == 0)
+            .unwrap_or(&ValueUnit::I);
+        Self::new(amount / unit.multiplier(), unit)
+    }
+
     /// Formats the value with its unit.
     pub fn with_denomination(&self) -> String {
         format!("{} {}", self.value, self.unit)
     }
 
     /// The transaction value without its unit.
+    #[deprecated(note = "overflows on large values, use `without_denomination_checked` instead")]
     pub fn without_denomination(&self) -> u64 {
-        let multiplier = match self.unit {
-            ValueUnit::I => 1,
-            ValueUnit::Ki => 1000,
-            ValueUnit::Mi => 1000000,
-            ValueUnit::Gi => 1000000000,
-            ValueUnit::Ti => 1000000000000,
-            ValueUnit::Pi => 1000000000000000,
-        };
-        self.value * multiplier
+        self.value * self.unit.multiplier()
+    }
+
+    /// The transaction value without its unit, or `None` if it overflows.
+    pub fn without_denomination_checked(&self) -> Option<u64> {
+        self.value.checked_mul(self.unit.multiplier())
+    }
+}
+
+impl FromStr for Value {
+    type Err = crate::WalletError;
+
+    /// Parses a value like `1.5 Mi`; see `parse_amount`.
+    /// The value is kept on the largest unit that represents it exactly, e.g. `1.5 Mi` becomes `1500 Ki`.
+    fn from_str(value: &str) -> crate::Result<Self> {
+        parse_amount(value).map(Self::from_iotas)
     }
 }
 
@@ -193,6 +635,21 @@ pub struct Message {
     pub(crate) incoming: bool,
     /// The message's value.
     pub(crate) value: u64,
+    /// The index of the message's indexation payload.
+    #[serde(default)]
+    pub(crate) index: Option<String>,
+    /// The data of the message's indexation payload.
+    #[serde(default)]
+    pub(crate) data: Option<Vec<u8>>,
+    /// The idempotency key of the transfer that sent the message.
+    #[serde(rename = "idempotencyKey", default)]
+    pub(crate) idempotency_key: Option<String>,
+    /// The value sent to addresses outside of the account, set on the messages sent by the wallet.
+    #[serde(rename = "transferAmount", default)]
+    pub(crate) transfer_amount: Option<u64>,
+    /// The transaction outputs, with the addresses encoded with the account's bech32 HRP.
+    #[serde(default)]
+    pub(crate) outputs: Vec<TransferOutput>,
 }
 
 impl Hash for Message {
@@ -226,14 +683,20 @@ impl Message {
         id: MessageId,
         account_addresses: &[Address],
         message: &IotaMessage,
+        bech32_hrp: &str,
     ) -> crate::Result<Self> {
-        let message = Self {
+        let indexation = Self::indexation(message.payload());
+        let mut message = Self {
             id,
             version: 1,
             trunk: *message.parent1(),
             branch: *message.parent2(),
             payload_length: 5, // TODO
-            payload: message.payload().as_ref().unwrap().clone(),
+            payload: message
+                .payload()
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("message {} doesn't have a payload", id))?
+                .clone(),
             timestamp: Utc::now(),
             // TODO timestamp: DateTime::<Utc>::from_utc(
             //    NaiveDateTime::from_timestamp(*message.attachment_ts().to_inner() as i64, 0),
@@ -245,12 +708,60 @@ impl Message {
             incoming: account_addresses
                 .iter()
                 .any(|address| address.outputs().iter().any(|o| o.message_id() == &id)),
-            value: Self::compute_value(&message, &id, &account_addresses).without_denomination(),
+            // the computed value is always in iotas
+            value: *Self::compute_value(&message, &id, &account_addresses).value(),
+            index: indexation.map(|indexation| indexation.index().to_string()),
+            data: indexation.map(|indexation| indexation.data().to_vec()),
+            idempotency_key: None,
+            transfer_amount: None,
+            outputs: vec![],
         };
+        message.set_bech32_hrp(bech32_hrp)?;
 
         Ok(message)
     }
 
+    /// Encodes the transaction output addresses with the given bech32 HRP.
+    pub(crate) fn set_bech32_hrp(&mut self, bech32_hrp: &str) -> crate::Result<()> {
+        if !crate::address::is_valid_bech32_hrp(bech32_hrp) {
+            return Err(crate::WalletError::InvalidBech32Hrp(bech32_hrp.to_string()));
+        }
+        self.outputs = match &self.payload {
+            Payload::Transaction(tx) => tx
+                .essence()
+                .outputs()
+                .iter()
+                .filter_map(|output| match output {
+                    Output::SignatureLockedSingle(x) => Some(TransferOutput {
+                        amount: x.amount().get(),
+                        address: x.address().clone(),
+                        bech32_hrp: Some(bech32_hrp.to_string()),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+        Ok(())
+    }
+
+    /// Gets the indexation payload of the message, either as the message payload or inside the transaction essence.
+    fn indexation(payload: &Option<Payload>) -> Option<&Indexation> {
+        match payload {
+            Some(Payload::Indexation(indexation)) => Some(indexation),
+            Some(Payload::Transaction(tx)) => match tx.essence().payload() {
+                Some(Payload::Indexation(indexation)) => Some(indexation),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The message's indexation data as an UTF-8 string.
+    pub fn data_as_utf8(&self) -> Option<String> {
+        self.data.as_ref().map(|data| String::from_utf8_lossy(data).to_string())
+    }
+
     /// Check if attachment timestamp on transaction is above max depth (~11 minutes)
     pub(crate) fn is_above_max_depth(&self) -> bool {
         let current_timestamp = Utc::now().timestamp();
@@ -265,12 +776,9 @@ impl Message {
                 .essence()
                 .outputs()
                 .iter()
-                .map(|output| {
-                    if let Output::SignatureLockedSingle(x) = output {
-                        x.address()
-                    } else {
-                        unimplemented!()
-                    }
+                .filter_map(|output| match output {
+                    Output::SignatureLockedSingle(x) => Some(x.address()),
+                    _ => None,
                 })
                 .collect(),
             _ => vec![],
@@ -279,8 +787,8 @@ impl Message {
 
     /// Gets the absolute value of the transaction.
     pub fn compute_value(iota_message: &IotaMessage, id: &MessageId, account_addresses: &[Address]) -> Value {
-        let amount = match iota_message.payload().as_ref().unwrap() {
-            Payload::Transaction(tx) => {
+        let amount = match iota_message.payload() {
+            Some(Payload::Transaction(tx)) => {
                 let sent = !account_addresses
                     .iter()
                     .any(|address| address.outputs().iter().any(|o| o.message_id() == id));
@@ -323,4 +831,232 @@ pub enum MessageType {
     Unconfirmed = 4,
     /// A value message.
     Value = 5,
+    /// A data-only (indexation) message.
+    Data = 6,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_amount, format_amount_with_unit, parse_amount, Message, MessageId, Payload, Transfer, TransferOutput,
+        Value, ValueUnit, DEFAULT_TRANSFER_INDEX,
+    };
+    use crate::{
+        address::{to_bech32, IotaAddress, MAINNET_BECH32_HRP, TESTNET_BECH32_HRP},
+        test_utils::{iota_message, transaction_payload},
+    };
+    use iota::message::prelude::{Ed25519Address, Indexation};
+
+    fn address(byte: u8) -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    #[test]
+    fn transfer_indexation() {
+        let transfer = Transfer::new(address(1), 1_000_000);
+        assert!(transfer.indexation().unwrap().is_none());
+
+        let indexation = transfer
+            .clone()
+            .data("invoice 42".to_string())
+            .indexation()
+            .unwrap()
+            .unwrap();
+        assert_eq!(indexation.index(), DEFAULT_TRANSFER_INDEX);
+        assert_eq!(indexation.data(), b"invoice 42");
+
+        let indexation = transfer.index("INVOICES".to_string()).indexation().unwrap().unwrap();
+        assert_eq!(indexation.index(), "INVOICES");
+        assert!(indexation.data().is_empty());
+    }
+
+    #[test]
+    fn transaction_indexation() {
+        let indexation = Indexation::new("INVOICES".to_string(), b"invoice 42").unwrap();
+        let message_id = MessageId::new([2; 32]);
+
+        let message = Message::from_iota_message(
+            message_id,
+            &[],
+            &iota_message(transaction_payload(Some(indexation))),
+            MAINNET_BECH32_HRP,
+        )
+        .unwrap();
+        assert_eq!(message.index(), &Some("INVOICES".to_string()));
+        assert_eq!(message.data_as_utf8(), Some("invoice 42".to_string()));
+
+        let message = Message::from_iota_message(
+            message_id,
+            &[],
+            &iota_message(transaction_payload(None)),
+            MAINNET_BECH32_HRP,
+        )
+        .unwrap();
+        assert!(message.index().is_none());
+        assert!(message.data().is_none());
+    }
+
+    #[test]
+    fn data_message() {
+        let indexation = Indexation::new("NEWS".to_string(), b"hello").unwrap();
+        let message_id = MessageId::new([3; 32]);
+
+        let message = Message::from_iota_message(
+            message_id,
+            &[],
+            &iota_message(Payload::Indexation(Box::new(indexation))),
+            MAINNET_BECH32_HRP,
+        )
+        .unwrap();
+        assert_eq!(message.index(), &Some("NEWS".to_string()));
+        assert_eq!(message.data(), &Some(b"hello".to_vec()));
+        assert_eq!(*message.value(), 0);
+        assert!(message.addresses().is_empty());
+    }
+
+    #[test]
+    fn transfer_recipients() {
+        let single = serde_json::json!({
+            "address": to_bech32(&address(1), MAINNET_BECH32_HRP).unwrap(),
+            "amount": "1.5 Mi",
+            "remainder_value_strategy": { "strategy": "ChangeAddress" },
+        });
+        let transfer: Transfer = serde_json::from_value(single).unwrap();
+        assert_eq!(transfer, Transfer::new(address(1), 1_500_000));
+
+        let batch = serde_json::json!({
+            "outputs": [
+                { "address": to_bech32(&address(1), MAINNET_BECH32_HRP).unwrap(), "amount": 10 },
+                { "address": to_bech32(&address(2), MAINNET_BECH32_HRP).unwrap(), "amount": "1 Ki" },
+            ],
+            "remainder_value_strategy": { "strategy": "ChangeAddress" },
+        });
+        let transfer: Transfer = serde_json::from_value(batch).unwrap();
+        assert_eq!(
+            transfer,
+            Transfer::with_outputs(vec![(address(1), 10), (address(2), 1000)])
+        );
+        assert_eq!(transfer.amount(), 1010);
+    }
+
+    #[test]
+    fn transfer_bech32_hrp() {
+        let testnet_transfer = serde_json::json!({
+            "address": to_bech32(&address(1), TESTNET_BECH32_HRP).unwrap(),
+            "amount": 10,
+            "remainder_value_strategy": { "strategy": "ChangeAddress" },
+        });
+        let mut transfer: Transfer = serde_json::from_value(testnet_transfer).unwrap();
+        assert_eq!(
+            transfer.outputs()[0].bech32_hrp(),
+            &Some(TESTNET_BECH32_HRP.to_string())
+        );
+        assert!(matches!(
+            transfer.use_bech32_hrp(MAINNET_BECH32_HRP),
+            Err(crate::WalletError::Bech32HrpMismatch { .. })
+        ));
+        transfer.use_bech32_hrp(TESTNET_BECH32_HRP).unwrap();
+        let serialized = serde_json::to_string(&transfer).unwrap();
+        assert!(serialized.contains(&to_bech32(&address(1), TESTNET_BECH32_HRP).unwrap()));
+
+        // addresses given as `IotaAddress` take the account HRP
+        let mut transfer = Transfer::new(address(1), 10);
+        assert!(transfer.outputs()[0].bech32_hrp().is_none());
+        transfer.use_bech32_hrp(TESTNET_BECH32_HRP).unwrap();
+        assert_eq!(
+            transfer.outputs()[0].to_bech32(),
+            to_bech32(&address(1), TESTNET_BECH32_HRP).unwrap()
+        );
+
+        let invalid_address = serde_json::json!({
+            "address": "not an address",
+            "amount": 10,
+            "remainder_value_strategy": { "strategy": "ChangeAddress" },
+        });
+        assert!(serde_json::from_value::<Transfer>(invalid_address).is_err());
+    }
+
+    #[test]
+    fn message_outputs_bech32_hrp() {
+        let mut message = Message::from_iota_message(
+            MessageId::new([1; 32]),
+            &[],
+            &iota_message(transaction_payload(None)),
+            TESTNET_BECH32_HRP,
+        )
+        .unwrap();
+        assert_eq!(message.outputs(), &vec![TransferOutput::new(address(1), 1_000_000)]);
+        let serialized = serde_json::to_string(&message).unwrap();
+        assert!(serialized.contains(&to_bech32(&address(1), TESTNET_BECH32_HRP).unwrap()));
+
+        message.set_bech32_hrp(MAINNET_BECH32_HRP).unwrap();
+        assert_eq!(
+            message.outputs()[0].to_bech32(),
+            to_bech32(&address(1), MAINNET_BECH32_HRP).unwrap()
+        );
+        assert!(matches!(
+            message.set_bech32_hrp(""),
+            Err(crate::WalletError::InvalidBech32Hrp(_))
+        ));
+    }
+
+    #[test]
+    fn merged_outputs() {
+        let transfer = Transfer::with_outputs(vec![(address(1), 10), (address(2), 20), (address(1), 5)]);
+        assert_eq!(
+            transfer.merged_outputs(),
+            vec![TransferOutput::new(address(1), 15), TransferOutput::new(address(2), 20)]
+        );
+        assert_eq!(transfer.checked_amount(), Some(35));
+
+        let overflowing_transfer = Transfer::with_outputs(vec![(address(1), u64::MAX), (address(2), 1)]);
+        assert_eq!(overflowing_transfer.checked_amount(), None);
+        assert_eq!(overflowing_transfer.amount(), u64::MAX);
+    }
+
+    #[test]
+    fn parse_amounts() {
+        assert_eq!(parse_amount("150").unwrap(), 150);
+        assert_eq!(parse_amount("1.5 Mi").unwrap(), 1_500_000);
+        assert_eq!(parse_amount(" 0.25gi ").unwrap(), 250_000_000);
+        assert_eq!(parse_amount(".5Ki").unwrap(), 500);
+        assert_eq!(parse_amount("2.000 i").unwrap(), 2);
+        assert_eq!(parse_amount("18446744073709551615").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn reject_invalid_amounts() {
+        for amount in &["", "Mi", "-1", "1.5", "1.0001 Ki", "1.2.3 Mi", "1 Xi", "1,5 Mi"] {
+            assert!(
+                matches!(parse_amount(amount), Err(crate::WalletError::InvalidAmount(_))),
+                "{}",
+                amount
+            );
+        }
+        for amount in &["18446744073709551616", "18447 Pi", "18446.744073709551616 Pi"] {
+            assert!(
+                matches!(parse_amount(amount), Err(crate::WalletError::AmountOverflow(_))),
+                "{}",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn format_amounts() {
+        assert_eq!(format_amount(0, 2), "0.00 i");
+        assert_eq!(format_amount(999, 0), "999 i");
+        assert_eq!(format_amount(1_500_000, 2), "1.50 Mi");
+        assert_eq!(format_amount(1_234_567, 3), "1.235 Mi");
+        assert_eq!(format_amount(u64::MAX, 2), "18446.74 Pi");
+        assert_eq!(format_amount_with_unit(1500, ValueUnit::Ki, 5), "1.50000 Ki");
+        assert_eq!(format_amount_with_unit(1_999_999, ValueUnit::Mi, 1), "2.0 Mi");
+    }
+
+    #[test]
+    fn value_from_str() {
+        assert_eq!("1.5 Mi".parse::<Value>().unwrap(), Value::new(1500, ValueUnit::Ki));
+        assert_eq!("0".parse::<Value>().unwrap(), Value::new(0, ValueUnit::I));
+        assert_eq!(Value::new(18447, ValueUnit::Pi).without_denomination_checked(), None);
+    }
 }
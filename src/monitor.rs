@@ -91,7 +91,7 @@ pub fn monitor_address_balance(account: &Account, address: &IotaAddress) -> crat
     let storage_path = account.storage_path().clone();
     let client_options = account.client_options().clone();
     let address = address.clone();
-    let address_bech32 = address.to_bech32();
+    let address_bech32 = crate::address::to_bech32(&address, &account.bech32_hrp())?;
 
     subscribe_to_topic(
         account.client_options(),
@@ -159,15 +159,18 @@ async fn process_output(
             Some(message_index) => {
                 let message = &mut messages[message_index];
                 message.set_confirmed(true);
+                acc.on_message_confirmed(&message_id_);
             }
             None => {
-                let message = Message::from_iota_message(message_id_, &addresses, &message).unwrap();
-                crate::event::emit_transaction_event(
-                    crate::event::TransactionEventType::NewTransaction,
-                    account_id_raw,
-                    &message,
-                );
-                messages.push(message);
+                // a message that can't be parsed is skipped; dropping the error emits it as an error event
+                if let Ok(message) = Message::from_iota_message(message_id_, &addresses, &message, &acc.bech32_hrp()) {
+                    crate::event::emit_transaction_event(
+                        crate::event::TransactionEventType::NewTransaction,
+                        account_id_raw,
+                        &message,
+                    );
+                    messages.push(message);
+                }
             }
         }
     })?;
@@ -226,7 +229,9 @@ fn process_metadata(
         mutate_account(&account_id, &storage_path, |account, addresses, messages| {
             let message = messages.iter_mut().find(|m| m.id() == &message_id).unwrap();
             message.set_confirmed(confirmed);
-            if !confirmed {
+            if confirmed {
+                account.on_message_confirmed(message.id());
+            } else {
                 account.on_message_unconfirmed(message.id());
             }
             *addresses = account.addresses().to_vec();
@@ -0,0 +1,207 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::address::{IotaAddress, OutputId};
+
+use bee_common::packable::Packable;
+use chrono::prelude::{DateTime, Utc};
+use getset::Getters;
+use iota::message::prelude::{Message as IotaMessage, MessageId};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of settled (confirmed or failed) entries kept on the account outbox.
+const MAX_SETTLED_OUTBOX_ENTRIES: usize = 50;
+
+/// The state of an outgoing transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutboxState {
+    /// The inputs were selected but the transaction wasn't signed yet.
+    Prepared,
+    /// The message was signed but it might not have reached the node.
+    Signed,
+    /// The message was posted to the node.
+    Posted,
+    /// The message was confirmed.
+    Confirmed,
+    /// The transfer failed before the message was posted. Its inputs are available again.
+    Failed,
+}
+
+/// An outgoing transfer, persisted before signing so it can be recovered if the process stops mid-transfer.
+#[derive(Debug, Getters, Clone, PartialEq, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct OutboxEntry {
+    /// The entry identifier.
+    id: String,
+    /// The transfer state.
+    state: OutboxState,
+    /// The outputs spent by the transfer.
+    inputs: Vec<OutputId>,
+    /// The addresses of the outputs spent by the transfer.
+    #[serde(rename = "inputAddresses", with = "crate::serde::iota_address_vec_serde")]
+    input_addresses: Vec<IotaAddress>,
+    /// The value sent to addresses outside of the account; it's 0 for transfers between the account's addresses.
+    #[serde(default)]
+    amount: u64,
+    /// The idempotency key of the transfer.
+    #[serde(rename = "idempotencyKey", default)]
+    idempotency_key: Option<String>,
+    /// The packed signed message as hex string, kept until the message is posted.
+    #[serde(rename = "signedMessage", default)]
+    #[getset(skip)]
+    signed_message: Option<String>,
+    /// The id of the posted message.
+    #[serde(rename = "messageId", default)]
+    message_id: Option<MessageId>,
+    /// The time the transfer was prepared.
+    #[serde(rename = "createdAt")]
+    created_at: DateTime<Utc>,
+}
+
+impl OutboxEntry {
+    pub(crate) fn new(
+        inputs: Vec<OutputId>,
+        input_addresses: Vec<IotaAddress>,
+        amount: u64,
+        idempotency_key: Option<String>,
+    ) -> Self {
+        Self {
+            id: thread_rng().gen_ascii_chars().take(16).collect(),
+            state: OutboxState::Prepared,
+            inputs,
+            input_addresses,
+            amount,
+            idempotency_key,
+            signed_message: None,
+            message_id: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether the transfer is being signed or posted.
+    pub fn is_in_progress(&self) -> bool {
+        self.state == OutboxState::Prepared || self.state == OutboxState::Signed
+    }
+
+    /// Whether the transfer reached a final state or not.
+    pub fn is_settled(&self) -> bool {
+        self.state == OutboxState::Confirmed || self.state == OutboxState::Failed
+    }
+
+    pub(crate) fn set_signed(&mut self, message: &IotaMessage) {
+        self.signed_message = Some(hex::encode(message.pack_new()));
+        self.state = OutboxState::Signed;
+    }
+
+    pub(crate) fn set_posted(&mut self, message_id: MessageId) {
+        self.signed_message = None;
+        self.message_id = Some(message_id);
+        self.state = OutboxState::Posted;
+    }
+
+    pub(crate) fn set_message_id(&mut self, message_id: MessageId) {
+        self.message_id = Some(message_id);
+    }
+
+    pub(crate) fn set_state(&mut self, state: OutboxState) {
+        self.state = state;
+    }
+
+    /// Unpacks the signed message.
+    pub(crate) fn signed_message(&self) -> crate::Result<IotaMessage> {
+        let packed = self
+            .signed_message
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("the outbox entry doesn't have a signed message"))?;
+        let bytes = hex::decode(packed).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let message = IotaMessage::unpack(&mut bytes.as_slice()).map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+        Ok(message)
+    }
+}
+
+/// The state of a transfer sent as a chain of transactions, when it's split or its inputs are consolidated first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferChainState {
+    /// The chain is being sent, or the process stopped before it was completed.
+    InProgress,
+    /// Every transaction of the chain was sent.
+    Completed,
+    /// A transaction of the chain failed after the previous ones were sent.
+    Failed,
+}
+
+/// The outcome of a transfer chain with an idempotency key, returned to the retries of the transfer.
+#[derive(Debug, Getters, Clone, PartialEq, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct TransferChain {
+    /// The idempotency key of the transfer.
+    #[serde(rename = "idempotencyKey")]
+    idempotency_key: String,
+    /// The chain state.
+    state: TransferChainState,
+    /// The value sent to the transfer recipients, set when the chain is completed.
+    #[serde(default)]
+    amount: u64,
+    /// The error of the failed transaction.
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl TransferChain {
+    pub(crate) fn new(idempotency_key: String) -> Self {
+        Self {
+            idempotency_key,
+            state: TransferChainState::InProgress,
+            amount: 0,
+            error: None,
+        }
+    }
+
+    pub(crate) fn set_completed(&mut self, amount: u64) {
+        self.state = TransferChainState::Completed;
+        self.amount = amount;
+    }
+
+    pub(crate) fn set_failed(&mut self, error: String) {
+        self.state = TransferChainState::Failed;
+        self.error = Some(error);
+    }
+}
+
+/// Removes the oldest settled entries exceeding `MAX_SETTLED_OUTBOX_ENTRIES`.
+pub(crate) fn prune(outbox: &mut Vec<OutboxEntry>) {
+    let settled_count = outbox.iter().filter(|entry| entry.is_settled()).count();
+    let mut to_remove = settled_count.saturating_sub(MAX_SETTLED_OUTBOX_ENTRIES);
+    outbox.retain(|entry| {
+        if to_remove > 0 && entry.is_settled() {
+            to_remove -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prune, OutboxEntry, OutboxState, MAX_SETTLED_OUTBOX_ENTRIES};
+
+    #[test]
+    fn prune_keeps_pending_entries() {
+        let mut outbox = vec![OutboxEntry::new(vec![], vec![], 1, None)];
+        for _ in 0..MAX_SETTLED_OUTBOX_ENTRIES + 5 {
+            let mut entry = OutboxEntry::new(vec![], vec![], 1, None);
+            entry.set_state(OutboxState::Confirmed);
+            outbox.push(entry);
+        }
+        let pending_id = outbox[0].id().clone();
+        let newest_id = outbox.last().unwrap().id().clone();
+
+        prune(&mut outbox);
+
+        assert_eq!(outbox.len(), MAX_SETTLED_OUTBOX_ENTRIES + 1);
+        assert!(outbox.iter().any(|entry| entry.id() == &pending_id));
+        assert!(outbox.iter().any(|entry| entry.id() == &newest_id));
+    }
+}
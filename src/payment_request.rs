@@ -0,0 +1,286 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    address::{is_valid_bech32_hrp, parse_bech32, to_bech32, IotaAddress, MAINNET_BECH32_HRP},
+    message::{Transfer, Value},
+};
+
+use getset::Getters;
+use url::{form_urlencoded, Url};
+
+use std::{fmt, str::FromStr};
+
+/// The payment request URI scheme.
+pub const PAYMENT_REQUEST_SCHEME: &str = "iota";
+
+/// A payment request, exchanged as an `iota:<address>?amount=<amount>&message=<message>&data=<data>&label=<label>`
+/// URI. The amount is an integer with an optional unit suffix (`i`, `Ki`, `Mi`, `Gi`, `Ti` or `Pi`).
+#[derive(Debug, Clone, Getters, PartialEq)]
+#[getset(get = "pub")]
+pub struct PaymentRequest {
+    /// The recipient address.
+    address: IotaAddress,
+    /// The bech32 human-readable part of the recipient address.
+    bech32_hrp: String,
+    /// The requested amount.
+    amount: Option<Value>,
+    /// A message for the payer.
+    message: Option<String>,
+    /// The data attached to the transfer.
+    data: Option<String>,
+    /// A label for the recipient.
+    label: Option<String>,
+}
+
+fn invalid_request(reason: impl AsRef<str>) -> crate::WalletError {
+    crate::WalletError::InvalidPaymentRequest(reason.as_ref().to_string())
+}
+
+/// Parses an amount with an optional unit suffix, e.g. `10`, `10Mi` or `1.5Mi`.
+fn parse_amount(amount: &str) -> crate::Result<Value> {
+    amount.parse::<Value>().map_err(|e| invalid_request(e.to_string()))
+}
+
+impl PaymentRequest {
+    /// Initialises a new payment request to the given mainnet address.
+    pub fn new(address: IotaAddress) -> Self {
+        Self {
+            address,
+            bech32_hrp: MAINNET_BECH32_HRP.to_string(),
+            amount: None,
+            message: None,
+            data: None,
+            label: None,
+        }
+    }
+
+    /// Gets the payment request URI.
+    pub fn to_uri(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        if let Some(amount) = &self.amount {
+            query.append_pair("amount", &format!("{}{}", amount.value(), amount.unit()));
+        }
+        if let Some(message) = &self.message {
+            query.append_pair("message", message);
+        }
+        if let Some(data) = &self.data {
+            query.append_pair("data", data);
+        }
+        if let Some(label) = &self.label {
+            query.append_pair("label", label);
+        }
+        let query = query.finish();
+
+        let mut uri = format!(
+            "{}:{}",
+            PAYMENT_REQUEST_SCHEME,
+            to_bech32(&self.address, &self.bech32_hrp).expect("the payment request bech32 HRP is validated")
+        );
+        if !query.is_empty() {
+            uri.push('?');
+            uri.push_str(&query);
+        }
+        uri
+    }
+
+    /// Builds the transfer paying the request.
+    /// The request data is attached to the transfer; the message is only meant to be displayed to the payer.
+    /// The transfer output keeps the request address HRP, so it's rejected when sent from an account on another network.
+    pub fn to_transfer(&self) -> crate::Result<Transfer> {
+        let amount = self
+            .amount
+            .as_ref()
+            .ok_or_else(|| invalid_request("the payment request doesn't have an amount"))?
+            .without_denomination_checked()
+            .ok_or_else(|| invalid_request("the payment request amount is too large"))?;
+        if amount == 0 {
+            return Err(crate::WalletError::ZeroAmount);
+        }
+        let mut transfer = Transfer::new(self.address.clone(), amount);
+        transfer.outputs[0].bech32_hrp = Some(self.bech32_hrp.clone());
+        if let Some(data) = &self.data {
+            transfer = transfer.data(data.clone());
+        }
+        Ok(transfer)
+    }
+}
+
+/// The payment request builder.
+pub struct PaymentRequestBuilder {
+    request: PaymentRequest,
+}
+
+impl PaymentRequestBuilder {
+    /// Initialises a new instance of the payment request builder to the given mainnet address.
+    pub fn new(address: IotaAddress) -> Self {
+        Self {
+            request: PaymentRequest::new(address),
+        }
+    }
+
+    /// Sets the bech32 human-readable part of the recipient address.
+    pub fn bech32_hrp(mut self, bech32_hrp: impl Into<String>) -> Self {
+        self.request.bech32_hrp = bech32_hrp.into();
+        self
+    }
+
+    /// Sets the requested amount.
+    pub fn amount(mut self, amount: Value) -> Self {
+        self.request.amount = Some(amount);
+        self
+    }
+
+    /// Sets the message for the payer.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.request.message = Some(message.into());
+        self
+    }
+
+    /// Sets the data attached to the transfer.
+    pub fn data(mut self, data: impl Into<String>) -> Self {
+        self.request.data = Some(data.into());
+        self
+    }
+
+    /// Sets the label for the recipient.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.request.label = Some(label.into());
+        self
+    }
+
+    /// Builds the payment request.
+    pub fn build(self) -> crate::Result<PaymentRequest> {
+        if !is_valid_bech32_hrp(&self.request.bech32_hrp) {
+            return Err(crate::WalletError::InvalidBech32Hrp(self.request.bech32_hrp));
+        }
+        Ok(self.request)
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = crate::WalletError;
+
+    fn from_str(uri: &str) -> crate::Result<Self> {
+        let url = Url::parse(uri).map_err(|e| invalid_request(e.to_string()))?;
+        if url.scheme() != PAYMENT_REQUEST_SCHEME {
+            return Err(invalid_request(format!("unexpected scheme `{}`", url.scheme())));
+        }
+        let (address, bech32_hrp) = parse_bech32(url.path())?;
+
+        let mut request = Self::new(address);
+        request.bech32_hrp = bech32_hrp;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "amount" => request.amount = Some(parse_amount(&value)?),
+                "message" => request.message = Some(value.into_owned()),
+                "data" => request.data = Some(value.into_owned()),
+                "label" => request.label = Some(value.into_owned()),
+                // unknown parameters are ignored so newer requests can still be paid
+                _ => {}
+            }
+        }
+        Ok(request)
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_uri())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PaymentRequest, PaymentRequestBuilder};
+    use crate::{
+        address::{to_bech32, IotaAddress, MAINNET_BECH32_HRP, TESTNET_BECH32_HRP},
+        message::{Value, ValueUnit},
+    };
+    use iota::message::prelude::Ed25519Address;
+
+    fn address() -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([7; 32]))
+    }
+
+    fn bech32_address() -> String {
+        to_bech32(&address(), MAINNET_BECH32_HRP).unwrap()
+    }
+
+    #[test]
+    fn uri_roundtrip() {
+        let request = PaymentRequestBuilder::new(address())
+            .amount(Value::new(5, ValueUnit::Mi))
+            .message("coffee & cake")
+            .data("order #42")
+            .label("Café")
+            .build()
+            .unwrap();
+        let uri = request.to_uri();
+        assert!(uri.starts_with(&format!("iota:{}?amount=5Mi&", bech32_address())));
+        assert_eq!(uri.parse::<PaymentRequest>().unwrap(), request);
+    }
+
+    #[test]
+    fn address_only() {
+        let uri = format!("iota:{}", bech32_address());
+        let request: PaymentRequest = uri.parse().unwrap();
+        assert_eq!(request, PaymentRequest::new(address()));
+        assert_eq!(request.to_uri(), uri);
+        assert!(request.to_transfer().is_err());
+    }
+
+    #[test]
+    fn amount_without_unit() {
+        let request: PaymentRequest = format!("iota:{}?amount=150&unknown=1", bech32_address())
+            .parse()
+            .unwrap();
+        assert_eq!(request.amount(), &Some(Value::new(150, ValueUnit::I)));
+        let transfer = request.to_transfer().unwrap();
+        assert_eq!(transfer.amount(), 150);
+    }
+
+    #[test]
+    fn testnet_address() {
+        let uri = format!("iota:{}", to_bech32(&address(), TESTNET_BECH32_HRP).unwrap());
+        let request: PaymentRequest = uri.parse().unwrap();
+        assert_eq!(request.bech32_hrp(), TESTNET_BECH32_HRP);
+        assert_eq!(request.address(), &address());
+        assert_eq!(request.to_uri(), uri);
+    }
+
+    #[test]
+    fn transfer_keeps_bech32_hrp() {
+        let uri = format!("iota:{}?amount=1Ki", to_bech32(&address(), TESTNET_BECH32_HRP).unwrap());
+        let request: PaymentRequest = uri.parse().unwrap();
+        let transfer = request.to_transfer().unwrap();
+        assert_eq!(
+            transfer.outputs()[0].bech32_hrp(),
+            &Some(TESTNET_BECH32_HRP.to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_bech32_hrp() {
+        for bech32_hrp in &["", "IOTA", "io ta"] {
+            assert!(matches!(
+                PaymentRequestBuilder::new(address()).bech32_hrp(*bech32_hrp).build(),
+                Err(crate::WalletError::InvalidBech32Hrp(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_uris() {
+        let address = bech32_address();
+        for uri in &[
+            format!("bitcoin:{}", address),
+            "iota:not-an-address".to_string(),
+            format!("iota:{}?amount=10Xi", address),
+            format!("iota:{}?amount=-1", address),
+            format!("iota:{}?amount=18446744073709551615Pi", address),
+        ] {
+            assert!(uri.parse::<PaymentRequest>().is_err(), "{}", uri);
+        }
+    }
+}
@@ -0,0 +1,459 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::message::Transfer;
+
+use chrono::prelude::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use getset::Getters;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use std::{convert::TryFrom, str::FromStr, time::Duration};
+
+/// How far in the future the next run of a cron rule is searched for.
+const CRON_SEARCH_LIMIT_DAYS: i64 = 5 * 366;
+
+/// A cron-like rule with the `minute hour day-of-month month day-of-week` fields, evaluated in UTC.
+///
+/// Each field accepts `*`, values, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `0-30/10`).
+/// The day of week goes from 0 (Sunday) to 7 (also Sunday).
+/// If both the day of month and the day of week are restricted, a day matching either of them runs the rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronRule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+fn invalid_cron(expression: &str, reason: &str) -> crate::WalletError {
+    crate::WalletError::InvalidSchedule(format!("invalid cron expression `{}`: {}", expression, reason))
+}
+
+/// Parses a cron field into the list of matching values, indexed from 0 up to `max`.
+fn parse_cron_field(expression: &str, field: &str, min: usize, max: usize) -> crate::Result<Vec<bool>> {
+    let parse_value = |value: &str| -> crate::Result<usize> {
+        let value = value
+            .parse::<usize>()
+            .map_err(|_| invalid_cron(expression, &format!("`{}` isn't a number", value)))?;
+        if value < min || value > max {
+            return Err(invalid_cron(
+                expression,
+                &format!("`{}` is out of the {}-{} range", value, min, max),
+            ));
+        }
+        Ok(value)
+    };
+
+    let mut matches = vec![false; max + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(index) => {
+                let step = part[index + 1..]
+                    .parse::<usize>()
+                    .map_err(|_| invalid_cron(expression, &format!("invalid step on `{}`", part)))?;
+                if step == 0 {
+                    return Err(invalid_cron(expression, "the step can't be zero"));
+                }
+                (&part[..index], step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(index) = range.find('-') {
+            (parse_value(&range[..index])?, parse_value(&range[index + 1..])?)
+        } else {
+            let value = parse_value(range)?;
+            // `5/10` runs from 5 to the end of the range
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(invalid_cron(expression, &format!("invalid range `{}`", range)));
+        }
+        for value in (start..=end).step_by(step) {
+            matches[value] = true;
+        }
+    }
+    Ok(matches)
+}
+
+impl FromStr for CronRule {
+    type Err = crate::WalletError;
+
+    fn from_str(expression: &str) -> crate::Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(invalid_cron(expression, "expected 5 fields"));
+        }
+        let mut days_of_week = parse_cron_field(expression, fields[4], 0, 7)?;
+        // 7 is an alias for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            expression: expression.to_string(),
+            minutes: parse_cron_field(expression, fields[0], 0, 59)?,
+            hours: parse_cron_field(expression, fields[1], 0, 23)?,
+            days_of_month: parse_cron_field(expression, fields[2], 1, 31)?,
+            months: parse_cron_field(expression, fields[3], 1, 12)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+}
+
+impl TryFrom<String> for CronRule {
+    type Error = crate::WalletError;
+
+    fn try_from(expression: String) -> crate::Result<Self> {
+        expression.parse()
+    }
+}
+
+impl From<CronRule> for String {
+    fn from(rule: CronRule) -> Self {
+        rule.expression
+    }
+}
+
+impl CronRule {
+    /// The cron expression.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month[time.day() as usize];
+        let day_of_week = self.days_of_week[time.weekday().num_days_from_sunday() as usize];
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (false, true) => day_of_month,
+            (true, false) => day_of_week,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    /// Gets the first time matching the rule strictly after the given time.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + chrono::Duration::days(CRON_SEARCH_LIMIT_DAYS);
+        let mut time = Utc
+            .ymd(after.year(), after.month(), after.day())
+            .and_hms(after.hour(), after.minute(), 0)
+            + chrono::Duration::minutes(1);
+        while time <= limit {
+            if !self.months[time.month() as usize] {
+                let (year, month) = if time.month() == 12 {
+                    (time.year() + 1, 1)
+                } else {
+                    (time.year(), time.month() + 1)
+                };
+                time = Utc.ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.matches_day(&time) {
+                time = Utc.ymd(time.year(), time.month(), time.day()).and_hms(0, 0, 0) + chrono::Duration::days(1);
+            } else if !self.hours[time.hour() as usize] {
+                time = Utc
+                    .ymd(time.year(), time.month(), time.day())
+                    .and_hms(time.hour(), 0, 0)
+                    + chrono::Duration::hours(1);
+            } else if !self.minutes[time.minute() as usize] {
+                time = time.checked_add_signed(chrono::Duration::minutes(1))?;
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// When a scheduled transfer runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Schedule {
+    /// Runs once at the given time.
+    Once(DateTime<Utc>),
+    /// Runs on every interval, starting at the given time.
+    Interval {
+        /// The first run time.
+        start: DateTime<Utc>,
+        /// The time between runs, in whole seconds.
+        every: Duration,
+    },
+    /// Runs at the times matching the cron rule.
+    Cron(CronRule),
+}
+
+impl Schedule {
+    /// Gets the first run time.
+    pub(crate) fn first_run(&self, now: DateTime<Utc>) -> crate::Result<DateTime<Utc>> {
+        match self {
+            Self::Once(time) => Ok(*time),
+            Self::Interval { start, every } => {
+                if every.as_secs() == 0 {
+                    return Err(crate::WalletError::InvalidSchedule(
+                        "the interval must be at least one second".to_string(),
+                    ));
+                }
+                if every.subsec_nanos() != 0 {
+                    return Err(crate::WalletError::InvalidSchedule(
+                        "the interval must be a whole number of seconds".to_string(),
+                    ));
+                }
+                Ok(*start)
+            }
+            Self::Cron(rule) => rule.next_after(now).ok_or_else(|| {
+                crate::WalletError::InvalidSchedule(format!("the cron rule `{}` never runs", rule.expression()))
+            }),
+        }
+    }
+
+    /// Gets the run time following the run scheduled at `previous`, skipping the runs missed before `now`.
+    pub fn next_run(&self, previous: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Once(_) => None,
+            Self::Interval { every, .. } => {
+                let every_secs = every.as_secs() as i64;
+                let elapsed = (now - previous).num_seconds();
+                let runs = if elapsed < every_secs {
+                    1
+                } else {
+                    elapsed / every_secs + 1
+                };
+                previous.checked_add_signed(chrono::Duration::seconds(every_secs * runs))
+            }
+            Self::Cron(rule) => rule.next_after(std::cmp::max(previous, now)),
+        }
+    }
+}
+
+/// What a scheduled transfer does when the account doesn't have enough funds on a run.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InsufficientFundsPolicy {
+    /// Skips the run and waits for the next one.
+    Skip,
+    /// Retries the run on every poll until it succeeds.
+    Retry,
+}
+
+impl Default for InsufficientFundsPolicy {
+    fn default() -> Self {
+        Self::Retry
+    }
+}
+
+/// A transfer executed by the account manager's polling system according to its schedule.
+#[derive(Debug, Getters, Clone, PartialEq, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct ScheduledTransfer {
+    /// The scheduled transfer identifier.
+    id: String,
+    /// The transfer sent on each run.
+    transfer: Transfer,
+    /// The transfer schedule.
+    schedule: Schedule,
+    /// What to do when the account doesn't have enough funds on a run.
+    #[serde(rename = "onInsufficientFunds", default)]
+    on_insufficient_funds: InsufficientFundsPolicy,
+    /// The next run time. `None` if the schedule has no more runs.
+    #[serde(rename = "nextRun")]
+    next_run: Option<DateTime<Utc>>,
+    /// The time of the last successful run.
+    #[serde(rename = "lastRun", default)]
+    last_run: Option<DateTime<Utc>>,
+    /// The number of successful runs.
+    #[serde(rename = "runCount", default)]
+    run_count: u64,
+}
+
+impl ScheduledTransfer {
+    /// Initialises a new scheduled transfer.
+    pub fn new(transfer: Transfer, schedule: Schedule) -> Self {
+        Self {
+            id: thread_rng().gen_ascii_chars().take(16).collect(),
+            transfer,
+            schedule,
+            on_insufficient_funds: Default::default(),
+            next_run: None,
+            last_run: None,
+            run_count: 0,
+        }
+    }
+
+    /// Sets what to do when the account doesn't have enough funds on a run.
+    pub fn insufficient_funds_policy(mut self, policy: InsufficientFundsPolicy) -> Self {
+        self.on_insufficient_funds = policy;
+        self
+    }
+
+    pub(crate) fn transfer_mut(&mut self) -> &mut Transfer {
+        &mut self.transfer
+    }
+
+    /// Whether the transfer must run at the given time or not.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_run.map(|next_run| next_run <= now).unwrap_or(false)
+    }
+
+    /// The transfer sent on the next run.
+    /// Its idempotency key identifies the run so it's never sent twice.
+    pub(crate) fn next_transfer(&self) -> Transfer {
+        let run_time = self.next_run.map(|time| time.timestamp()).unwrap_or_default();
+        self.transfer
+            .clone()
+            .idempotency_key(format!("scheduled-{}-{}", self.id, run_time))
+    }
+
+    pub(crate) fn init(&mut self, now: DateTime<Utc>) -> crate::Result<()> {
+        self.next_run = Some(self.schedule.first_run(now)?);
+        Ok(())
+    }
+
+    /// Moves the schedule to the next run.
+    pub(crate) fn advance(&mut self, now: DateTime<Utc>) {
+        self.next_run = self.next_run.and_then(|previous| self.schedule.next_run(previous, now));
+    }
+
+    pub(crate) fn on_run(&mut self, now: DateTime<Utc>) {
+        self.last_run = Some(now);
+        self.run_count += 1;
+        self.advance(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CronRule, InsufficientFundsPolicy, Schedule, ScheduledTransfer};
+    use crate::{address::IotaAddress, message::Transfer};
+    use chrono::prelude::{TimeZone, Utc};
+    use iota::message::prelude::Ed25519Address;
+    use std::time::Duration;
+
+    #[test]
+    fn cron_every_fifteen_minutes() {
+        let rule: CronRule = "*/15 * * * *".parse().unwrap();
+        let next = rule.next_after(Utc.ymd(2020, 10, 1).and_hms(10, 7, 30)).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 1).and_hms(10, 15, 0));
+        let next = rule.next_after(next).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 1).and_hms(10, 30, 0));
+    }
+
+    #[test]
+    fn cron_monthly_salary() {
+        // 09:00 on the first day of every month
+        let rule: CronRule = "0 9 1 * *".parse().unwrap();
+        let next = rule.next_after(Utc.ymd(2020, 12, 1).and_hms(9, 0, 0)).unwrap();
+        assert_eq!(next, Utc.ymd(2021, 1, 1).and_hms(9, 0, 0));
+    }
+
+    #[test]
+    fn cron_weekdays() {
+        // 18:30 from Monday to Friday; 2020-10-03 is a Saturday
+        let rule: CronRule = "30 18 * * 1-5".parse().unwrap();
+        let next = rule.next_after(Utc.ymd(2020, 10, 3).and_hms(0, 0, 0)).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 5).and_hms(18, 30, 0));
+    }
+
+    #[test]
+    fn cron_sunday_alias() {
+        let rule: CronRule = "0 0 * * 7".parse().unwrap();
+        let next = rule.next_after(Utc.ymd(2020, 10, 1).and_hms(0, 0, 0)).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 4).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn cron_day_of_month_or_day_of_week() {
+        // the 15th or any Monday
+        let rule: CronRule = "0 0 15 * 1".parse().unwrap();
+        let next = rule.next_after(Utc.ymd(2020, 10, 6).and_hms(0, 0, 0)).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 12).and_hms(0, 0, 0));
+        let next = rule.next_after(next).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 15).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn cron_never_matching() {
+        let rule: CronRule = "0 0 31 2 *".parse().unwrap();
+        assert!(rule.next_after(Utc.ymd(2020, 1, 1).and_hms(0, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn cron_invalid_expressions() {
+        for expression in &[
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(expression.parse::<CronRule>().is_err(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn cron_serde() {
+        let rule: CronRule = "0 9 1,15 * *".parse().unwrap();
+        let serialized = serde_json::to_string(&rule).unwrap();
+        assert_eq!(serialized, "\"0 9 1,15 * *\"");
+        assert_eq!(serde_json::from_str::<CronRule>(&serialized).unwrap(), rule);
+    }
+
+    #[test]
+    fn interval_skips_missed_runs() {
+        let start = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
+        let schedule = Schedule::Interval {
+            start,
+            every: Duration::from_secs(3600),
+        };
+        let next = schedule
+            .next_run(start, Utc.ymd(2020, 10, 1).and_hms(5, 30, 0))
+            .unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 1).and_hms(6, 0, 0));
+        let next = schedule.next_run(start, start).unwrap();
+        assert_eq!(next, Utc.ymd(2020, 10, 1).and_hms(1, 0, 0));
+    }
+
+    #[test]
+    fn interval_must_be_whole_seconds() {
+        let start = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
+        for every in &[Duration::from_millis(500), Duration::from_millis(1500)] {
+            let schedule = Schedule::Interval { start, every: *every };
+            assert!(matches!(
+                schedule.first_run(start),
+                Err(crate::WalletError::InvalidSchedule(_))
+            ));
+        }
+        let schedule = Schedule::Interval {
+            start,
+            every: Duration::from_secs(60),
+        };
+        assert_eq!(schedule.first_run(start).unwrap(), start);
+    }
+
+    #[test]
+    fn once_has_no_next_run() {
+        let time = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
+        assert!(Schedule::Once(time).next_run(time, time).is_none());
+    }
+
+    #[test]
+    fn scheduled_transfer_serde() {
+        let transfer = Transfer::new(IotaAddress::Ed25519(Ed25519Address::new([1; 32])), 100)
+            .source_addresses(vec![IotaAddress::Ed25519(Ed25519Address::new([2; 32]))]);
+        let mut scheduled_transfer = ScheduledTransfer::new(transfer, Schedule::Cron("0 9 1 * *".parse().unwrap()))
+            .insufficient_funds_policy(InsufficientFundsPolicy::Skip);
+        scheduled_transfer.init(Utc::now()).unwrap();
+
+        let serialized = serde_json::to_string(&scheduled_transfer).unwrap();
+        let deserialized: ScheduledTransfer = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, scheduled_transfer);
+    }
+}
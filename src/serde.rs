@@ -3,15 +3,16 @@
 
 use serde::{ser::SerializeStruct, Serializer};
 
+/// Addresses whose network isn't known, e.g. the account's own addresses on its settings.
+/// They're serialized with the mainnet bech32 HRP and any valid HRP is accepted when deserializing;
+/// recipient addresses keep their HRP instead (see `TransferOutput`).
 pub(crate) mod iota_address_serde {
-    use crate::address::IotaAddress;
-    use bech32::FromBase32;
-    use iota::message::prelude::Ed25519Address;
+    use crate::address::{parse_bech32, to_bech32, IotaAddress, MAINNET_BECH32_HRP};
     use serde::{de::Visitor, Deserializer, Serializer};
-    use std::convert::TryInto;
 
     pub fn serialize<S: Serializer>(address: &IotaAddress, s: S) -> std::result::Result<S::Ok, S::Error> {
-        s.serialize_str(&address.to_bech32())
+        let address = to_bech32(address, MAINNET_BECH32_HRP).map_err(serde::ser::Error::custom)?;
+        s.serialize_str(&address)
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<IotaAddress, D::Error>
@@ -29,21 +30,9 @@ pub(crate) mod iota_address_serde {
             where
                 E: serde::de::Error,
             {
-                let decoded = Vec::from_base32(
-                    &bech32::decode(v)
-                        .map_err(|e| serde::de::Error::custom(e.to_string()))?
-                        .1,
-                )
-                .map_err(|e| serde::de::Error::custom(e.to_string()))?;
-                let address_type = decoded[0];
-                match address_type {
-                    1 => Ok(IotaAddress::Ed25519(Ed25519Address::new(
-                        decoded[1..]
-                            .try_into()
-                            .map_err(|_| serde::de::Error::custom("invalid address length"))?,
-                    ))),
-                    _ => Err(serde::de::Error::custom("invalid address type")),
-                }
+                parse_bech32(v)
+                    .map(|(address, _)| address)
+                    .map_err(|e| serde::de::Error::custom(e.to_string()))
             }
         }
 
@@ -51,6 +40,105 @@ pub(crate) mod iota_address_serde {
     }
 }
 
+pub(crate) mod iota_address_vec_serde {
+    use crate::address::IotaAddress;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct AddressDto(#[serde(with = "super::iota_address_serde")] IotaAddress);
+
+    pub fn serialize<S: Serializer>(addresses: &[IotaAddress], s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_seq(addresses.iter().map(|address| AddressDto(address.clone())))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<IotaAddress>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let addresses = Vec::<AddressDto>::deserialize(deserializer)?;
+        Ok(addresses.into_iter().map(|address| address.0).collect())
+    }
+}
+
+/// Optional mnemonics, kept in a container that is wiped from memory when dropped.
+pub(crate) mod mnemonic_serde {
+    use crate::signing::Mnemonic;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mnemonic: &Option<Mnemonic>, s: S) -> std::result::Result<S::Ok, S::Error> {
+        match mnemonic {
+            Some(mnemonic) => s.serialize_some(mnemonic.as_str()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Mnemonic>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<String>::deserialize(deserializer)?.map(Mnemonic::new))
+    }
+}
+
+/// Amounts of iotas, deserialized from either a number or a string like `1.5 Mi`.
+pub(crate) mod amount_serde {
+    use serde::{de::Visitor, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &u64, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.serialize_u64(*amount)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AmountVisitor;
+        impl<'de> Visitor<'de> for AmountVisitor {
+            type Value = u64;
+            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str("an amount of iotas as number or a string with an optional unit")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(v)
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v < 0 {
+                    return Err(serde::de::Error::custom("the amount can't be negative"));
+                }
+                Ok(v as u64)
+            }
+
+            // javascript numbers are floats
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v < 0.0 || v.fract() != 0.0 || v >= u64::MAX as f64 {
+                    return Err(serde::de::Error::custom("the amount must be a positive integer"));
+                }
+                Ok(v as u64)
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                crate::message::parse_amount(v).map_err(|e| serde::de::Error::custom(e.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
 pub(crate) mod message_id_serde {
     use iota::message::prelude::MessageId;
     use serde::{
@@ -120,12 +208,40 @@ impl serde::Serialize for crate::WalletError {
             Self::MessageAlreadyConfirmed => serialize_variant(serializer, "MessageAlreadyConfirmed", None),
             Self::MessageNotFound => serialize_variant(serializer, "MessageNotFound", None),
             Self::EmptyNodeList => serialize_variant(serializer, "EmptyNodeList", None),
+            Self::NetworkMismatch(message) => serialize_variant(serializer, "NetworkMismatch", Some(message)),
+            Self::NetworkIdUnavailable => serialize_variant(serializer, "NetworkIdUnavailable", None),
+            Self::InvalidTransaction(error) => {
+                serialize_variant(serializer, "InvalidTransaction", Some(&error.to_string()))
+            }
+            Self::SpendingPolicyViolation(error) => {
+                serialize_variant(serializer, "SpendingPolicyViolation", Some(&error.to_string()))
+            }
+            Self::InvalidStrongholdPassword => serialize_variant(serializer, "InvalidStrongholdPassword", None),
+            Self::StrongholdSnapshotNotFound => serialize_variant(serializer, "StrongholdSnapshotNotFound", None),
+            Self::PowTimeout(milliseconds) => serialize_variant(
+                serializer,
+                "PowTimeout",
+                Some(&format!(
+                    "the proof-of-work didn't finish in {} milliseconds",
+                    milliseconds
+                )),
+            ),
             Self::InvalidAddressLength => serialize_variant(serializer, "InvalidAddressLength", None),
             Self::InvalidTransactionIdLength => {
                 serializer.serialize_newtype_variant("WalletError", 14, "InvalidTransactionIdLength", "")
             }
             Self::InvalidMessageIdLength => serialize_variant(serializer, "InvalidMessageIdLength", None),
             Self::Bech32Error(error) => serialize_variant(serializer, "Bech32Error", Some(&error.to_string())),
+            Self::Bech32HrpMismatch { expected, found } => serialize_variant(
+                serializer,
+                "Bech32HrpMismatch",
+                Some(&format!("expected `{}` address prefix, found `{}`", expected, found)),
+            ),
+            Self::InvalidBech32Hrp(bech32_hrp) => serialize_variant(
+                serializer,
+                "InvalidBech32Hrp",
+                Some(&format!("invalid bech32 human-readable part `{}`", bech32_hrp)),
+            ),
             Self::AccountAlreadyImported { alias } => serialize_variant(
                 serializer,
                 "AccountAlreadyImported",
@@ -138,6 +254,59 @@ impl serde::Serialize for crate::WalletError {
             Self::ZeroAmount => serialize_variant(serializer, "ZeroAmount", None),
             Self::AccountNotFound => serialize_variant(serializer, "AccountNotFound", None),
             Self::InvalidRemainderValueAddress => serialize_variant(serializer, "InvalidRemainderValueAddress", None),
+            Self::PassphraseNotSet => serialize_variant(serializer, "PassphraseNotSet", None),
+            Self::SeedNotLoaded => serialize_variant(serializer, "SeedNotLoaded", Some(&self.to_string())),
+            Self::NoSingleInputAddress => serialize_variant(serializer, "NoSingleInputAddress", None),
+            Self::TooManyInputs { count, max } => serialize_variant(
+                serializer,
+                "TooManyInputs",
+                Some(&format!("{} inputs, the maximum is {}", count, max)),
+            ),
+            Self::TooManyOutputs { count, max } => serialize_variant(
+                serializer,
+                "TooManyOutputs",
+                Some(&format!("{} outputs, the maximum is {}", count, max)),
+            ),
+            Self::PartialTransfer { messages, error } => serialize_variant(
+                serializer,
+                "PartialTransfer",
+                Some(&format!(
+                    "{}; posted messages: {}",
+                    error,
+                    messages
+                        .iter()
+                        .map(|message| message.id().to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )),
+            ),
+            Self::TransferInProgress(key) => serialize_variant(serializer, "TransferInProgress", Some(key)),
+            Self::InvalidSchedule(message) => serialize_variant(serializer, "InvalidSchedule", Some(message)),
+            Self::InvalidPaymentRequest(message) => {
+                serialize_variant(serializer, "InvalidPaymentRequest", Some(message))
+            }
+            Self::ContactNotFound(name) => serialize_variant(serializer, "ContactNotFound", Some(name)),
+            Self::ContactAlreadyExists(name) => serialize_variant(serializer, "ContactAlreadyExists", Some(name)),
+            Self::InvalidContact(message) => serialize_variant(serializer, "InvalidContact", Some(message)),
+            Self::InvalidAmount(message) => serialize_variant(serializer, "InvalidAmount", Some(message)),
+            Self::AmountOverflow(amount) => serialize_variant(serializer, "AmountOverflow", Some(amount)),
+            Self::DustRemainder(remainder) => {
+                serialize_variant(serializer, "DustRemainder", Some(&remainder.to_string()))
+            }
+            Self::InputNotAvailable(input) => serialize_variant(
+                serializer,
+                "InputNotAvailable",
+                Some(&format!("{}:{}", input.transaction_id(), input.index())),
+            ),
+            Self::SignerMigrationAddressMismatch { index, internal } => serialize_variant(
+                serializer,
+                "SignerMigrationAddressMismatch",
+                Some(&format!(
+                    "{} address with index {} doesn't match",
+                    if *internal { "internal" } else { "public" },
+                    index
+                )),
+            ),
         }
     }
 }
@@ -0,0 +1,417 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::Mnemonic;
+use crate::account::Account;
+
+use std::{collections::HashMap, env, ops::Range, sync::RwLock};
+
+use bee_common::packable::Packable;
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use hmac::Hmac;
+use iota::{Ed25519Address, Ed25519Signature, ReferenceUnlock, SignatureUnlock, UnlockBlock};
+use rand::{thread_rng, Rng};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+use bee_signing_ext::{
+    binary::{ed25519, BIP32Path},
+    Signer,
+};
+
+const PBKDF2_ROUNDS: usize = 2048;
+const PBKDF2_BYTES: usize = 32; // 64 for secp256k1 , 32 for ed25
+
+/// The environment variable holding the mnemonic read by [`SeedSigner::from_env`](struct.SeedSigner.html#method.from_env).
+pub const MNEMONIC_ENV_VAR: &str = "IOTA_WALLET_MNEMONIC";
+/// The environment variable holding the passphrase read by [`SeedSigner::from_env`](struct.SeedSigner.html#method.from_env).
+pub const MNEMONIC_PASSPHRASE_ENV_VAR: &str = "IOTA_WALLET_MNEMONIC_PASSWORD";
+
+type Seed = Zeroizing<Vec<u8>>;
+
+/// PBKDF2 helper, used to generate [`Seed`][Seed] from [`Mnemonic`][Mnemonic]
+///
+/// [Mnemonic]: ../mnemonic/struct.Mnemonic.html
+/// [Seed]: ../seed/struct.Seed.html
+fn _pbkdf2(input: &[u8], salt: &str) -> Seed {
+    let mut seed = Zeroizing::new(vec![0u8; PBKDF2_BYTES]);
+    pbkdf2::pbkdf2::<Hmac<sha2::Sha512>>(input, salt.as_bytes(), PBKDF2_ROUNDS, &mut seed);
+    seed
+}
+
+fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> Seed {
+    let salt = Zeroizing::new(format!("mnemonic{}", passphrase));
+    let normalized_salt = Zeroizing::new(salt.nfkd().to_string());
+    _pbkdf2(mnemonic.as_bytes(), &normalized_salt)
+}
+
+fn derive_into_address(private_key: ed25519::Ed25519PrivateKey) -> iota::Address {
+    let public_key = private_key.generate_public_key().to_bytes();
+    // Hash the public key to get the address
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(public_key);
+    let mut result = [0u8; 32];
+    hasher.finalize_variable(|res| {
+        result.copy_from_slice(res);
+    });
+    iota::Address::Ed25519(Ed25519Address::new(result))
+}
+
+/// A signer deriving the account keys from a BIP39 mnemonic.
+///
+/// The mnemonic is only used to derive the seed and is never stored.
+/// The passphrase and the seeds are kept in containers that are wiped from memory when dropped.
+/// A seed is only available while the signer instance is alive, so after a restart the mnemonic
+/// must be provided again through [load_mnemonic](#method.load_mnemonic).
+///
+/// The signer registered by default for `SignerType::Mnemonic` has no passphrase and no seeds: the application
+/// registers its own with [set_signer](fn.set_signer.html), built with [new](#method.new) or, opting in to the
+/// environment variables, with [from_env](#method.from_env). Until then, using an account fails with `SeedNotLoaded`.
+///
+/// # Accounts of the former environment mnemonic signer
+///
+/// The former signer read `IOTA_WALLET_MNEMONIC` on its own and derived the seed with the `password` passphrase when
+/// `IOTA_WALLET_MNEMONIC_PASSWORD` wasn't set. Those accounts are moved to a signer holding their mnemonic with
+/// [migrate_signer](../account_manager/struct.AccountManager.html#method.migrate_signer), passing the mnemonic as
+/// `MnemonicSource::Mnemonic` and a target signer deriving the same addresses, e.g. a `SignerType::Custom` signer
+/// created with `SeedSigner::new` and the passphrase the account was created with. The migration re-derives every
+/// stored address and leaves the account untouched if any of them doesn't match.
+#[derive(Default)]
+pub struct SeedSigner {
+    passphrase: Option<Zeroizing<String>>,
+    seeds: RwLock<HashMap<String, Seed>>,
+    fallback_seed: Option<Seed>,
+}
+
+impl SeedSigner {
+    /// Initialises a new seed signer with the passphrase used to derive the seeds.
+    pub fn new<P: Into<String>>(passphrase: P) -> Self {
+        Self {
+            passphrase: Some(Zeroizing::new(passphrase.into())),
+            ..Default::default()
+        }
+    }
+
+    /// Opt-in loader reading the mnemonic and the passphrase from the `IOTA_WALLET_MNEMONIC`
+    /// and `IOTA_WALLET_MNEMONIC_PASSWORD` environment variables.
+    /// The mnemonic is used by every account that doesn't have its own seed loaded.
+    /// Fails with `PassphraseNotSet` if the passphrase variable isn't set; there's no default passphrase.
+    pub fn from_env() -> crate::Result<Self> {
+        let passphrase =
+            Zeroizing::new(env::var(MNEMONIC_PASSPHRASE_ENV_VAR).map_err(|_| crate::WalletError::PassphraseNotSet)?);
+        let mnemonic = Zeroizing::new(
+            env::var(MNEMONIC_ENV_VAR).map_err(|_| anyhow::anyhow!("{} must be set", MNEMONIC_ENV_VAR))?,
+        );
+        let fallback_seed = mnemonic_to_seed(&mnemonic, &passphrase);
+        Ok(Self {
+            passphrase: Some(passphrase),
+            fallback_seed: Some(fallback_seed),
+            ..Default::default()
+        })
+    }
+
+    /// Loads the mnemonic of an existing account, e.g. after the wallet has been restarted.
+    pub fn load_mnemonic(&self, account_id: &str, mnemonic: Mnemonic) -> crate::Result<()> {
+        self.load_seed(account_id, &mnemonic)
+    }
+
+    fn load_seed(&self, account_id: &str, mnemonic: &Mnemonic) -> crate::Result<()> {
+        let seed = mnemonic_to_seed(mnemonic, self.passphrase()?);
+        self.seeds.write().unwrap().insert(account_id.to_string(), seed);
+        Ok(())
+    }
+
+    /// Removes the seed of the given account from memory.
+    pub fn unload(&self, account_id: &str) {
+        self.seeds.write().unwrap().remove(account_id);
+    }
+
+    fn passphrase(&self) -> crate::Result<&str> {
+        self.passphrase
+            .as_ref()
+            .map(|passphrase| passphrase.as_str())
+            .ok_or(crate::WalletError::PassphraseNotSet)
+    }
+
+    fn with_seed<T, F: FnOnce(&ed25519::Ed25519Seed) -> crate::Result<T>>(
+        &self,
+        account: &Account,
+        cb: F,
+    ) -> crate::Result<T> {
+        let seeds = self.seeds.read().unwrap();
+        let seed_bytes = seeds
+            .get(account.id())
+            .or_else(|| self.fallback_seed.as_ref())
+            .ok_or(crate::WalletError::SeedNotLoaded)?;
+        let seed = ed25519::Ed25519Seed::from_bytes(&seed_bytes).map_err(|_| anyhow::anyhow!("invalid seed length"))?;
+        cb(&seed)
+    }
+
+    fn get_private_key(
+        &self,
+        seed: &ed25519::Ed25519Seed,
+        derivation_path: String,
+    ) -> crate::Result<ed25519::Ed25519PrivateKey> {
+        Ok(ed25519::Ed25519PrivateKey::generate_from_seed(
+            seed,
+            &BIP32Path::from_str(&derivation_path).map_err(|e| anyhow::anyhow!(e.to_string()))?,
+        )
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?)
+    }
+
+    fn derive_address(
+        &self,
+        seed: &ed25519::Ed25519Seed,
+        account: &Account,
+        address_index: usize,
+        internal: bool,
+    ) -> crate::Result<iota::Address> {
+        let private_key = self.get_private_key(
+            seed,
+            format!(
+                "m/44H/4218H/{}H/{}H/{}H",
+                account.index(),
+                internal as u32,
+                address_index
+            ),
+        )?;
+        Ok(derive_into_address(private_key))
+    }
+}
+
+impl super::Signer for SeedSigner {
+    fn init_account(&self, account: &Account, mnemonic: Option<Mnemonic>) -> crate::Result<String> {
+        let id: String = thread_rng().gen_ascii_chars().take(10).collect();
+        match mnemonic {
+            Some(mnemonic) => self.load_seed(&id, &mnemonic)?,
+            None => {
+                if self.fallback_seed.is_none() {
+                    return Err(crate::WalletError::SeedNotLoaded);
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    fn remove_account(&self, account: &Account) -> crate::Result<()> {
+        self.unload(account.id());
+        Ok(())
+    }
+
+    fn generate_address(
+        &self,
+        account: &Account,
+        address_index: usize,
+        internal: bool,
+    ) -> crate::Result<iota::Address> {
+        self.with_seed(account, |seed| {
+            self.derive_address(seed, account, address_index, internal)
+        })
+    }
+
+    fn generate_addresses(
+        &self,
+        account: &Account,
+        range: Range<usize>,
+        internal: bool,
+    ) -> crate::Result<Vec<iota::Address>> {
+        self.with_seed(account, |seed| {
+            range
+                .map(|address_index| self.derive_address(seed, account, address_index, internal))
+                .collect()
+        })
+    }
+
+    fn sign_message(
+        &self,
+        account: &Account,
+        essence: &iota::TransactionEssence,
+        inputs: &mut Vec<super::TransactionInput>,
+    ) -> crate::Result<Vec<iota::UnlockBlock>> {
+        let serialized_essence = essence.pack_new();
+
+        self.with_seed(account, |seed| {
+            let mut unlock_blocks = vec![];
+            // keyed by the derivation path, since public and internal addresses share their indexes
+            let mut signature_indexes = HashMap::<String, usize>::new();
+            inputs.sort_by(|a, b| a.input.cmp(&b.input));
+
+            for recorder in inputs.iter() {
+                // Check if current path is same as previous path
+                // If so, add a reference unlock block
+                let address_path = recorder.address_path.to_string();
+                if let Some(block_index) = signature_indexes.get(&address_path) {
+                    unlock_blocks.push(UnlockBlock::Reference(
+                        ReferenceUnlock::new(*block_index as u16)
+                            .map_err(|_| anyhow::anyhow!("failed to create reference unlock block"))?,
+                    ));
+                } else {
+                    // If not, we should create a signature unlock block
+                    let private_key = ed25519::Ed25519PrivateKey::generate_from_seed(seed, &recorder.address_path)
+                        .map_err(|_| anyhow::anyhow!("invalid parameter: seed inputs"))?;
+                    let public_key = private_key.generate_public_key().to_bytes();
+                    // The block should sign the entire transaction essence part of the transaction payload
+                    let signature = Box::new(private_key.sign(&serialized_essence).to_bytes());
+                    signature_indexes.insert(address_path, unlock_blocks.len());
+                    unlock_blocks.push(UnlockBlock::Signature(SignatureUnlock::Ed25519(Ed25519Signature::new(
+                        public_key, signature,
+                    ))));
+                }
+            }
+            Ok(unlock_blocks)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mnemonic_to_seed, SeedSigner, MNEMONIC_ENV_VAR, MNEMONIC_PASSPHRASE_ENV_VAR};
+    use crate::{
+        address::OutputId,
+        client::ClientOptionsBuilder,
+        signing::{set_signer, with_signer, Mnemonic, Signer, SignerType, TransactionInput},
+        test_utils::MNEMONIC,
+        validation::{validate_transaction, KnownOutput},
+    };
+    use bee_signing_ext::binary::BIP32Path;
+    use iota::message::prelude::{
+        Input, SignatureLockedSingleOutput, Transaction, TransactionEssence, TransactionId, UTXOInput, UnlockBlock,
+    };
+    use rusty_fork::rusty_fork_test;
+    use std::{num::NonZeroU64, str::FromStr};
+
+    rusty_fork_test! {
+        #[test]
+        fn env_mnemonic() {
+            std::env::set_var(MNEMONIC_ENV_VAR, MNEMONIC);
+            std::env::remove_var(MNEMONIC_PASSPHRASE_ENV_VAR);
+            // there's no default passphrase
+            assert!(matches!(SeedSigner::from_env(), Err(crate::WalletError::PassphraseNotSet)));
+
+            std::env::set_var(MNEMONIC_PASSPHRASE_ENV_VAR, "passphrase");
+            let signer = SeedSigner::from_env().unwrap();
+            assert_eq!(
+                **signer.fallback_seed.as_ref().unwrap(),
+                *mnemonic_to_seed(MNEMONIC, "passphrase")
+            );
+
+            // the default signer doesn't read the environment
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let response = crate::test_utils::get_account_manager()
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .skip_persistance()
+                .initialise();
+            assert!(matches!(response, Err(crate::WalletError::SeedNotLoaded)));
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn seed_lifecycle() {
+            let manager = crate::test_utils::get_account_manager();
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic(MNEMONIC)
+                .skip_persistance()
+                .initialise()
+                .expect("failed to add account");
+            let address = with_signer(&SignerType::Mnemonic, |signer| signer.generate_address(&account, 0, false))
+                .expect("failed to generate address");
+
+            // a restart drops the seed
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+            let response = with_signer(&SignerType::Mnemonic, |signer| signer.generate_address(&account, 0, false));
+            match response {
+                Err(e @ crate::WalletError::SeedNotLoaded) => assert!(e.to_string().contains("load_mnemonic")),
+                _ => panic!("expected the seed not to be loaded"),
+            }
+
+            let signer = SeedSigner::new("passphrase");
+            signer.load_mnemonic(account.id(), Mnemonic::new(MNEMONIC.to_string())).unwrap();
+            set_signer(SignerType::Mnemonic, signer);
+            let reloaded_address = with_signer(&SignerType::Mnemonic, |signer| signer.generate_address(&account, 0, false))
+                .expect("failed to generate address");
+            assert_eq!(reloaded_address, address);
+        }
+    }
+
+    rusty_fork_test! {
+        #[test]
+        fn public_and_internal_inputs_with_the_same_index() {
+            let manager = crate::test_utils::get_account_manager();
+            let signer = SeedSigner::new("passphrase");
+            set_signer(SignerType::Mnemonic, SeedSigner::new("passphrase"));
+
+            let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+                .expect("invalid node URL")
+                .build();
+            let account = manager
+                .create_account(client_options)
+                .signer_type(SignerType::Mnemonic)
+                .mnemonic(MNEMONIC)
+                .skip_persistance()
+                .initialise()
+                .expect("failed to add account");
+            signer.load_mnemonic(account.id(), Mnemonic::new(MNEMONIC.to_string())).unwrap();
+            let public_address = signer.generate_address(&account, 0, false).unwrap();
+            let internal_address = signer.generate_address(&account, 0, true).unwrap();
+
+            // the first and the last inputs are on the public address, the second on the change address
+            let path = |internal: u32| {
+                BIP32Path::from_str(&format!("m/44H/4218H/{}H/{}H/0H", account.index(), internal)).unwrap()
+            };
+            let mut known_outputs = vec![];
+            let mut inputs = vec![];
+            let mut essence_builder = TransactionEssence::builder();
+            let input_addresses = vec![(&public_address, 0), (&internal_address, 1), (&public_address, 0)];
+            for (index, (address, internal)) in input_addresses.into_iter().enumerate() {
+                let output_id = OutputId::new(TransactionId::new([1; 32]), index as u16);
+                let input: Input = UTXOInput::new(*output_id.transaction_id(), *output_id.index()).unwrap().into();
+                essence_builder = essence_builder.add_input(input.clone());
+                inputs.push(TransactionInput {
+                    input,
+                    address_index: 0,
+                    address_path: path(internal),
+                });
+                known_outputs.push(KnownOutput::new(output_id, address.clone(), 1_000_000, false));
+            }
+            let output = SignatureLockedSingleOutput::new(public_address.clone(), NonZeroU64::new(3_000_000).unwrap());
+            let essence = essence_builder.add_output(output.into()).finish().unwrap();
+
+            let unlock_blocks = signer.sign_message(&account, &essence, &mut inputs).unwrap();
+            assert!(matches!(unlock_blocks[1], UnlockBlock::Signature(_)));
+            assert!(matches!(unlock_blocks[2], UnlockBlock::Reference(ref reference) if reference.index() == 0));
+
+            let mut transaction_builder = Transaction::builder().with_essence(essence);
+            for unlock_block in unlock_blocks {
+                transaction_builder = transaction_builder.add_unlock_block(unlock_block);
+            }
+            let transaction = transaction_builder.finish().unwrap();
+            validate_transaction(&transaction, &known_outputs).expect("invalid unlock blocks");
+        }
+    }
+
+    #[test]
+    fn seed_depends_on_passphrase() {
+        assert_eq!(
+            *mnemonic_to_seed(MNEMONIC, "passphrase"),
+            *mnemonic_to_seed(MNEMONIC, "passphrase")
+        );
+        assert_ne!(
+            *mnemonic_to_seed(MNEMONIC, "passphrase"),
+            *mnemonic_to_seed(MNEMONIC, "")
+        );
+    }
+}
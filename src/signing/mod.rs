@@ -3,6 +3,7 @@
 
 use std::{
     collections::HashMap,
+    ops::Range,
     sync::{Arc, RwLock},
 };
 
@@ -14,8 +15,12 @@ use slip10::BIP32Path;
 
 mod stronghold;
 use self::stronghold::StrongholdSigner;
-mod env_mnemonic;
-use env_mnemonic::EnvMnemonicSigner;
+mod mnemonic;
+pub use mnemonic::{SeedSigner, MNEMONIC_ENV_VAR, MNEMONIC_PASSPHRASE_ENV_VAR};
+mod remote;
+pub use remote::{
+    serve as serve_remote_signer, RemoteSigner, RemoteSignerEndpoint, RemoteSignerListener, REMOTE_SIGNER_TOKEN_ENV_VAR,
+};
 
 type BoxedSigner = Box<dyn Signer + Sync + Send>;
 type Signers = Arc<RwLock<HashMap<SignerType, BoxedSigner>>>;
@@ -28,12 +33,16 @@ pub enum SignerType {
     /// Stronghold signer.
     #[cfg(feature = "stronghold")]
     Stronghold,
-    /// Mnemonic through environment variable.
-    EnvMnemonic,
+    /// BIP39 mnemonic signer.
+    #[serde(alias = "EnvMnemonic")]
+    Mnemonic,
     /// Custom signer with its identifier.
     Custom(String),
 }
 
+/// A BIP39 mnemonic, wiped from memory when dropped.
+pub type Mnemonic = zeroize::Zeroizing<String>;
+
 /// One of the transaction inputs and its address information needed for signing it.
 pub struct TransactionInput {
     /// The input.
@@ -47,9 +56,26 @@ pub struct TransactionInput {
 /// Signer interface.
 pub trait Signer {
     /// Initialises an account.
-    fn init_account(&self, account: &Account, mnemonic: Option<String>) -> crate::Result<String>;
+    fn init_account(&self, account: &Account, mnemonic: Option<Mnemonic>) -> crate::Result<String>;
     /// Generates an address.
     fn generate_address(&self, account: &Account, index: usize, internal: bool) -> crate::Result<iota::Address>;
+    /// Generates the addresses on the given index range.
+    /// Signers should override it when deriving a batch of addresses is cheaper than deriving them one by one.
+    fn generate_addresses(
+        &self,
+        account: &Account,
+        range: Range<usize>,
+        internal: bool,
+    ) -> crate::Result<Vec<iota::Address>> {
+        range
+            .map(|index| self.generate_address(account, index, internal))
+            .collect()
+    }
+    /// Removes the account secrets from the signer, e.g. after the account was migrated to another signer.
+    /// Signers that don't keep secrets per account don't need to override it.
+    fn remove_account(&self, _account: &Account) -> crate::Result<()> {
+        Ok(())
+    }
     /// Signs message.
     fn sign_message(
         &self,
@@ -70,9 +96,10 @@ fn default_signers() -> Signers {
         );
     }
 
+    // the mnemonic signer has neither a passphrase nor seeds until the application registers a configured one
     signers.insert(
-        SignerType::EnvMnemonic,
-        Box::new(EnvMnemonicSigner::default()) as Box<dyn Signer + Sync + Send>,
+        SignerType::Mnemonic,
+        Box::new(SeedSigner::default()) as Box<dyn Signer + Sync + Send>,
     );
 
     Arc::new(RwLock::new(signers))
@@ -0,0 +1,470 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::Mnemonic;
+use crate::account::Account;
+
+use bee_common::packable::Packable;
+use serde::{Deserialize, Serialize};
+use slip10::BIP32Path;
+
+#[cfg(unix)]
+use std::os::unix::{
+    fs::PermissionsExt,
+    net::{UnixListener, UnixStream},
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    ops::Range,
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+/// The environment variable holding the token shared by the remote signer server and its clients.
+pub const REMOTE_SIGNER_TOKEN_ENV_VAR: &str = "IOTA_WALLET_REMOTE_SIGNER_TOKEN";
+/// The maximum length of a request or response line, including the serialized account.
+const MAX_LINE_LENGTH: usize = 4 * 1024 * 1024;
+/// The maximum number of connections the server handles at the same time.
+const MAX_WORKERS: usize = 8;
+/// The time the server waits for a client to send or receive a line.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The endpoint of a remote signer.
+#[derive(Debug, Clone)]
+pub enum RemoteSignerEndpoint {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// A Unix domain socket path.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// A transaction input sent to the remote signer.
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteTransactionInput {
+    /// The packed input as hex string.
+    input: String,
+    /// Input's address index.
+    #[serde(rename = "addressIndex")]
+    address_index: usize,
+    /// Input's address BIP32 derivation path.
+    #[serde(rename = "addressPath")]
+    address_path: String,
+}
+
+/// The requests sent to the remote signer.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", content = "payload")]
+enum SignerRequest {
+    /// Initialises an account.
+    InitAccount {
+        account: Account,
+        #[serde(with = "crate::serde::mnemonic_serde")]
+        mnemonic: Option<Mnemonic>,
+    },
+    /// Generates an address.
+    GenerateAddress {
+        account: Account,
+        #[serde(rename = "addressIndex")]
+        address_index: usize,
+        internal: bool,
+    },
+    /// Generates the addresses on the `start..end` index range.
+    GenerateAddresses {
+        account: Account,
+        start: usize,
+        end: usize,
+        internal: bool,
+    },
+    /// Signs a transaction essence.
+    SignMessage {
+        account: Account,
+        /// The packed essence as hex string.
+        essence: String,
+        inputs: Vec<RemoteTransactionInput>,
+    },
+}
+
+/// The responses sent by the remote signer.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload")]
+enum SignerResponse {
+    /// InitAccount response.
+    AccountInitialised(String),
+    /// GenerateAddress response (bech32 encoded address).
+    GeneratedAddress(String),
+    /// GenerateAddresses response (bech32 encoded addresses).
+    GeneratedAddresses(Vec<String>),
+    /// SignMessage response (packed unlock blocks as hex strings).
+    SignedMessage(Vec<String>),
+    /// An error occurred.
+    Error(String),
+}
+
+/// Gets the account sent on a request; the remote signer doesn't need its cache of derived addresses.
+fn request_account(account: &Account) -> Account {
+    let mut account = account.clone();
+    account.address_cache_mut().clear();
+    account
+}
+
+fn unexpected_response(response: SignerResponse) -> crate::WalletError {
+    match response {
+        SignerResponse::Error(error) => crate::WalletError::UnexpectedResponse(error),
+        _ => crate::WalletError::UnexpectedResponse("unexpected remote signer response".to_string()),
+    }
+}
+
+/// A signer that forwards the signing operations to a remote process through a local socket.
+///
+/// The protocol is line based: the client sends the token shared with the server, then the request, and the server
+/// answers with the response; requests and responses are JSON objects followed by a newline.
+/// Use it with `signing::set_signer(SignerType::Custom("remote".to_string()), RemoteSigner::new(endpoint, token))`.
+pub struct RemoteSigner {
+    endpoint: RemoteSignerEndpoint,
+    token: Zeroizing<String>,
+    timeout: Option<Duration>,
+}
+
+impl RemoteSigner {
+    /// Initialises a new remote signer connected to the given endpoint, authenticated with the server token.
+    pub fn new<T: Into<String>>(endpoint: RemoteSignerEndpoint, token: T) -> Self {
+        Self {
+            endpoint,
+            token: Zeroizing::new(token.into()),
+            timeout: None,
+        }
+    }
+
+    /// Sets the read and write timeout of the remote requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn send(&self, request: SignerRequest) -> crate::Result<SignerResponse> {
+        let request = Zeroizing::new(serde_json::to_string(&request)?);
+        match &self.endpoint {
+            RemoteSignerEndpoint::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_read_timeout(self.timeout)?;
+                stream.set_write_timeout(self.timeout)?;
+                exchange(stream, &self.token, &request)
+            }
+            #[cfg(unix)]
+            RemoteSignerEndpoint::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(self.timeout)?;
+                stream.set_write_timeout(self.timeout)?;
+                exchange(stream, &self.token, &request)
+            }
+        }
+    }
+}
+
+fn exchange<S: Read + Write>(mut stream: S, token: &str, request: &str) -> crate::Result<SignerResponse> {
+    for line in &[token, request] {
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\n")?;
+    }
+    stream.flush()?;
+
+    let response = read_line(&mut BufReader::new(stream))?;
+    let response = serde_json::from_str(&response)?;
+    Ok(response)
+}
+
+/// Reads a line, failing if it's longer than `MAX_LINE_LENGTH` bytes.
+fn read_line<R: BufRead>(reader: &mut R) -> crate::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_LENGTH as u64 + 1).read_line(&mut line)?;
+    if line.len() > MAX_LINE_LENGTH {
+        return Err(anyhow::anyhow!("the remote signer line exceeds {} bytes", MAX_LINE_LENGTH).into());
+    }
+    Ok(line)
+}
+
+/// Compares the tokens in constant time.
+fn token_matches(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0, |acc, (expected, received)| acc | (expected ^ received))
+            == 0
+}
+
+impl super::Signer for RemoteSigner {
+    fn init_account(&self, account: &Account, mnemonic: Option<Mnemonic>) -> crate::Result<String> {
+        match self.send(SignerRequest::InitAccount {
+            account: request_account(account),
+            mnemonic,
+        })? {
+            SignerResponse::AccountInitialised(id) => Ok(id),
+            response => Err(unexpected_response(response)),
+        }
+    }
+
+    fn generate_address(
+        &self,
+        account: &Account,
+        address_index: usize,
+        internal: bool,
+    ) -> crate::Result<iota::Address> {
+        match self.send(SignerRequest::GenerateAddress {
+            account: request_account(account),
+            address_index,
+            internal,
+        })? {
+            SignerResponse::GeneratedAddress(address) => crate::address::parse(address),
+            response => Err(unexpected_response(response)),
+        }
+    }
+
+    fn generate_addresses(
+        &self,
+        account: &Account,
+        range: Range<usize>,
+        internal: bool,
+    ) -> crate::Result<Vec<iota::Address>> {
+        match self.send(SignerRequest::GenerateAddresses {
+            account: request_account(account),
+            start: range.start,
+            end: range.end,
+            internal,
+        })? {
+            SignerResponse::GeneratedAddresses(addresses) => addresses.into_iter().map(crate::address::parse).collect(),
+            response => Err(unexpected_response(response)),
+        }
+    }
+
+    fn sign_message(
+        &self,
+        account: &Account,
+        essence: &iota::TransactionEssence,
+        inputs: &mut Vec<super::TransactionInput>,
+    ) -> crate::Result<Vec<iota::UnlockBlock>> {
+        let remote_inputs = inputs
+            .iter()
+            .map(|input| RemoteTransactionInput {
+                input: hex::encode(input.input.pack_new()),
+                address_index: input.address_index,
+                address_path: input.address_path.to_string(),
+            })
+            .collect();
+        match self.send(SignerRequest::SignMessage {
+            account: request_account(account),
+            essence: hex::encode(essence.pack_new()),
+            inputs: remote_inputs,
+        })? {
+            SignerResponse::SignedMessage(unlock_blocks) => {
+                let mut blocks = vec![];
+                for block in unlock_blocks {
+                    let bytes = hex::decode(block).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                    let block = iota::UnlockBlock::unpack(&mut bytes.as_slice())
+                        .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+                    blocks.push(block);
+                }
+                Ok(blocks)
+            }
+            response => Err(unexpected_response(response)),
+        }
+    }
+}
+
+/// The listener of a remote signer server.
+pub enum RemoteSignerListener {
+    /// A TCP listener.
+    Tcp(TcpListener),
+    /// A Unix domain socket listener.
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl RemoteSignerListener {
+    /// Binds a listener to the given endpoint.
+    /// A Unix domain socket is only accessible to its owner; prefer it to a TCP socket, reachable by every local user.
+    pub fn bind(endpoint: &RemoteSignerEndpoint) -> crate::Result<Self> {
+        let listener = match endpoint {
+            RemoteSignerEndpoint::Tcp(address) => Self::Tcp(TcpListener::bind(address)?),
+            #[cfg(unix)]
+            RemoteSignerEndpoint::Unix(path) => {
+                let listener = UnixListener::bind(path)?;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+                Self::Unix(listener)
+            }
+        };
+        Ok(listener)
+    }
+}
+
+fn handle_request(signer_type: &super::SignerType, request: SignerRequest) -> crate::Result<SignerResponse> {
+    let response = match request {
+        SignerRequest::InitAccount { account, mnemonic } => {
+            let id = super::with_signer(signer_type, |signer| signer.init_account(&account, mnemonic))?;
+            SignerResponse::AccountInitialised(id)
+        }
+        SignerRequest::GenerateAddress {
+            account,
+            address_index,
+            internal,
+        } => {
+            let address = super::with_signer(signer_type, |signer| {
+                signer.generate_address(&account, address_index, internal)
+            })?;
+            SignerResponse::GeneratedAddress(address.to_bech32())
+        }
+        SignerRequest::GenerateAddresses {
+            account,
+            start,
+            end,
+            internal,
+        } => {
+            let addresses = super::with_signer(signer_type, |signer| {
+                signer.generate_addresses(&account, start..end, internal)
+            })?;
+            SignerResponse::GeneratedAddresses(addresses.iter().map(|address| address.to_bech32()).collect())
+        }
+        SignerRequest::SignMessage {
+            account,
+            essence,
+            inputs,
+        } => {
+            let essence = hex::decode(essence).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+            let essence = iota::TransactionEssence::unpack(&mut essence.as_slice())
+                .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
+            let mut transaction_inputs = vec![];
+            for input in inputs {
+                let packed_input = hex::decode(input.input).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                transaction_inputs.push(super::TransactionInput {
+                    input: iota::Input::unpack(&mut packed_input.as_slice())
+                        .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?,
+                    address_index: input.address_index,
+                    address_path: BIP32Path::from_str(&input.address_path)
+                        .map_err(|e| anyhow::anyhow!(e.to_string()))?,
+                });
+            }
+            let unlock_blocks = super::with_signer(signer_type, |signer| {
+                signer.sign_message(&account, &essence, &mut transaction_inputs)
+            })?;
+            SignerResponse::SignedMessage(
+                unlock_blocks
+                    .iter()
+                    .map(|block| hex::encode(block.pack_new()))
+                    .collect(),
+            )
+        }
+    };
+    Ok(response)
+}
+
+fn handle_connection<S: Read + Write>(stream: S, signer_type: &super::SignerType, token: &str) -> crate::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let received_token = Zeroizing::new(read_line(&mut reader)?);
+
+    let response = if token_matches(token, received_token.trim_end_matches('\n')) {
+        let request = Zeroizing::new(read_line(&mut reader)?);
+        match serde_json::from_str(&request) {
+            Ok(request) => {
+                handle_request(signer_type, request).unwrap_or_else(|e| SignerResponse::Error(e.to_string()))
+            }
+            Err(e) => SignerResponse::Error(e.to_string()),
+        }
+    } else {
+        SignerResponse::Error("invalid remote signer token".to_string())
+    };
+    let stream = reader.get_mut();
+    stream.write_all(serde_json::to_string(&response)?.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Handles the connections on a fixed pool of `MAX_WORKERS` threads; new connections wait until a worker is free.
+fn serve_connections<S, I>(incoming: I, signer_type: super::SignerType, token: Zeroizing<String>) -> crate::Result<()>
+where
+    S: Read + Write + Send + 'static,
+    I: Iterator<Item = std::io::Result<S>>,
+{
+    let (sender, receiver) = mpsc::sync_channel::<S>(0);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let token = Arc::new(token);
+    for _ in 0..MAX_WORKERS {
+        let receiver = receiver.clone();
+        let signer_type = signer_type.clone();
+        let token = token.clone();
+        thread::spawn(move || loop {
+            let stream = match receiver.lock().unwrap().recv() {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+            let _ = handle_connection(stream, &signer_type, &token);
+        });
+    }
+    for stream in incoming {
+        sender
+            .send(stream?)
+            .map_err(|_| anyhow::anyhow!("the remote signer workers stopped"))?;
+    }
+    Ok(())
+}
+
+/// Serves the signer registered for `signer_type` on the given listener to the clients sending the given token.
+/// At most `MAX_WORKERS` connections are handled at the same time. This function blocks forever.
+pub fn serve<T: Into<String>>(
+    listener: RemoteSignerListener,
+    signer_type: super::SignerType,
+    token: T,
+) -> crate::Result<()> {
+    let token = Zeroizing::new(token.into());
+    if token.is_empty() || token.contains('\n') {
+        return Err(anyhow::anyhow!("the remote signer token must be a non-empty single line").into());
+    }
+    match listener {
+        RemoteSignerListener::Tcp(listener) => {
+            let incoming = listener.incoming().map(|stream| -> std::io::Result<TcpStream> {
+                let stream = stream?;
+                stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+                stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+                Ok(stream)
+            });
+            serve_connections(incoming, signer_type, token)
+        }
+        #[cfg(unix)]
+        RemoteSignerListener::Unix(listener) => {
+            let incoming = listener.incoming().map(|stream| -> std::io::Result<UnixStream> {
+                let stream = stream?;
+                stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+                stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+                Ok(stream)
+            });
+            serve_connections(incoming, signer_type, token)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_line, token_matches, MAX_LINE_LENGTH};
+    use std::io::Cursor;
+
+    #[test]
+    fn line_length_limit() {
+        let line = format!("{}\n", "a".repeat(MAX_LINE_LENGTH - 1));
+        assert_eq!(read_line(&mut Cursor::new(line.clone())).unwrap(), line);
+        let line = format!("{}\n", "a".repeat(MAX_LINE_LENGTH));
+        assert!(read_line(&mut Cursor::new(line)).is_err());
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(token_matches("token", "token"));
+        assert!(!token_matches("token", "tokem"));
+        assert!(!token_matches("token", "token "));
+        assert!(!token_matches("token", ""));
+    }
+}
@@ -1,15 +1,16 @@
 // Copyright 2020 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
+use super::Mnemonic;
 use crate::account::{account_id_to_stronghold_record_id, Account};
 
-use std::convert::TryInto;
+use std::{convert::TryInto, ops::Range};
 
 #[derive(Default)]
 pub struct StrongholdSigner;
 
 impl super::Signer for StrongholdSigner {
-    fn init_account(&self, account: &Account, mnemonic: Option<String>) -> crate::Result<String> {
+    fn init_account(&self, account: &Account, mnemonic: Option<Mnemonic>) -> crate::Result<String> {
         let stronghold_account_res: crate::Result<stronghold::Account> =
             crate::with_stronghold_from_path(account.storage_path(), |stronghold| {
                 let created_at_timestamp: u128 = account.created_at().timestamp().try_into().unwrap(); // safe to unwrap since it's > 0;
@@ -17,7 +18,7 @@ impl super::Signer for StrongholdSigner {
                     Some(mnemonic) => stronghold.account_import(
                         Some(created_at_timestamp),
                         Some(created_at_timestamp),
-                        mnemonic,
+                        mnemonic.to_string(),
                         Some("password"),
                     )?,
                     None => stronghold.account_create(Some("password".to_string()))?,
@@ -29,6 +30,13 @@ impl super::Signer for StrongholdSigner {
         Ok(hex::encode(id))
     }
 
+    fn remove_account(&self, account: &Account) -> crate::Result<()> {
+        crate::with_stronghold_from_path(account.storage_path(), |stronghold| {
+            stronghold.account_remove(&account_id_to_stronghold_record_id(account.id())?)?;
+            Ok(())
+        })
+    }
+
     fn generate_address(
         &self,
         account: &Account,
@@ -46,6 +54,25 @@ impl super::Signer for StrongholdSigner {
         })
     }
 
+    fn generate_addresses(
+        &self,
+        account: &Account,
+        range: Range<usize>,
+        internal: bool,
+    ) -> crate::Result<Vec<iota::Address>> {
+        // derive the whole range with a single stronghold lock
+        crate::with_stronghold_from_path(account.storage_path(), |stronghold| {
+            let record_id = account_id_to_stronghold_record_id(account.id())?;
+            let mut addresses = vec![];
+            for address_index in range {
+                let address_str =
+                    stronghold.address_get(&record_id, Some(*account.index()), address_index, internal)?;
+                addresses.push(crate::address::parse(address_str)?);
+            }
+            Ok(addresses)
+        })
+    }
+
     fn sign_message(
         &self,
         account: &Account,
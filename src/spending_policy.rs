@@ -0,0 +1,345 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::address::{to_bech32, IotaAddress};
+
+use chrono::prelude::{DateTime, Utc};
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+/// The rolling window of a spending limit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpendingPeriod {
+    /// The last 24 hours.
+    Day,
+    /// The last 7 days.
+    Week,
+}
+
+impl SpendingPeriod {
+    /// The period duration.
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            Self::Day => chrono::Duration::days(1),
+            Self::Week => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+/// The reason a transfer was rejected by the account spending policy.
+#[derive(Debug, thiserror::Error)]
+pub enum SpendingPolicyError {
+    /// The transfer amount exceeds the per-transaction maximum.
+    #[error("the transfer amount {amount} exceeds the per-transaction maximum {max}")]
+    TransactionAmountExceeded {
+        /// The transfer amount.
+        amount: u64,
+        /// The per-transaction maximum.
+        max: u64,
+    },
+    /// The transfer would exceed a rolling spending limit.
+    #[error("the transfer would spend {spent} on the {period:?} period, the limit is {limit}")]
+    LimitExceeded {
+        /// The limit period.
+        period: SpendingPeriod,
+        /// The amount spent on the period, including the transfer.
+        spent: u64,
+        /// The limit.
+        limit: u64,
+    },
+    /// The recipient isn't on the allowlist.
+    #[error("recipient {0} isn't on the allowlist")]
+    RecipientNotAllowed(String),
+    /// The recipient was added to the allowlist too recently.
+    #[error("recipient {address} can only receive funds after {available_at}")]
+    RecipientNotAvailableYet {
+        /// The recipient address.
+        address: String,
+        /// The time the recipient can receive funds.
+        available_at: DateTime<Utc>,
+    },
+}
+
+/// A recipient on the spending policy allowlist.
+#[derive(Debug, Clone, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct AllowedRecipient {
+    /// The recipient address.
+    #[serde(with = "crate::serde::iota_address_serde")]
+    address: IotaAddress,
+    /// The time the recipient was added to the allowlist, set by the library.
+    #[serde(rename = "addedAt", default = "Utc::now")]
+    added_at: DateTime<Utc>,
+}
+
+/// The spending controls of an account, enforced on every outgoing transfer.
+/// Transfers between the account's own addresses aren't limited.
+#[derive(Debug, Clone, Default, PartialEq, Getters, Serialize, Deserialize)]
+#[getset(get = "pub")]
+pub struct SpendingPolicy {
+    /// The maximum amount of a single transfer.
+    #[serde(rename = "maxTransactionAmount", default)]
+    max_transaction_amount: Option<u64>,
+    /// The maximum amount spent on the last 24 hours.
+    #[serde(rename = "dailyLimit", default)]
+    daily_limit: Option<u64>,
+    /// The maximum amount spent on the last 7 days.
+    #[serde(rename = "weeklyLimit", default)]
+    weekly_limit: Option<u64>,
+    /// The recipients allowed to receive funds. Every recipient is allowed if it's empty.
+    #[serde(rename = "allowedRecipients", default)]
+    allowed_recipients: Vec<AllowedRecipient>,
+    /// The time in seconds a recipient must be on the allowlist before it can receive funds.
+    #[serde(rename = "newRecipientDelay", default)]
+    new_recipient_delay: Option<u64>,
+}
+
+impl SpendingPolicy {
+    /// Initialises a policy without limits.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Keeps the time the recipients were added to the previous policy allowlist.
+    /// The other recipients are considered added now, so the delay can't be skipped by backdating them.
+    pub(crate) fn inherit_allowlist(&mut self, previous: &SpendingPolicy, now: DateTime<Utc>) {
+        for recipient in self.allowed_recipients.iter_mut() {
+            recipient.added_at = previous
+                .allowed_recipients
+                .iter()
+                .find(|r| r.address == recipient.address)
+                .map(|r| r.added_at)
+                .unwrap_or(now);
+        }
+    }
+
+    /// Gets the limit of the given period.
+    pub fn limit(&self, period: SpendingPeriod) -> Option<u64> {
+        match period {
+            SpendingPeriod::Day => self.daily_limit,
+            SpendingPeriod::Week => self.weekly_limit,
+        }
+    }
+
+    /// Checks a transfer against the policy.
+    ///
+    /// `recipients` are the transfer outputs that don't belong to the account,
+    /// and `spent` gets the amount the account sent since the given time.
+    pub(crate) fn check<F: Fn(DateTime<Utc>) -> u64>(
+        &self,
+        recipients: &[(IotaAddress, u64)],
+        spent: F,
+        bech32_hrp: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), SpendingPolicyError> {
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let amount = recipients
+            .iter()
+            .fold(0u64, |acc, (_, amount)| acc.saturating_add(*amount));
+
+        if let Some(max) = self.max_transaction_amount {
+            if amount > max {
+                return Err(SpendingPolicyError::TransactionAmountExceeded { amount, max });
+            }
+        }
+
+        for period in &[SpendingPeriod::Day, SpendingPeriod::Week] {
+            if let Some(limit) = self.limit(*period) {
+                let period_spent = spent(now - period.duration()).saturating_add(amount);
+                if period_spent > limit {
+                    return Err(SpendingPolicyError::LimitExceeded {
+                        period: *period,
+                        spent: period_spent,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        if !self.allowed_recipients.is_empty() {
+            let to_bech32 = |address: &IotaAddress| {
+                to_bech32(address, bech32_hrp).expect("the account bech32 HRP is validated when it's set")
+            };
+            for (address, _) in recipients {
+                let recipient = self
+                    .allowed_recipients
+                    .iter()
+                    .find(|r| &r.address == address)
+                    .ok_or_else(|| SpendingPolicyError::RecipientNotAllowed(to_bech32(address)))?;
+                if let Some(delay) = self.new_recipient_delay {
+                    let available_at = recipient.added_at + chrono::Duration::seconds(delay as i64);
+                    if available_at > now {
+                        return Err(SpendingPolicyError::RecipientNotAvailableYet {
+                            address: to_bech32(address),
+                            available_at,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The spending policy builder.
+#[derive(Default)]
+pub struct SpendingPolicyBuilder {
+    policy: SpendingPolicy,
+}
+
+impl SpendingPolicyBuilder {
+    /// Initialises a new instance of the spending policy builder.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the maximum amount of a single transfer.
+    pub fn max_transaction_amount(mut self, amount: u64) -> Self {
+        self.policy.max_transaction_amount = Some(amount);
+        self
+    }
+
+    /// Sets the maximum amount spent on the last 24 hours.
+    pub fn daily_limit(mut self, limit: u64) -> Self {
+        self.policy.daily_limit = Some(limit);
+        self
+    }
+
+    /// Sets the maximum amount spent on the last 7 days.
+    pub fn weekly_limit(mut self, limit: u64) -> Self {
+        self.policy.weekly_limit = Some(limit);
+        self
+    }
+
+    /// Adds a recipient to the allowlist.
+    pub fn allowed_recipient(mut self, address: IotaAddress) -> Self {
+        if !self.policy.allowed_recipients.iter().any(|r| r.address == address) {
+            self.policy.allowed_recipients.push(AllowedRecipient {
+                address,
+                added_at: Utc::now(),
+            });
+        }
+        self
+    }
+
+    /// Sets the time a recipient must be on the allowlist before it can receive funds.
+    /// The delay is rounded up to whole seconds, so it's never shorter than requested.
+    pub fn new_recipient_delay(mut self, delay: Duration) -> Self {
+        let seconds = if delay.subsec_nanos() > 0 {
+            delay.as_secs().saturating_add(1)
+        } else {
+            delay.as_secs()
+        };
+        self.policy.new_recipient_delay = Some(seconds);
+        self
+    }
+
+    /// Builds the spending policy.
+    pub fn build(self) -> SpendingPolicy {
+        self.policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpendingPeriod, SpendingPolicyBuilder, SpendingPolicyError};
+    use crate::address::{IotaAddress, MAINNET_BECH32_HRP as HRP};
+    use chrono::{Duration, Utc};
+    use iota::message::prelude::Ed25519Address;
+
+    fn address(byte: u8) -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    #[test]
+    fn limits() {
+        let policy = SpendingPolicyBuilder::new()
+            .max_transaction_amount(100)
+            .daily_limit(150)
+            .weekly_limit(500)
+            .build();
+        let now = Utc::now();
+
+        assert!(policy.check(&[(address(1), 100)], |_| 0, HRP, now).is_ok());
+        // own address transfers aren't limited
+        assert!(policy.check(&[], |_| 1000, HRP, now).is_ok());
+        assert!(matches!(
+            policy.check(&[(address(1), 60), (address(2), 50)], |_| 0, HRP, now),
+            Err(SpendingPolicyError::TransactionAmountExceeded { amount: 110, max: 100 })
+        ));
+        assert!(matches!(
+            policy.check(
+                &[(address(1), 60)],
+                |since| if since > now - Duration::days(2) { 100 } else { 0 },
+                HRP,
+                now
+            ),
+            Err(SpendingPolicyError::LimitExceeded {
+                period: SpendingPeriod::Day,
+                spent: 160,
+                limit: 150
+            })
+        ));
+        assert!(matches!(
+            policy.check(
+                &[(address(1), 60)],
+                |since| if since > now - Duration::days(2) { 0 } else { 450 },
+                HRP,
+                now
+            ),
+            Err(SpendingPolicyError::LimitExceeded {
+                period: SpendingPeriod::Week,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn allowlist() {
+        let now = Utc::now();
+        let policy = SpendingPolicyBuilder::new()
+            .allowed_recipient(address(1))
+            .new_recipient_delay(std::time::Duration::from_secs(3600))
+            .build();
+
+        assert!(matches!(
+            policy.check(&[(address(2), 10)], |_| 0, HRP, now),
+            Err(SpendingPolicyError::RecipientNotAllowed(_))
+        ));
+        assert!(matches!(
+            policy.check(&[(address(1), 10)], |_| 0, HRP, now),
+            Err(SpendingPolicyError::RecipientNotAvailableYet { .. })
+        ));
+        assert!(policy
+            .check(&[(address(1), 10)], |_| 0, HRP, now + Duration::hours(2))
+            .is_ok());
+
+        // recipients already on the previous policy keep their time, new ones are added now
+        let previous = policy;
+        let mut policy = SpendingPolicyBuilder::new()
+            .allowed_recipient(address(1))
+            .allowed_recipient(address(2))
+            .build();
+        policy.inherit_allowlist(&previous, now + Duration::hours(1));
+        assert_eq!(
+            policy.allowed_recipients()[0].added_at(),
+            previous.allowed_recipients()[0].added_at()
+        );
+        assert_eq!(policy.allowed_recipients()[1].added_at(), &(now + Duration::hours(1)));
+
+        // sub-second delays are rounded up
+        let policy = SpendingPolicyBuilder::new()
+            .new_recipient_delay(std::time::Duration::from_millis(1500))
+            .build();
+        assert_eq!(policy.new_recipient_delay(), &Some(2));
+        let policy = SpendingPolicyBuilder::new()
+            .new_recipient_delay(std::time::Duration::from_millis(1))
+            .build();
+        assert_eq!(policy.new_recipient_delay(), &Some(1));
+    }
+}
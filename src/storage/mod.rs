@@ -63,6 +63,14 @@ pub trait StorageAdapter {
     fn set(&self, account_id: AccountIdentifier, account: String) -> crate::Result<()>;
     /// Removes an account from the storage.
     fn remove(&self, account_id: AccountIdentifier) -> crate::Result<()>;
+    /// Gets the address book from the storage, or `None` if it wasn't saved yet.
+    fn get_address_book(&self) -> crate::Result<Option<String>> {
+        Err(anyhow::anyhow!("the storage adapter doesn't support the address book").into())
+    }
+    /// Saves or updates the address book on the storage.
+    fn set_address_book(&self, _address_book: String) -> crate::Result<()> {
+        Err(anyhow::anyhow!("the storage adapter doesn't support the address book").into())
+    }
 }
 
 pub(crate) fn parse_accounts(storage_path: &PathBuf, accounts: &[String]) -> crate::Result<Vec<Account>> {
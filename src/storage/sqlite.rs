@@ -14,6 +14,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+static ADDRESS_BOOK_TABLE_NAME: &str = "address_book";
+
 /// Key value storage adapter.
 pub struct SqliteStorageAdapter {
     table_name: String,
@@ -38,6 +40,16 @@ impl SqliteStorageAdapter {
             ),
             NO_PARAMS,
         )?;
+        connection.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    key TEXT NOT NULL UNIQUE,
+                    value TEXT
+                )",
+                ADDRESS_BOOK_TABLE_NAME
+            ),
+            NO_PARAMS,
+        )?;
 
         Ok(Self {
             table_name: table_name.as_ref().to_string(),
@@ -118,4 +130,28 @@ impl StorageAdapter for SqliteStorageAdapter {
             .map_err(|_| anyhow::anyhow!("failed to delete data"))?;
         Ok(())
     }
+
+    fn get_address_book(&self) -> crate::Result<Option<String>> {
+        let connection = self.connection.lock().expect("failed to get connection lock");
+        let mut query = connection.prepare(&format!(
+            "SELECT value FROM {} WHERE key = ?1 LIMIT 1",
+            ADDRESS_BOOK_TABLE_NAME
+        ))?;
+        let results = query
+            .query_and_then(params![ADDRESS_BOOK_TABLE_NAME], |row| row.get(0))?
+            .collect::<Vec<rusqlite::Result<String>>>();
+        let address_book = results.first().map(|val| val.as_ref().unwrap().to_string());
+        Ok(address_book)
+    }
+
+    fn set_address_book(&self, address_book: String) -> crate::Result<()> {
+        let connection = self.connection.lock().expect("failed to get connection lock");
+        connection
+            .execute(
+                &format!("INSERT OR REPLACE INTO {} VALUES (?1, ?2)", ADDRESS_BOOK_TABLE_NAME),
+                params![ADDRESS_BOOK_TABLE_NAME, address_book],
+            )
+            .map_err(|_| anyhow::anyhow!("failed to insert data"))?;
+        Ok(())
+    }
 }
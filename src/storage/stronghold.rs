@@ -9,6 +9,7 @@ use std::path::{Path, PathBuf};
 use stronghold::{RecordHint, RecordId, Stronghold};
 
 static ACCOUNT_ID_INDEX_HINT: &str = "wallet.rs-account-ids";
+static ADDRESS_BOOK_HINT: &str = "wallet.rs-address-book";
 
 type AccountIdIndex = Vec<(AccountIdentifier, RecordId)>;
 
@@ -49,6 +50,17 @@ fn get_account_index(stronghold: &Stronghold) -> crate::Result<(RecordId, Accoun
     Ok((record_id, index))
 }
 
+fn find_address_book_records(stronghold: &Stronghold) -> crate::Result<Vec<RecordId>> {
+    let storage_index = stronghold.record_list()?;
+    let address_book_hint = RecordHint::new(ADDRESS_BOOK_HINT)?;
+    let record_ids = storage_index
+        .iter()
+        .filter(|(_, record_hint)| record_hint == &address_book_hint)
+        .map(|(record_id, _)| *record_id)
+        .collect();
+    Ok(record_ids)
+}
+
 fn get_from_index(
     #[allow(clippy::ptr_arg)] index: &AccountIdIndex,
     account_id: &AccountIdentifier,
@@ -151,4 +163,32 @@ impl StorageAdapter for StrongholdStorageAdapter {
 
         Ok(())
     }
+
+    fn get_address_book(&self) -> crate::Result<Option<String>> {
+        // an interrupted update can leave the previous record behind until the next update removes it
+        crate::with_stronghold_from_path(&self.path, |stronghold| {
+            match find_address_book_records(&stronghold)?.last().copied() {
+                Some(record_id) => stronghold
+                    .record_read(&record_id)
+                    .map(Some)
+                    .map_err(crate::WalletError::GenericError),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn set_address_book(&self, address_book: String) -> crate::Result<()> {
+        crate::with_stronghold_from_path(&self.path, |stronghold| {
+            // the new record is created first so the address book isn't lost if the update is interrupted
+            let previous_records = find_address_book_records(&stronghold)?;
+            let record_id =
+                stronghold.record_create_with_hint(&address_book, RecordHint::new(ADDRESS_BOOK_HINT).unwrap())?;
+            for previous_record in previous_records {
+                if previous_record != record_id {
+                    stronghold.record_remove(previous_record)?;
+                }
+            }
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,413 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::address::{IotaAddress, OutputId};
+
+use bee_common::packable::Packable;
+use bee_signing_ext::{binary::ed25519, Verifier};
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use getset::Getters;
+use iota::message::prelude::{
+    Ed25519Address, Ed25519Signature, Input, Output, SignatureUnlock, Transaction, TransactionEssence, UnlockBlock,
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// The minimum amount of an output.
+pub const DUST_THRESHOLD: u64 = 1_000_000;
+
+/// The reason a transaction failed the local validation.
+#[derive(Debug, thiserror::Error)]
+pub enum TransactionValidationError {
+    /// The transaction doesn't have inputs.
+    #[error("the transaction has no inputs")]
+    NoInputs,
+    /// An input is used more than once.
+    #[error("input {0:?} is used more than once")]
+    DuplicateInput(OutputId),
+    /// An input isn't on the known outputs.
+    #[error("input {0:?} is unknown")]
+    UnknownInput(OutputId),
+    /// An input is already spent.
+    #[error("input {0:?} is already spent")]
+    SpentInput(OutputId),
+    /// The input kind isn't supported by the wallet.
+    #[error("input {0} has an unsupported kind")]
+    UnsupportedInput(usize),
+    /// The output kind isn't supported by the wallet.
+    #[error("output {0} has an unsupported kind")]
+    UnsupportedOutput(usize),
+    /// The inputs sum overflows.
+    #[error("the inputs sum overflows")]
+    InputsSumOverflow,
+    /// The outputs sum overflows.
+    #[error("the outputs sum overflows")]
+    OutputsSumOverflow,
+    /// The inputs and outputs sums differ.
+    #[error("the inputs sum {inputs} doesn't match the outputs sum {outputs}")]
+    AmountMismatch {
+        /// The inputs sum.
+        inputs: u64,
+        /// The outputs sum.
+        outputs: u64,
+    },
+    /// An output amount is below the dust threshold.
+    #[error("output {index} amount {amount} is below the dust threshold")]
+    DustOutput {
+        /// The output index.
+        index: usize,
+        /// The output amount.
+        amount: u64,
+    },
+    /// The number of unlock blocks doesn't match the number of inputs.
+    #[error("the transaction has {unlock_blocks} unlock blocks for {inputs} inputs")]
+    UnlockBlockCountMismatch {
+        /// The number of inputs.
+        inputs: usize,
+        /// The number of unlock blocks.
+        unlock_blocks: usize,
+    },
+    /// The unlock block signature doesn't verify against the transaction essence.
+    #[error("unlock block {0} has an invalid signature")]
+    InvalidSignature(usize),
+    /// The unlock block doesn't unlock the address of its input.
+    #[error("unlock block {0} doesn't unlock its input address")]
+    UnlockAddressMismatch(usize),
+    /// The reference unlock block doesn't point to a previous signature unlock block.
+    #[error("unlock block {0} has an invalid reference")]
+    InvalidReference(usize),
+}
+
+/// An output known to the validator, that a transaction may spend.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub")]
+pub struct KnownOutput {
+    /// The output identifier.
+    id: OutputId,
+    /// The address holding the output.
+    address: IotaAddress,
+    /// The output amount.
+    amount: u64,
+    /// Whether the output is spent or pending on another transaction.
+    spent: bool,
+}
+
+impl KnownOutput {
+    /// Initialises a new known output.
+    pub fn new(id: OutputId, address: IotaAddress, amount: u64, spent: bool) -> Self {
+        Self {
+            id,
+            address,
+            amount,
+            spent,
+        }
+    }
+}
+
+/// Validates the inputs, returning the address unlocked by each input and the inputs sum.
+fn validate_inputs(
+    essence: &TransactionEssence,
+    known_outputs: &[KnownOutput],
+) -> Result<(Vec<IotaAddress>, u64), TransactionValidationError> {
+    if essence.inputs().is_empty() {
+        return Err(TransactionValidationError::NoInputs);
+    }
+    let mut used_inputs = HashSet::new();
+    let mut input_addresses = Vec::new();
+    let mut input_sum: u64 = 0;
+    for (index, input) in essence.inputs().iter().enumerate() {
+        let output_id = match input {
+            Input::UTXO(input) => OutputId::new(*input.output_id().transaction_id(), input.output_id().index()),
+            _ => return Err(TransactionValidationError::UnsupportedInput(index)),
+        };
+        if !used_inputs.insert(output_id.clone()) {
+            return Err(TransactionValidationError::DuplicateInput(output_id));
+        }
+        let known_output = known_outputs
+            .iter()
+            .find(|output| output.id == output_id)
+            .ok_or_else(|| TransactionValidationError::UnknownInput(output_id.clone()))?;
+        if known_output.spent {
+            return Err(TransactionValidationError::SpentInput(output_id));
+        }
+        input_addresses.push(known_output.address.clone());
+        input_sum = input_sum
+            .checked_add(known_output.amount)
+            .ok_or(TransactionValidationError::InputsSumOverflow)?;
+    }
+    Ok((input_addresses, input_sum))
+}
+
+/// Validates the outputs amounts against the dust threshold and the inputs sum.
+fn validate_outputs(essence: &TransactionEssence, input_sum: u64) -> Result<(), TransactionValidationError> {
+    let mut output_sum: u64 = 0;
+    for (index, output) in essence.outputs().iter().enumerate() {
+        let amount = match output {
+            Output::SignatureLockedSingle(output) => output.amount().get(),
+            _ => return Err(TransactionValidationError::UnsupportedOutput(index)),
+        };
+        if amount < DUST_THRESHOLD {
+            return Err(TransactionValidationError::DustOutput { index, amount });
+        }
+        output_sum = output_sum
+            .checked_add(amount)
+            .ok_or(TransactionValidationError::OutputsSumOverflow)?;
+    }
+    if input_sum != output_sum {
+        return Err(TransactionValidationError::AmountMismatch {
+            inputs: input_sum,
+            outputs: output_sum,
+        });
+    }
+    Ok(())
+}
+
+/// Gets the address derived from the signature public key.
+fn signature_address(signature: &Ed25519Signature) -> IotaAddress {
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(signature.public_key());
+    let mut result = [0u8; 32];
+    hasher.finalize_variable(|res| {
+        result.copy_from_slice(res);
+    });
+    IotaAddress::Ed25519(Ed25519Address::new(result))
+}
+
+fn verify_signature(signature: &Ed25519Signature, serialized_essence: &[u8]) -> bool {
+    let public_key = match ed25519::Ed25519PublicKey::from_bytes(signature.public_key()) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match ed25519::Ed25519Signature::from_bytes(signature.signature()) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify(serialized_essence, &signature).is_ok()
+}
+
+/// Validates that each unlock block unlocks the address of its input.
+fn validate_unlock_blocks(
+    essence: &TransactionEssence,
+    unlock_blocks: &[UnlockBlock],
+    input_addresses: &[IotaAddress],
+) -> Result<(), TransactionValidationError> {
+    if unlock_blocks.len() != input_addresses.len() {
+        return Err(TransactionValidationError::UnlockBlockCountMismatch {
+            inputs: input_addresses.len(),
+            unlock_blocks: unlock_blocks.len(),
+        });
+    }
+    let serialized_essence = essence.pack_new();
+    // unlock block index => address unlocked by its signature
+    let mut signature_addresses = HashMap::new();
+    for (index, (unlock_block, input_address)) in unlock_blocks.iter().zip(input_addresses).enumerate() {
+        let unlocked_address = match unlock_block {
+            UnlockBlock::Signature(signature) => {
+                let signature = match signature {
+                    SignatureUnlock::Ed25519(signature) => signature,
+                    #[allow(unreachable_patterns)]
+                    _ => return Err(TransactionValidationError::InvalidSignature(index)),
+                };
+                if !verify_signature(signature, &serialized_essence) {
+                    return Err(TransactionValidationError::InvalidSignature(index));
+                }
+                let address = signature_address(signature);
+                signature_addresses.insert(index, address.clone());
+                address
+            }
+            // a reference must point to a previous signature block
+            UnlockBlock::Reference(reference) => signature_addresses
+                .get(&(reference.index() as usize))
+                .cloned()
+                .ok_or_else(|| TransactionValidationError::InvalidReference(index))?,
+        };
+        if &unlocked_address != input_address {
+            return Err(TransactionValidationError::UnlockAddressMismatch(index));
+        }
+    }
+    Ok(())
+}
+
+/// Validates a signed transaction before it's posted.
+///
+/// Checks that the inputs are unique, known and unspent, that the inputs and outputs sums match,
+/// that the outputs meet the dust threshold and that every unlock block unlocks its input address
+/// with a signature that verifies against the serialized essence.
+pub fn validate_transaction(transaction: &Transaction, known_outputs: &[KnownOutput]) -> crate::Result<()> {
+    let essence = transaction.essence();
+    let (input_addresses, input_sum) = validate_inputs(essence, known_outputs)?;
+    validate_outputs(essence, input_sum)?;
+    validate_unlock_blocks(essence, transaction.unlock_blocks(), &input_addresses)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bee_signing_ext::{binary::BIP32Path, Signer};
+    use iota::message::prelude::{ReferenceUnlock, SignatureLockedSingleOutput, TransactionId, UTXOInput};
+    use std::{num::NonZeroU64, str::FromStr};
+
+    fn address(byte: u8) -> IotaAddress {
+        IotaAddress::Ed25519(Ed25519Address::new([byte; 32]))
+    }
+
+    fn output_id(index: u16) -> OutputId {
+        OutputId::new(TransactionId::new([1; 32]), index)
+    }
+
+    fn essence(inputs: &[OutputId], outputs: &[u64]) -> TransactionEssence {
+        let mut builder = TransactionEssence::builder();
+        for input in inputs {
+            builder = builder.add_input(UTXOInput::new(*input.transaction_id(), *input.index()).unwrap().into());
+        }
+        for amount in outputs {
+            builder = builder
+                .add_output(SignatureLockedSingleOutput::new(address(9), NonZeroU64::new(*amount).unwrap()).into());
+        }
+        builder.finish().unwrap()
+    }
+
+    /// Signs the essence with a key derived from the given seed byte.
+    fn sign(essence: &TransactionEssence, seed_byte: u8) -> Ed25519Signature {
+        let seed = ed25519::Ed25519Seed::from_bytes(&[seed_byte; 32]).unwrap();
+        let private_key = ed25519::Ed25519PrivateKey::generate_from_seed(
+            &seed,
+            &BIP32Path::from_str("m/44H/4218H/0H/0H/0H").unwrap(),
+        )
+        .unwrap();
+        let public_key = private_key.generate_public_key().to_bytes();
+        let signature = Box::new(private_key.sign(&essence.pack_new()).to_bytes());
+        Ed25519Signature::new(public_key, signature)
+    }
+
+    #[test]
+    fn inputs() {
+        let known_outputs = vec![
+            KnownOutput::new(output_id(0), address(0), 2_000_000, false),
+            KnownOutput::new(output_id(1), address(1), 3_000_000, true),
+        ];
+        let (addresses, sum) = validate_inputs(&essence(&[output_id(0)], &[2_000_000]), &known_outputs).unwrap();
+        assert_eq!(addresses, vec![address(0)]);
+        assert_eq!(sum, 2_000_000);
+
+        assert!(matches!(
+            validate_inputs(&essence(&[output_id(1)], &[3_000_000]), &known_outputs),
+            Err(TransactionValidationError::SpentInput(_))
+        ));
+        assert!(matches!(
+            validate_inputs(&essence(&[output_id(2)], &[3_000_000]), &known_outputs),
+            Err(TransactionValidationError::UnknownInput(_))
+        ));
+
+        let overflowing_outputs = vec![
+            KnownOutput::new(output_id(0), address(0), u64::MAX, false),
+            KnownOutput::new(output_id(1), address(1), 1, false),
+        ];
+        assert!(matches!(
+            validate_inputs(
+                &essence(&[output_id(0), output_id(1)], &[2_000_000]),
+                &overflowing_outputs
+            ),
+            Err(TransactionValidationError::InputsSumOverflow)
+        ));
+    }
+
+    #[test]
+    fn outputs() {
+        let essence = essence(&[output_id(0)], &[1_500_000, 1_000_000]);
+        assert!(validate_outputs(&essence, 2_500_000).is_ok());
+        assert!(matches!(
+            validate_outputs(&essence, 3_000_000),
+            Err(TransactionValidationError::AmountMismatch {
+                inputs: 3_000_000,
+                outputs: 2_500_000
+            })
+        ));
+
+        let dust_essence = self::essence(&[output_id(0)], &[2_000_000, 500]);
+        assert!(matches!(
+            validate_outputs(&dust_essence, 2_000_500),
+            Err(TransactionValidationError::DustOutput { amount: 500, .. })
+        ));
+
+        let overflowing_essence = self::essence(&[output_id(0)], &[u64::MAX, 2_000_000]);
+        assert!(matches!(
+            validate_outputs(&overflowing_essence, u64::MAX),
+            Err(TransactionValidationError::OutputsSumOverflow)
+        ));
+    }
+
+    #[test]
+    fn valid_signature() {
+        let essence = essence(&[output_id(0), output_id(1)], &[5_000_000]);
+        let signature = sign(&essence, 1);
+        let signer_address = signature_address(&signature);
+        let unlock_blocks = vec![
+            UnlockBlock::Signature(SignatureUnlock::Ed25519(signature)),
+            UnlockBlock::Reference(ReferenceUnlock::new(0).unwrap()),
+        ];
+        assert!(validate_unlock_blocks(
+            &essence,
+            &unlock_blocks,
+            &[signer_address.clone(), signer_address.clone()]
+        )
+        .is_ok());
+
+        // the signature must unlock the input address
+        assert!(matches!(
+            validate_unlock_blocks(&essence, &unlock_blocks, &[signer_address, address(0)]),
+            Err(TransactionValidationError::UnlockAddressMismatch(1))
+        ));
+    }
+
+    #[test]
+    fn tampered_signature() {
+        let essence = essence(&[output_id(0)], &[5_000_000]);
+        let signature = sign(&essence, 1);
+        let signer_address = signature_address(&signature);
+
+        let mut tampered_bytes = signature.signature().to_vec();
+        tampered_bytes[0] ^= 1;
+        let mut public_key = [0u8; 32];
+        public_key.copy_from_slice(signature.public_key());
+        let tampered_signature = Ed25519Signature::new(public_key, tampered_bytes.into_boxed_slice());
+        assert!(matches!(
+            validate_unlock_blocks(
+                &essence,
+                &[UnlockBlock::Signature(SignatureUnlock::Ed25519(tampered_signature))],
+                &[signer_address.clone()]
+            ),
+            Err(TransactionValidationError::InvalidSignature(0))
+        ));
+
+        // a signature of another essence doesn't verify either
+        let other_essence = self::essence(&[output_id(0)], &[4_000_000]);
+        assert!(matches!(
+            validate_unlock_blocks(
+                &essence,
+                &[UnlockBlock::Signature(SignatureUnlock::Ed25519(sign(
+                    &other_essence,
+                    1
+                )))],
+                &[signer_address]
+            ),
+            Err(TransactionValidationError::InvalidSignature(0))
+        ));
+    }
+
+    #[test]
+    fn unlock_block_count() {
+        let essence = essence(&[output_id(0)], &[2_000_000]);
+        assert!(matches!(
+            validate_unlock_blocks(&essence, &[], &[address(0)]),
+            Err(TransactionValidationError::UnlockBlockCountMismatch {
+                inputs: 1,
+                unlock_blocks: 0
+            })
+        ));
+    }
+}
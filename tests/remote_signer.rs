@@ -0,0 +1,94 @@
+// Copyright 2020 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(unix)]
+
+use iota_wallet::{
+    account_manager::AccountManager,
+    client::ClientOptionsBuilder,
+    signing::{
+        set_signer, Mnemonic, RemoteSigner, RemoteSignerEndpoint, SeedSigner, Signer, SignerType, MNEMONIC_ENV_VAR,
+        MNEMONIC_PASSPHRASE_ENV_VAR, REMOTE_SIGNER_TOKEN_ENV_VAR,
+    },
+};
+
+use std::{
+    path::PathBuf,
+    process::{Child, Command},
+    thread,
+    time::Duration,
+};
+
+const MNEMONIC: &str = "error morning burst mutual beauty hold mesh tuition noble lobster zone unfold expose hint jealous edge worry hobby enforce blush ice wise sad clip";
+
+/// Kills the remote signer process when the test ends.
+struct RemoteSignerProcess(Child);
+
+impl Drop for RemoteSignerProcess {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn spawn_remote_signer(socket_path: &PathBuf) -> RemoteSignerProcess {
+    let _ = std::fs::remove_file(socket_path);
+    let process = Command::new(env!("CARGO_BIN_EXE_remote_signer"))
+        .arg("unix")
+        .arg(socket_path)
+        .env(MNEMONIC_ENV_VAR, MNEMONIC)
+        .env(MNEMONIC_PASSPHRASE_ENV_VAR, "passphrase")
+        .env(REMOTE_SIGNER_TOKEN_ENV_VAR, "token")
+        .spawn()
+        .expect("failed to spawn the remote signer");
+    for _ in 0..100 {
+        if socket_path.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    RemoteSignerProcess(process)
+}
+
+#[test]
+fn remote_signer_binary() {
+    let socket_path = std::env::temp_dir().join(format!("iota-wallet-signer-{}.sock", std::process::id()));
+    let _remote_signer = spawn_remote_signer(&socket_path);
+    let endpoint = RemoteSignerEndpoint::Unix(socket_path.clone());
+    set_signer(
+        SignerType::Custom("remote".to_string()),
+        RemoteSigner::new(endpoint.clone(), "token"),
+    );
+
+    let mut manager =
+        AccountManager::with_storage_path(format!("./example-database/remote-signer-{}", std::process::id())).unwrap();
+    manager.set_stronghold_password("password").unwrap();
+    let client_options = ClientOptionsBuilder::node("https://nodes.devnet.iota.org:443")
+        .expect("invalid node URL")
+        .build();
+    let account = manager
+        .create_account(client_options)
+        .signer_type(SignerType::Custom("remote".to_string()))
+        .mnemonic(MNEMONIC)
+        .initialise()
+        .expect("failed to add account");
+
+    let local_signer = SeedSigner::new("passphrase");
+    local_signer
+        .load_mnemonic(account.id(), Mnemonic::new(MNEMONIC.to_string()))
+        .unwrap();
+    let remote_address = RemoteSigner::new(endpoint.clone(), "token")
+        .generate_address(&account, 0, false)
+        .unwrap();
+    assert_eq!(
+        remote_address,
+        local_signer.generate_address(&account, 0, false).unwrap()
+    );
+
+    // requests with another token are rejected
+    assert!(RemoteSigner::new(endpoint, "another token")
+        .generate_address(&account, 0, false)
+        .is_err());
+
+    let _ = std::fs::remove_file(socket_path);
+}